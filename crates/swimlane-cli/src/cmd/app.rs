@@ -0,0 +1,75 @@
+use std::path::{Path, PathBuf};
+
+use swimlane::SwimlaneClient;
+use swimlane_migrator::backend::MemoryBackend;
+use swimlane_migrator::native::{read_native_applications, write_native_application};
+use swimlane_migrator::server::SwimlaneServer;
+use swimlane_migrator::snapshot::Snapshot;
+use swimlane_migrator::{ResourceType, SwimlaneMigrator};
+
+use crate::config::Config;
+use crate::error::SwimlaneCliError;
+use crate::util::confirm;
+
+use super::migrate::dry_run_resource_migrate;
+
+/// Writes the applications to the path in the format the Swimlane UI exports them, one file
+/// per application
+pub async fn export_native_apps(
+    swimlane_client: &SwimlaneClient,
+    path: &Path,
+    app: &Option<String>,
+) -> Result<(), SwimlaneCliError> {
+    let apps = swimlane_client.get_applications().await?;
+    let apps = apps
+        .iter()
+        .filter(|a| app.is_none() || app.as_ref() == Some(&a.name));
+    for app in apps {
+        let file_path = write_native_application(path, app)?;
+        println!("Exported {} to {}", app.name, file_path.display());
+    }
+    Ok(())
+}
+
+/// Creates or updates the applications in the files, which were exported from the Swimlane UI
+pub async fn import_native_apps(
+    files: &[PathBuf],
+    target: SwimlaneServer,
+    dry_run: bool,
+    auto_approve: bool,
+    allow_destructive: bool,
+    config: &Config,
+) -> Result<(), SwimlaneCliError> {
+    let mut applications = vec![];
+    for file in files {
+        applications.extend(read_native_applications(file)?);
+    }
+    println!(
+        "Importing {} application(s) to {}",
+        applications.len(),
+        target.base_url
+    );
+
+    let source = MemoryBackend::new(
+        "import",
+        Snapshot {
+            applications,
+            ..Default::default()
+        },
+    );
+    let migrator = SwimlaneMigrator::new(source, target, dry_run)?
+        .with_diff_rules(config.diff.clone())
+        .with_allow_destructive(allow_destructive);
+
+    let plans = migrator.get_native_apps_to_import().await?;
+    dry_run_resource_migrate(ResourceType::Application, &plans);
+
+    if dry_run || plans.is_empty() {
+        return Ok(());
+    }
+    if !auto_approve && !confirm("Import these applications?") {
+        return Ok(());
+    }
+    migrator.import_native_apps(plans).await?;
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use std::path::{Path, PathBuf};
+
+use colored::Colorize;
+use swimlane::SwimlaneClient;
+use swimlane_migrator::backend::MemoryBackend;
+use swimlane_migrator::backup::Backup;
+use swimlane_migrator::server::SwimlaneServer;
+use swimlane_migrator::snapshot::{Snapshot, SnapshotError};
+
+use crate::config::Config;
+use crate::error::SwimlaneCliError;
+use crate::util::confirm;
+
+use super::commands::{reconcile, MigrateOptions};
+
+/// Writes every resource on the Swimlane server to a new archive in the directory
+pub async fn create_backup(
+    swimlane_client: &SwimlaneClient,
+    dir: &Path,
+) -> Result<(), SwimlaneCliError> {
+    println!("Backing up {}", swimlane_client.base_url);
+    let snapshot = Snapshot::fetch(swimlane_client).await?;
+    let backup = Backup::create(dir, &swimlane_client.base_url, &snapshot)?;
+    println!("Created {}", backup.path.display());
+    print_backup(&backup);
+    Ok(())
+}
+
+pub fn list_backups(dir: &Path) -> Result<(), SwimlaneCliError> {
+    let list = Backup::list(dir)?;
+    print_unreadable(&list.unreadable);
+    if list.backups.is_empty() {
+        println!("No backups in {}", dir.display());
+    }
+    for backup in &list.backups {
+        print_backup(backup);
+    }
+    Ok(())
+}
+
+/// Removes all but the newest `keep` backups in the directory
+pub fn prune_backups(dir: &Path, keep: usize) -> Result<(), SwimlaneCliError> {
+    let removed = Backup::prune(dir, keep)?;
+    print_unreadable(&removed.unreadable);
+    for backup in &removed.backups {
+        println!("Removed {}", backup.path.display());
+    }
+    println!("Removed {} backup(s)", removed.backups.len());
+    Ok(())
+}
+
+/// Reconciles the Swimlane server with the resources in the archive. A backup of a different
+/// Swimlane server is only restored once confirmed, or with `--force`
+pub async fn restore_backup(
+    archive: &Path,
+    target: SwimlaneServer,
+    options: &MigrateOptions,
+    config: &Config,
+) -> Result<(), SwimlaneCliError> {
+    let backup = Backup::open(archive)?;
+    println!(
+        "Restoring the backup of {} taken at {} to {}",
+        backup.manifest.url, backup.manifest.created, target.base_url
+    );
+    if backup.manifest.url != target.base_url {
+        println!(
+            "{}",
+            "The backup was taken of a different Swimlane server".yellow()
+        );
+        let confirmed = options.force
+            || options.dry_run
+            || (!options.auto_approve
+                && confirm(&format!(
+                    "Restore the backup of {} to {}?",
+                    backup.manifest.url, target.base_url
+                )));
+        if !confirmed {
+            return Err(SwimlaneCliError::BackupUrlMismatch(
+                backup.manifest.url,
+                target.base_url.clone(),
+            ));
+        }
+    }
+
+    let source = MemoryBackend::new(archive.display().to_string(), backup.snapshot()?);
+    reconcile(source, target, options, config).await
+}
+
+fn print_backup(backup: &Backup) {
+    let counts = backup
+        .manifest
+        .counts
+        .iter()
+        .map(|(resource, count)| format!("{} {}", count, resource))
+        .collect::<Vec<_>>()
+        .join(", ");
+    println!(
+        "{}  {}  {}\n  {}",
+        backup.manifest.created.format("%Y-%m-%d %H:%M:%S UTC"),
+        backup.manifest.url,
+        backup.path.display(),
+        counts
+    );
+}
+
+fn print_unreadable(unreadable: &[(PathBuf, SnapshotError)]) {
+    for (path, error) in unreadable {
+        println!(
+            "{}",
+            format!("Skipping {}, it can't be read: {}", path.display(), error).yellow()
+        );
+    }
+}
@@ -1,9 +1,17 @@
+use crate::config::Config;
 use crate::error::SwimlaneCliError;
+use crate::util::confirm;
 use crate::Migrate;
+use std::path::{Path, PathBuf};
 use swimlane::SwimlaneClient;
-use swimlane_migrator::SwimlaneMigrator;
+use swimlane_migrator::backend::Backend;
+use swimlane_migrator::baseline::Baseline;
+use swimlane_migrator::server::SwimlaneServer;
+use swimlane_migrator::snapshot::SnapshotDirectory;
+use swimlane_migrator::template::{TemplateValues, TemplatedBackend};
+use swimlane_migrator::{ResourceType, SwimlaneMigrator};
 
-use super::migrate::dry_run_resource_migrate;
+use super::migrate::{dry_run_resource_migrate, resolve_conflicts, FieldTypeChange};
 
 pub async fn remove_python_package(
     swimlane_client: &SwimlaneClient,
@@ -24,72 +32,247 @@ pub async fn freeze_python_packages(
     Ok(())
 }
 
+pub struct MigrateOptions {
+    pub dry_run: bool,
+    pub auto_approve: bool,
+    pub force: bool,
+    pub allow_destructive: bool,
+    pub field_type_change: FieldTypeChange,
+    pub baseline_dir: PathBuf,
+    /// The values of the source environment, replaced with placeholders when resources are read
+    pub source_values: TemplateValues,
+    /// The values of the target environment, substituted for placeholders when resources are read
+    pub values: TemplateValues,
+}
+
 pub async fn handle_migrate(
-    source: SwimlaneClient,
-    target: SwimlaneClient,
+    source: SwimlaneServer,
+    target: SwimlaneServer,
     migration_type: Migrate,
-    dry_run: bool,
+    options: &MigrateOptions,
+    config: &Config,
 ) -> Result<(), SwimlaneCliError> {
     println!(
         "Migrating from {} to {}",
         &source.base_url, &target.base_url
     );
 
-    let migrator = SwimlaneMigrator::new(source, target, dry_run)?;
+    let dry_run = options.dry_run;
+    let migrator = match options.source_values.is_empty() && options.values.is_empty() {
+        true => SwimlaneMigrator::new(source, target, dry_run)?,
+        false => {
+            let source = TemplatedBackend::new(
+                source,
+                options.source_values.clone(),
+                options.values.clone(),
+            );
+            SwimlaneMigrator::new(source, target, dry_run)?
+        }
+    }
+    .with_diff_rules(config.diff.clone())
+    .with_baseline(Baseline::new(&options.baseline_dir))
+    .with_allow_destructive(options.allow_destructive)
+    .with_field_type_change(options.field_type_change.into());
 
     if dry_run {
         println!("Dry run enabled, no changes will be made");
     }
 
     match migration_type {
-        Migrate::Users => match dry_run {
-            true => {
-                let users = migrator.get_users_to_migrate().await?;
-                dry_run_resource_migrate(users);
+        Migrate::Users => {
+            let users = migrator.get_users_to_migrate().await?;
+            match dry_run {
+                true => dry_run_resource_migrate(ResourceType::User, &users),
+                false => {
+                    let users = resolve_conflicts(
+                        ResourceType::User,
+                        users,
+                        options.force,
+                        options.auto_approve,
+                    );
+                    migrator.apply_users(users).await?
+                }
             }
-            false => migrator.migrate_users().await?,
-        },
+        }
         Migrate::User { user_id: _ } => {
             todo!();
         }
-        Migrate::Groups => match dry_run {
-            true => {
-                let groups = migrator.get_groups_to_migrate().await?;
-                dry_run_resource_migrate(groups);
+        Migrate::Groups => {
+            let groups = migrator.get_groups_to_migrate().await?;
+            match dry_run {
+                true => dry_run_resource_migrate(ResourceType::Group, &groups),
+                false => {
+                    let groups = resolve_conflicts(
+                        ResourceType::Group,
+                        groups,
+                        options.force,
+                        options.auto_approve,
+                    );
+                    migrator.apply_groups(groups).await?
+                }
             }
-            false => migrator.migrate_groups().await?,
-        },
+        }
         Migrate::Group { group_id: _ } => {
             todo!();
         }
-        Migrate::Roles => match dry_run {
-            true => {
-                let roles = migrator.get_roles_to_migrate().await?;
-                dry_run_resource_migrate(roles);
+        Migrate::Roles => {
+            let roles = migrator.get_roles_to_migrate().await?;
+            match dry_run {
+                true => dry_run_resource_migrate(ResourceType::Role, &roles),
+                false => {
+                    let roles = resolve_conflicts(
+                        ResourceType::Role,
+                        roles,
+                        options.force,
+                        options.auto_approve,
+                    );
+                    if !roles.is_empty() {
+                        println!("Roles can't be applied yet and will be skipped");
+                    }
+                    migrator.apply_roles(roles).await?
+                }
             }
-            false => migrator.migrate_roles().await?,
-        },
+        }
         Migrate::Role { role_id: _ } => {
             todo!();
         }
-        Migrate::Apps => match dry_run {
-            true => {
-                let apps = migrator.get_apps_to_migrate().await?;
-                dry_run_resource_migrate(apps);
-            }
-            false => {
-                // todo: prevent migrating apps where the acronym will change
-                migrator.migrate_apps().await?
+        Migrate::Apps => {
+            let apps = migrator.get_apps_to_migrate().await?;
+            match dry_run {
+                true => dry_run_resource_migrate(ResourceType::Application, &apps),
+                false => {
+                    // todo: prevent migrating apps where the acronym will change
+                    let apps = resolve_conflicts(
+                        ResourceType::Application,
+                        apps,
+                        options.force,
+                        options.auto_approve,
+                    );
+                    migrator.apply_apps(apps).await?
+                }
             }
-        },
+        }
         Migrate::App {
             application_name: _,
         } => {
             todo!();
         }
+        Migrate::Tasks => {
+            let tasks = migrator.get_tasks_to_migrate().await?;
+            match dry_run {
+                true => dry_run_resource_migrate(ResourceType::Task, &tasks),
+                false => {
+                    let tasks = resolve_conflicts(
+                        ResourceType::Task,
+                        tasks,
+                        options.force,
+                        options.auto_approve,
+                    );
+                    migrator.apply_tasks(tasks).await?
+                }
+            }
+        }
         Migrate::All => {
             todo!();
         }
     }
     Ok(())
 }
+
+/// Reconciles the Swimlane server with the configuration exported to the path, patched by each
+/// of the overlays in turn
+pub async fn handle_apply(
+    path: &Path,
+    overlays: &[PathBuf],
+    target: SwimlaneServer,
+    options: &MigrateOptions,
+    config: &Config,
+) -> Result<(), SwimlaneCliError> {
+    println!("Applying {} to {}", path.display(), &target.base_url);
+    for overlay in overlays {
+        println!("Patching with the overlay {}", overlay.display());
+    }
+
+    let source = TemplatedBackend::new(
+        SnapshotDirectory::open_with_overlays(path, overlays)?,
+        TemplateValues::default(),
+        options.values.clone(),
+    );
+    reconcile(source, target, options, config).await
+}
+
+/// Plans every resource, then applies the plans to the Swimlane server once confirmed
+pub async fn reconcile(
+    source: impl Backend + 'static,
+    target: SwimlaneServer,
+    options: &MigrateOptions,
+    config: &Config,
+) -> Result<(), SwimlaneCliError> {
+    let dry_run = options.dry_run;
+    let migrator = SwimlaneMigrator::new(source, target, dry_run)?
+        .with_diff_rules(config.diff.clone())
+        .with_baseline(Baseline::new(&options.baseline_dir))
+        .with_allow_destructive(options.allow_destructive)
+        .with_field_type_change(options.field_type_change.into());
+
+    let users = migrator.get_users_to_migrate().await?;
+    let groups = migrator.get_groups_to_migrate().await?;
+    let roles = migrator.get_roles_to_migrate().await?;
+    let apps = migrator.get_apps_to_migrate().await?;
+
+    if dry_run {
+        println!("Dry run enabled, no changes will be made");
+    }
+    let has_changes =
+        !(users.is_empty() && groups.is_empty() && roles.is_empty() && apps.is_empty());
+
+    println!("Users:");
+    dry_run_resource_migrate(ResourceType::User, &users);
+    println!("Groups:");
+    dry_run_resource_migrate(ResourceType::Group, &groups);
+    println!("Roles:");
+    dry_run_resource_migrate(ResourceType::Role, &roles);
+    println!("Apps:");
+    dry_run_resource_migrate(ResourceType::Application, &apps);
+
+    if dry_run || !has_changes {
+        return Ok(());
+    }
+    if !roles.is_empty() {
+        println!("Roles can't be applied yet and will be skipped");
+    }
+    if !options.auto_approve && !confirm("Apply these changes?") {
+        return Ok(());
+    }
+
+    let users = resolve_conflicts(
+        ResourceType::User,
+        users,
+        options.force,
+        options.auto_approve,
+    );
+    migrator.apply_users(users).await?;
+    let groups = resolve_conflicts(
+        ResourceType::Group,
+        groups,
+        options.force,
+        options.auto_approve,
+    );
+    migrator.apply_groups(groups).await?;
+    let apps = resolve_conflicts(
+        ResourceType::Application,
+        apps,
+        options.force,
+        options.auto_approve,
+    );
+    migrator.apply_apps(apps).await?;
+    let roles = resolve_conflicts(
+        ResourceType::Role,
+        roles,
+        options.force,
+        options.auto_approve,
+    );
+    migrator.apply_roles(roles).await?;
+
+    Ok(())
+}
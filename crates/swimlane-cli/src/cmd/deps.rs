@@ -0,0 +1,253 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use colored::Colorize;
+use swimlane::python::PipPackage;
+use swimlane::SwimlaneClient;
+
+use super::task::fetch_tasks;
+use crate::error::SwimlaneCliError;
+use crate::python::{distribution_name, is_stdlib, normalise_package_name, Script};
+
+/// The packages imported by the task scripts, cross-referenced with the packages installed on
+/// the server
+#[derive(Debug, Clone, Default)]
+pub struct DependencyReport {
+    /// The packages each task imports, keyed by `{folder}/{task}`
+    pub tasks: BTreeMap<String, BTreeSet<String>>,
+    /// Packages which are imported but not installed, with the tasks which import them
+    pub missing: BTreeMap<String, BTreeSet<String>>,
+    /// Installed packages which at least one task imports
+    pub used: Vec<PipPackage>,
+    /// Installed packages which no task imports
+    pub unused: Vec<PipPackage>,
+}
+
+impl DependencyReport {
+    /// `scripts` are the scripts of the tasks, keyed by `{folder}/{task}`
+    pub fn new(scripts: &BTreeMap<String, String>, installed: &[PipPackage]) -> Self {
+        let mut report = Self::default();
+        let mut used = BTreeSet::new();
+        for (task, script) in scripts {
+            let mut packages = BTreeSet::new();
+            for import in Script::parse(script).imports() {
+                if is_stdlib(&import.name) {
+                    continue;
+                }
+                match installed_package(&import.name, installed) {
+                    Some(package) => {
+                        used.insert(normalise_package_name(&package.name));
+                        packages.insert(package.name.clone());
+                    }
+                    None => {
+                        let package = distribution_name(&import.name).to_string();
+                        report
+                            .missing
+                            .entry(package.clone())
+                            .or_default()
+                            .insert(task.clone());
+                        packages.insert(package);
+                    }
+                }
+            }
+            if !packages.is_empty() {
+                report.tasks.insert(task.clone(), packages);
+            }
+        }
+
+        let mut installed = installed.to_vec();
+        installed.sort_by_key(|package| normalise_package_name(&package.name));
+        (report.used, report.unused) = installed
+            .into_iter()
+            .partition(|package| used.contains(&normalise_package_name(&package.name)));
+        report
+    }
+
+    /// The packages the tasks need, in the requirements.txt format. Installed packages are pinned
+    /// to their installed version.
+    pub fn requirements(&self) -> String {
+        let mut lines: Vec<String> = self
+            .used
+            .iter()
+            .map(|package| match &package.version {
+                Some(version) => format!("{}=={}", package.name, version),
+                None => package.name.clone(),
+            })
+            .chain(self.missing.keys().cloned())
+            .collect();
+        lines.sort_by_key(|line| line.to_lowercase());
+        lines.into_iter().map(|line| line + "\n").collect()
+    }
+
+    /// Keeps the tasks for which `keep` returns true, and the missing packages they import. The
+    /// used and unused packages still account for every task.
+    pub fn retain_tasks(&mut self, keep: impl Fn(&str) -> bool) {
+        self.tasks.retain(|task, _| keep(task));
+        for tasks in self.missing.values_mut() {
+            tasks.retain(|task| keep(task));
+        }
+        self.missing.retain(|_, tasks| !tasks.is_empty());
+    }
+}
+
+/// The installed package which provides the module, matched by the name of the package which
+/// usually provides it, or by the module's own name
+fn installed_package<'a>(module: &str, installed: &'a [PipPackage]) -> Option<&'a PipPackage> {
+    let names = [
+        normalise_package_name(distribution_name(module)),
+        normalise_package_name(module),
+    ];
+    installed
+        .iter()
+        .find(|package| names.contains(&normalise_package_name(&package.name)))
+}
+
+/// The scripts of the tasks, keyed by `{folder}/{task}`
+async fn fetch_scripts(
+    swimlane_client: &SwimlaneClient,
+    app: &Option<String>,
+) -> Result<BTreeMap<String, String>, SwimlaneCliError> {
+    let mut scripts = BTreeMap::new();
+    for (folder, tasks) in fetch_tasks(swimlane_client, app).await? {
+        for task in tasks {
+            if let Some(script) = task.action.script {
+                scripts.insert(format!("{}/{}", folder, task.name), script);
+            }
+        }
+    }
+    Ok(scripts)
+}
+
+/// Reports the packages the tasks on the server import, and optionally writes the packages they
+/// need to a requirements.txt file. The installed packages are checked against every task, even
+/// when only one application's tasks are listed.
+pub async fn report_task_dependencies(
+    swimlane_client: &SwimlaneClient,
+    app: &Option<String>,
+    requirements: &Option<PathBuf>,
+) -> Result<(), SwimlaneCliError> {
+    let scripts = fetch_scripts(swimlane_client, &None).await?;
+    let installed = swimlane_client.get_installed_pip_packages().await?;
+    let mut report = DependencyReport::new(&scripts, &installed);
+    let requirements = requirements
+        .as_ref()
+        .map(|path| (path, report.requirements()));
+    if app.is_some() {
+        let listed = fetch_scripts(swimlane_client, app).await?;
+        report.retain_tasks(|task| listed.contains_key(task));
+    }
+
+    println!("{}", "Packages imported by each task:".bold());
+    for (task, packages) in &report.tasks {
+        let packages: Vec<&str> = packages.iter().map(String::as_str).collect();
+        println!("  {}: {}", task, packages.join(", "));
+    }
+    if !report.missing.is_empty() {
+        println!("{}", "Imported, but not installed:".bold());
+        for (package, tasks) in &report.missing {
+            let tasks: Vec<&str> = tasks.iter().map(String::as_str).collect();
+            println!(
+                "  {} {}",
+                package.red(),
+                format!("(imported by {})", tasks.join(", ")).dimmed()
+            );
+        }
+    }
+    if !report.unused.is_empty() {
+        println!("{}", "Installed, but not imported by any task:".bold());
+        for package in &report.unused {
+            println!("  {}", package.name.yellow());
+        }
+    }
+    println!(
+        "{} task(s) import {} installed package(s) and {} missing package(s). {} installed package(s) aren't imported",
+        report.tasks.len(),
+        report.used.len(),
+        report.missing.len(),
+        report.unused.len()
+    );
+
+    if let Some((path, requirements)) = requirements {
+        std::fs::write(path, requirements)?;
+        println!(
+            "{}",
+            format!("Wrote the requirements to {}", path.display()).green()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dependency_report() {
+        let package = |name: &str, version: &str| PipPackage {
+            name: name.to_string(),
+            version: Some(version.to_string()),
+            python_version: "3".to_string(),
+            id: None,
+            requires: None,
+            license: None,
+            author_email: None,
+            author: None,
+            home_page: None,
+            summary: None,
+            disabled: None,
+        };
+        // `PipPackage` doesn't implement `PartialEq`, so packages are compared by name
+        let names = |packages: &[PipPackage]| {
+            packages
+                .iter()
+                .map(|package| package.name.clone())
+                .collect::<Vec<_>>()
+        };
+        let installed = vec![
+            package("requests", "2.31.0"),
+            package("PyYAML", "6.0.1"),
+            package("pandas", "2.1.0"),
+        ];
+        let scripts = BTreeMap::from([
+            (
+                "Phishing/Enrich URL".to_string(),
+                "import json\nimport requests\nfrom yaml import safe_load\n".to_string(),
+            ),
+            (
+                "common/Parse HTML".to_string(),
+                "from bs4 import BeautifulSoup\nimport requests.auth\n".to_string(),
+            ),
+            ("common/Hello".to_string(), "print('hello')\n".to_string()),
+        ]);
+
+        let report = DependencyReport::new(&scripts, &installed);
+
+        assert_eq!(
+            report.tasks["Phishing/Enrich URL"],
+            BTreeSet::from(["PyYAML".to_string(), "requests".to_string()])
+        );
+        assert!(!report.tasks.contains_key("common/Hello"));
+        assert_eq!(
+            report.missing,
+            BTreeMap::from([(
+                "beautifulsoup4".to_string(),
+                BTreeSet::from(["common/Parse HTML".to_string()])
+            )])
+        );
+        assert_eq!(names(&report.unused), vec!["pandas"]);
+        assert_eq!(
+            report.requirements(),
+            "beautifulsoup4\nPyYAML==6.0.1\nrequests==2.31.0\n"
+        );
+
+        let mut phishing = report.clone();
+        phishing.retain_tasks(|task| task.starts_with("Phishing/"));
+        assert_eq!(
+            phishing.tasks.keys().collect::<Vec<_>>(),
+            vec!["Phishing/Enrich URL"]
+        );
+        assert!(phishing.missing.is_empty());
+        assert_eq!(names(&phishing.used), names(&report.used));
+        assert_eq!(names(&phishing.unused), names(&report.unused));
+    }
+}
@@ -0,0 +1,143 @@
+use clap::ValueEnum;
+use colored::Colorize;
+use swimlane::SwimlaneClient;
+use swimlane_migrator::diff::{ResourceDiff, SwimlaneDiffer};
+use swimlane_migrator::ResourceType;
+
+use crate::config::Config;
+use crate::error::SwimlaneCliError;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum DiffResource {
+    Users,
+    Groups,
+    Roles,
+    Apps,
+    Workspaces,
+    Tasks,
+    All,
+}
+
+impl DiffResource {
+    fn resource_types(&self) -> Vec<ResourceType> {
+        match self {
+            DiffResource::Users => vec![ResourceType::User],
+            DiffResource::Groups => vec![ResourceType::Group],
+            DiffResource::Roles => vec![ResourceType::Role],
+            DiffResource::Apps => vec![ResourceType::Application],
+            DiffResource::Workspaces => vec![ResourceType::Workspace],
+            DiffResource::Tasks => vec![ResourceType::Task],
+            DiffResource::All => vec![
+                ResourceType::User,
+                ResourceType::Group,
+                ResourceType::Role,
+                ResourceType::Application,
+                ResourceType::Workspace,
+                ResourceType::Task,
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Markdown,
+}
+
+/// Compares the resources of two Swimlane servers. Neither server is modified.
+pub async fn handle_diff(
+    a: SwimlaneClient,
+    b: SwimlaneClient,
+    resource: DiffResource,
+    format: OutputFormat,
+    config: &Config,
+) -> Result<(), SwimlaneCliError> {
+    let a_url = a.base_url.clone();
+    let b_url = b.base_url.clone();
+    let differ = SwimlaneDiffer::new(a, b).with_diff_rules(config.diff.clone());
+
+    let mut diffs = vec![];
+    for resource_type in resource.resource_types() {
+        diffs.push(differ.diff(resource_type).await?);
+    }
+
+    match format {
+        OutputFormat::Table => print_table(&diffs, &a_url, &b_url),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&diffs)?),
+        OutputFormat::Markdown => print_markdown(&diffs, &a_url, &b_url),
+    }
+
+    Ok(())
+}
+
+fn print_table(diffs: &[ResourceDiff], a_url: &str, b_url: &str) {
+    println!("A: {}", a_url);
+    println!("B: {}", b_url);
+
+    for diff in diffs {
+        println!();
+        println!("{}", format!("{}s", diff.resource_type).bold());
+        if diff.is_empty() {
+            println!("{}", "  No differences".green());
+            continue;
+        }
+
+        for name in &diff.only_in_a {
+            println!("  {:<12} {}", "only in A".green(), name);
+        }
+        for name in &diff.only_in_b {
+            println!("  {:<12} {}", "only in B".red(), name);
+        }
+        for changed in &diff.changed {
+            println!("  {:<12} {}", "changed".yellow(), changed.name);
+            for difference in &changed.differences {
+                println!("  {:<12}   {}", "", difference);
+            }
+        }
+    }
+}
+
+fn print_markdown(diffs: &[ResourceDiff], a_url: &str, b_url: &str) {
+    println!("# Differences between A and B");
+    println!();
+    println!("- A: {}", a_url);
+    println!("- B: {}", b_url);
+
+    for diff in diffs {
+        println!();
+        println!("## {}s", diff.resource_type);
+        println!();
+        if diff.is_empty() {
+            println!("No differences");
+            continue;
+        }
+
+        println!("| Name | Status | Differences |");
+        println!("| --- | --- | --- |");
+        for name in &diff.only_in_a {
+            println!("| {} | Only in A | |", escape_markdown(name));
+        }
+        for name in &diff.only_in_b {
+            println!("| {} | Only in B | |", escape_markdown(name));
+        }
+        for changed in &diff.changed {
+            let differences = changed
+                .differences
+                .iter()
+                .map(|difference| escape_markdown(&difference.to_string()))
+                .collect::<Vec<_>>()
+                .join("<br>");
+            println!(
+                "| {} | Changed | {} |",
+                escape_markdown(&changed.name),
+                differences
+            );
+        }
+    }
+}
+
+fn escape_markdown(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
@@ -0,0 +1,67 @@
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use swimlane::SwimlaneClient;
+use swimlane_migrator::redact::RedactionPolicy;
+use swimlane_migrator::snapshot::{Snapshot, SnapshotFormat};
+use swimlane_migrator::template::TemplateValues;
+
+use crate::error::SwimlaneCliError;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Yaml,
+    Json,
+}
+
+impl From<ExportFormat> for SnapshotFormat {
+    fn from(value: ExportFormat) -> Self {
+        match value {
+            ExportFormat::Yaml => SnapshotFormat::Yaml,
+            ExportFormat::Json => SnapshotFormat::Json,
+        }
+    }
+}
+
+/// Writes the configuration of the Swimlane server to the path, one file per resource. With a
+/// base export, only the differences from it are written, as an overlay.
+pub async fn handle_export(
+    swimlane_client: &SwimlaneClient,
+    path: &Path,
+    base: &Option<PathBuf>,
+    format: ExportFormat,
+    redaction: &RedactionPolicy,
+    values: &TemplateValues,
+) -> Result<(), SwimlaneCliError> {
+    println!(
+        "Exporting {} to {}",
+        swimlane_client.base_url,
+        path.display()
+    );
+    let snapshot = Snapshot::fetch(swimlane_client)
+        .await?
+        .parameterise(values)?;
+    if !redaction.is_empty() {
+        println!("Redacting the fields listed in the configuration");
+    }
+    let snapshot = snapshot.redact(redaction)?;
+    if let Some(base) = base {
+        let files = snapshot.write_overlay(path, base, format.into())?;
+        println!(
+            "Exported the differences from {} as an overlay of {} file(s)",
+            base.display(),
+            files
+        );
+        return Ok(());
+    }
+    snapshot.write(path, format.into())?;
+    println!(
+        "Exported {} application(s), {} role(s), {} group(s), {} user(s) and {} workspace(s)",
+        snapshot.applications.len(),
+        snapshot.roles.len(),
+        snapshot.groups.len(),
+        snapshot.users.len(),
+        snapshot.workspaces.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,291 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use colored::Colorize;
+use serde_json::Value;
+use swimlane::apps::Application;
+use swimlane::SwimlaneClient;
+use swimlane_migrator::snapshot::file_name;
+use swimlane_migrator::tasks::sidecar_path;
+
+use super::task::{read_local_tasks, LocalTask};
+use crate::config::LintConfig;
+use crate::error::SwimlaneCliError;
+use crate::python::{distribution_name, is_stdlib, normalise_package_name, Location, Script};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintRule {
+    SyntaxError,
+    MissingPackage,
+    UnknownInput,
+    DeniedCall,
+}
+
+impl fmt::Display for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rule = match self {
+            LintRule::SyntaxError => "syntax-error",
+            LintRule::MissingPackage => "missing-package",
+            LintRule::UnknownInput => "unknown-input",
+            LintRule::DeniedCall => "denied-call",
+        };
+        write!(f, "{}", rule)
+    }
+}
+
+/// A problem found in a task script
+#[derive(Debug, Clone)]
+pub struct LintFinding {
+    pub path: PathBuf,
+    pub location: Location,
+    pub rule: LintRule,
+    pub message: String,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}: {}",
+            self.path.display(),
+            self.location.line,
+            self.location.column,
+            format!("error[{}]", self.rule).red(),
+            self.message
+        )
+    }
+}
+
+/// What the task scripts are checked against
+#[derive(Debug, Default)]
+pub struct LintContext {
+    /// The normalised names of the packages installed on the server
+    pub installed_packages: HashSet<String>,
+    /// The names and keys of each application's fields, keyed by the application's folder
+    pub fields: HashMap<String, HashSet<String>>,
+    /// The functions which mustn't be called, such as `eval` or `subprocess.*`
+    pub deny: Vec<String>,
+}
+
+impl LintContext {
+    fn new(
+        applications: &[Application],
+        installed_packages: &[String],
+        config: &LintConfig,
+    ) -> Self {
+        let fields = applications
+            .iter()
+            .map(|app| {
+                let names = app
+                    .fields
+                    .iter()
+                    .flat_map(|field| {
+                        let key = serde_json::to_value(field)
+                            .ok()
+                            .and_then(|field| field["key"].as_str().map(str::to_string));
+                        std::iter::once(field.name()).chain(key)
+                    })
+                    .collect();
+                (file_name(&app.name), names)
+            })
+            .collect();
+        Self {
+            installed_packages: installed_packages
+                .iter()
+                .map(|name| normalise_package_name(name))
+                .collect(),
+            fields,
+            deny: config.deny.clone(),
+        }
+    }
+
+    fn is_installed(&self, module: &str) -> bool {
+        is_stdlib(module)
+            || self
+                .installed_packages
+                .contains(&normalise_package_name(distribution_name(module)))
+            || self
+                .installed_packages
+                .contains(&normalise_package_name(module))
+    }
+
+    fn is_denied(&self, call: &str) -> bool {
+        self.deny
+            .iter()
+            .any(|pattern| match pattern.strip_suffix(".*") {
+                Some(module) => call.starts_with(&format!("{}.", module)),
+                None => call == pattern,
+            })
+    }
+}
+
+/// Checks the task's script for syntax errors, imports of packages which aren't installed,
+/// inputs which aren't fields of the task's application and calls to denied functions
+pub fn lint_task(
+    task: &LocalTask,
+    input_keys: &[String],
+    context: &LintContext,
+) -> Vec<LintFinding> {
+    let script = Script::parse(&task.script);
+    let finding = |location, rule, message| LintFinding {
+        path: task.path.clone(),
+        location,
+        rule,
+        message,
+    };
+    let mut findings = vec![];
+
+    for error in script.syntax_errors() {
+        findings.push(finding(error.location, LintRule::SyntaxError, error.name));
+    }
+    for import in script.imports() {
+        if !context.is_installed(&import.name) {
+            findings.push(finding(
+                import.location,
+                LintRule::MissingPackage,
+                format!(
+                    "'{}' is imported, but '{}' isn't installed on the instance",
+                    import.name,
+                    distribution_name(&import.name)
+                ),
+            ));
+        }
+    }
+    // Common tasks, and tasks of applications which no longer exist, have no fields to check
+    if let Some(fields) = context.fields.get(&task.folder) {
+        for key in script.input_keys() {
+            if !fields.contains(&key.name) && !input_keys.contains(&key.name) {
+                findings.push(finding(
+                    key.location,
+                    LintRule::UnknownInput,
+                    format!(
+                        "'{}' isn't an input of the task or a field of '{}'",
+                        key.name, task.folder
+                    ),
+                ));
+            }
+        }
+    }
+    for call in script.calls() {
+        if context.is_denied(&call.name) {
+            findings.push(finding(
+                call.location,
+                LintRule::DeniedCall,
+                format!("'{}' is on the deny-list", call.name),
+            ));
+        }
+    }
+
+    findings.sort_by_key(|finding| finding.location);
+    findings
+}
+
+/// The keys of the inputs mapped in the sidecar of the task script
+fn read_input_keys(script_path: &Path) -> Result<Vec<String>, SwimlaneCliError> {
+    let path = sidecar_path(script_path);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let sidecar: Value = serde_yaml::from_str(&std::fs::read_to_string(path)?)?;
+    let keys = sidecar["inputMapping"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|input| input["key"].as_str().map(str::to_string))
+        .collect();
+    Ok(keys)
+}
+
+/// Lints the task scripts saved to the path, printing each finding in the format
+/// `{path}:{line}:{column}: error[{rule}]: {message}`. Returns whether there are any findings.
+pub async fn lint_python_tasks(
+    swimlane_client: &SwimlaneClient,
+    path: &Path,
+    app: &Option<String>,
+    config: &LintConfig,
+) -> Result<bool, SwimlaneCliError> {
+    let local_tasks = read_local_tasks(path, app)?;
+    let installed_packages: Vec<String> = swimlane_client
+        .get_installed_pip_packages()
+        .await?
+        .into_iter()
+        .map(|package| package.name)
+        .collect();
+    let applications = swimlane_client.get_applications().await?;
+    let context = LintContext::new(&applications, &installed_packages, config);
+
+    let mut findings = 0;
+    let mut tasks_with_findings = 0;
+    for task in &local_tasks {
+        let task_findings = lint_task(task, &read_input_keys(&task.path)?, &context);
+        for finding in &task_findings {
+            println!("{}", finding);
+        }
+        if !task_findings.is_empty() {
+            findings += task_findings.len();
+            tasks_with_findings += 1;
+        }
+    }
+
+    if findings == 0 {
+        println!(
+            "{}",
+            format!("No problems found in {} task(s)", local_tasks.len()).green()
+        );
+        return Ok(false);
+    }
+    println!(
+        "{} problem(s) found in {} of {} task(s)",
+        findings,
+        tasks_with_findings,
+        local_tasks.len()
+    );
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_task() {
+        let task = LocalTask {
+            folder: "Phishing".to_string(),
+            name: "Enrich URL".to_string(),
+            path: PathBuf::from("Phishing/Enrich URL.py"),
+            script: [
+                "import json",
+                "import requests",
+                "import yaml",
+                "from subprocess import check_output",
+                "url = sw_context.inputs['URL']",
+                "ip = sw_context.inputs['ip']",
+                "host = sw_context.inputs['Hostname']",
+                "check_output(['curl', url])",
+            ]
+            .join("\n"),
+            manifest_entry: None,
+        };
+        let context = LintContext {
+            installed_packages: HashSet::from(["requests".to_string(), "pyyaml".to_string()]),
+            fields: HashMap::from([("Phishing".to_string(), HashSet::from(["URL".to_string()]))]),
+            deny: vec!["subprocess.*".to_string()],
+        };
+
+        let findings = lint_task(&task, &["ip".to_string()], &context);
+
+        let rules: Vec<(usize, LintRule)> = findings
+            .iter()
+            .map(|finding| (finding.location.line, finding.rule))
+            .collect();
+        assert_eq!(
+            rules,
+            vec![(7, LintRule::UnknownInput), (8, LintRule::DeniedCall)]
+        );
+        assert_eq!(
+            findings[1].to_string().replace("\u{1b}[31m", "").replace("\u{1b}[0m", ""),
+            "Phishing/Enrich URL.py:8:1: error[denied-call]: 'subprocess.check_output' is on the deny-list"
+        );
+    }
+}
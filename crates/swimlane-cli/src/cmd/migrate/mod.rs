@@ -1,12 +1,145 @@
-use std::fmt::Display;
-
+use clap::ValueEnum;
 use colored::Color;
 use colored::Colorize;
+use swimlane::apps::Application;
+use swimlane::groups::Group;
+use swimlane::roles::Role;
+use swimlane::tasks::Task;
+use swimlane::users::User;
+use swimlane_migrator::apps::FieldTypeChangeStrategy;
+use swimlane_migrator::baseline::Drift;
 use swimlane_migrator::equality::{Difference, LooksLike};
-use swimlane_migrator::MigrationPlan;
+use swimlane_migrator::risk::Risk;
+use swimlane_migrator::{MigrationPlan, ResourceType};
+
+use crate::util::{confirm, print_unified_diff};
+
+/// How application fields which kept their name but changed type are migrated
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum FieldTypeChange {
+    /// Refuse to migrate the application
+    Refuse,
+    /// Rename the existing field to keep its data, and create a new field with the new type
+    RenameAndCreate,
+}
+
+impl From<FieldTypeChange> for FieldTypeChangeStrategy {
+    fn from(value: FieldTypeChange) -> Self {
+        match value {
+            FieldTypeChange::Refuse => FieldTypeChangeStrategy::Refuse,
+            FieldTypeChange::RenameAndCreate => FieldTypeChangeStrategy::RenameAndCreate,
+        }
+    }
+}
+
+/// How a resource is named in the plans printed for it. Most resources are named by their
+/// `Display`, but tasks don't implement it.
+pub trait Describe {
+    fn describe(&self) -> String;
+}
+
+impl Describe for User {
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Describe for Group {
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Describe for Role {
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Describe for Application {
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Describe for Task {
+    fn describe(&self) -> String {
+        format!("Task: {}", self.name)
+    }
+}
+
+fn print_differences(resource_type: ResourceType, differences: &[Difference]) {
+    for difference in differences {
+        let risk = difference.risk(resource_type);
+        let line = format!("{} ({})", difference, risk);
+        if risk == Risk::Destructive {
+            println!("\t{}", line.red().bold());
+            continue;
+        }
+        let color = match difference {
+            Difference::UpdatingField { .. } => Color::Yellow,
+            Difference::AddingItem { .. } => Color::Green,
+            Difference::RemovingItem { .. } => Color::Red,
+            Difference::ChangingType { .. } => Color::Magenta,
+            Difference::UpdatingComplexField { .. } => Color::Yellow,
+            Difference::UpdatingScript { .. } => Color::Yellow,
+        };
+        println!("\t{}", line.color(color));
+        if let Difference::UpdatingScript {
+            current_script,
+            new_script,
+            ..
+        } = difference
+        {
+            print_unified_diff(new_script, current_script, "\t\t");
+        }
+    }
+}
+
+/// Resolves the plans which conflict with changes made directly on the target.
+/// Conflicts are overwritten when forced, skipped when auto-approving, and otherwise confirmed one by one.
+pub fn resolve_conflicts<T: LooksLike + Describe>(
+    resource_type: ResourceType,
+    plans: Vec<MigrationPlan<T>>,
+    force: bool,
+    auto_approve: bool,
+) -> Vec<MigrationPlan<T>> {
+    plans
+        .into_iter()
+        .filter_map(|plan| {
+            let overwrite = match &plan {
+                MigrationPlan::Conflict { drift, .. } => {
+                    let message = format!(
+                        "{} was {} since the last migration.",
+                        plan.resource().describe(),
+                        drift
+                    );
+                    if force {
+                        println!("{}", format!("{} Overwriting.", message).magenta());
+                        true
+                    } else if auto_approve {
+                        println!("{}", format!("{} Skipping.", message).magenta());
+                        false
+                    } else {
+                        println!("{}", message.magenta());
+                        if let Drift::Modified { differences } = drift {
+                            print_differences(resource_type, differences);
+                        }
+                        confirm("Overwrite it?")
+                    }
+                }
+                _ => true,
+            };
+            plan.resolve(overwrite)
+        })
+        .collect()
+}
 
 // todo: Move this to the Display trait for MigrationPlan?
-pub fn dry_run_resource_migrate<T: LooksLike + Display>(plans: Vec<MigrationPlan<T>>) {
+pub fn dry_run_resource_migrate<T: LooksLike + Describe>(
+    resource_type: ResourceType,
+    plans: &[MigrationPlan<T>],
+) {
     {
         if plans.is_empty() {
             println!(
@@ -18,36 +151,79 @@ pub fn dry_run_resource_migrate<T: LooksLike + Display>(plans: Vec<MigrationPlan
 
         plans.iter().for_each(|plan| {
             if let MigrationPlan::Delete { target_resource } = plan {
-                println!("{}", format!("{} will be deleted", target_resource).red())
+                println!(
+                    "{}",
+                    format!(
+                        "{} will be deleted ({})",
+                        target_resource.describe(),
+                        Risk::Destructive
+                    )
+                    .red()
+                    .bold()
+                )
             }
         });
 
         plans.iter().for_each(|plan| {
             if let MigrationPlan::Create { source_resource } = plan {
-                println!("{}", format!("{} will be created", source_resource).green())
+                println!(
+                    "{}",
+                    format!("{} will be created", source_resource.describe()).green()
+                )
             }
         });
 
         plans.iter().for_each(|plan| {
             if let MigrationPlan::Update {
                 source_resource,
-                target_resource,
+                differences,
+                ..
             } = plan
             {
+                let message = format!(
+                    "{} will be updated ({})",
+                    source_resource.describe(),
+                    plan.risk(resource_type)
+                );
+                match plan.risk(resource_type) {
+                    Risk::Destructive => println!("{}", message.red().bold()),
+                    _ => println!("{}", message.yellow()),
+                }
+                print_differences(resource_type, differences);
+            }
+        });
+
+        plans.iter().for_each(|plan| {
+            if let MigrationPlan::Conflict { drift, .. } = plan {
                 println!(
                     "{}",
-                    format!("{} will be updated", source_resource).yellow()
+                    format!(
+                        "{} was {} since the last migration and will not be changed without --force",
+                        plan.resource().describe(),
+                        drift
+                    )
+                    .magenta()
                 );
-                for difference in source_resource.differences(target_resource) {
-                    let color = match difference {
-                        Difference::UpdatingField { .. } => Color::Yellow,
-                        Difference::AddingItem { .. } => Color::Green,
-                        Difference::RemovingItem { .. } => Color::Red,
-                        Difference::UpdatingComplexField { .. } => Color::Yellow,
-                    };
-                    println!("\t{}", format!("{}", difference).color(color));
+                if let Drift::Modified { differences } = drift {
+                    print_differences(resource_type, differences);
                 }
             }
         });
+
+        let destructive = plans
+            .iter()
+            .filter(|plan| plan.risk(resource_type) == Risk::Destructive)
+            .count();
+        if destructive > 0 {
+            println!(
+                "{}",
+                format!(
+                    "{} destructive change(s) will destroy data on the target and require --allow-destructive",
+                    destructive
+                )
+                .red()
+                .bold()
+            );
+        }
     }
 }
@@ -1,3 +1,10 @@
+pub mod app;
+pub mod backup;
 pub mod commands;
-mod migrate;
+pub mod deps;
+pub mod diff;
+pub mod export;
+pub mod lint;
+pub mod migrate;
+pub mod run;
 pub mod task;
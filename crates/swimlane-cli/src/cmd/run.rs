@@ -0,0 +1,307 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use swimlane_migrator::tasks::sidecar_path;
+
+use crate::config::RunConfig;
+use crate::error::SwimlaneCliError;
+
+/// Runs the task script with an emulated `sw_context`, then writes its `sw_outputs` as JSON
+const HARNESS: &str = include_str!("run_task.py");
+
+/// The type of the input mappings which are read from the record
+const RECORD_TYPE: &str = "record";
+
+/// The type of the input mappings which are read from the asset
+const ASSET_TYPE: &str = "asset";
+
+/// The `sw_context` a task script is run with
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct TaskContext {
+    pub inputs: Map<String, Value>,
+    pub config: Map<String, Value>,
+    pub asset: Map<String, Value>,
+}
+
+impl TaskContext {
+    /// Builds the inputs of the task from the input mappings of its sidecar. Inputs mapped to a
+    /// field are read from the record, inputs of the asset are read from the asset, and other
+    /// inputs are given the mapping's value. Further mapping types can be read from the record or
+    /// asset with `run_config`. Returns the context, and a warning for each input which couldn't be found.
+    pub fn new(
+        sidecar: &Value,
+        record: Map<String, Value>,
+        asset: Map<String, Value>,
+        run_config: &RunConfig,
+    ) -> (Self, Vec<String>) {
+        let is_type = |mapping_type: &str, default: &str, configured: &[String]| {
+            mapping_type == default || configured.iter().any(|t| t == mapping_type)
+        };
+        let mut warnings = vec![];
+        let mut inputs = Map::new();
+        for mapping in sidecar["inputMapping"].as_array().into_iter().flatten() {
+            let Some(key) = mapping["key"].as_str() else {
+                continue;
+            };
+            let mapping_type = mapping["type"].as_str().unwrap_or_default();
+            let input = if is_type(mapping_type, RECORD_TYPE, &run_config.record_types) {
+                let field = mapping["value"].as_str().unwrap_or_default();
+                record.get(field).cloned().unwrap_or_else(|| {
+                    warnings.push(format!(
+                        "'{}' is mapped to the field '{}', which isn't in the record",
+                        key, field
+                    ));
+                    Value::Null
+                })
+            } else if is_type(mapping_type, ASSET_TYPE, &run_config.asset_types) {
+                asset.get(key).cloned().unwrap_or_else(|| {
+                    warnings.push(format!(
+                        "'{}' is read from the asset, which doesn't have it",
+                        key
+                    ));
+                    Value::Null
+                })
+            } else {
+                mapping["value"].clone()
+            };
+            inputs.insert(key.to_string(), input);
+        }
+
+        let config = Map::from_iter([
+            (
+                "ApplicationId".to_string(),
+                sidecar["applicationId"].clone(),
+            ),
+            (
+                "RecordId".to_string(),
+                record.get("id").cloned().unwrap_or(Value::Null),
+            ),
+        ]);
+        let context = Self {
+            inputs,
+            config,
+            asset,
+        };
+        (context, warnings)
+    }
+}
+
+/// Checks the task's outputs against the output mappings of its sidecar. Returns each output
+/// mapped to a field, by the field's name, along with the problems found.
+pub fn validate_outputs(sidecar: &Value, outputs: &Value) -> (Vec<(String, Value)>, Vec<String>) {
+    let mappings: Vec<(&str, &str)> = sidecar["outputs"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|output| output["mappings"].as_array().into_iter().flatten())
+        .filter_map(|mapping| Some((mapping["key"].as_str()?, mapping["value"].as_str()?)))
+        .collect();
+
+    let outputs = match outputs {
+        Value::Object(output) => vec![output],
+        Value::Array(items) if items.iter().all(Value::is_object) => {
+            items.iter().filter_map(Value::as_object).collect()
+        }
+        _ => {
+            return (
+                vec![],
+                vec!["sw_outputs must be a dict, or a list of dicts".to_string()],
+            )
+        }
+    };
+
+    let mut values = vec![];
+    let mut problems = vec![];
+    for output in &outputs {
+        for (key, value) in output.iter() {
+            match mappings.iter().find(|(mapped, _)| mapped == key) {
+                Some((_, field)) => values.push((field.to_string(), value.clone())),
+                None => problems.push(format!(
+                    "'{}' was output, but isn't mapped to a field, so would be discarded",
+                    key
+                )),
+            }
+        }
+    }
+    for (key, field) in &mappings {
+        if !outputs.iter().any(|output| output.contains_key(*key)) {
+            problems.push(format!(
+                "'{}' is mapped to the field '{}', but wasn't output",
+                key, field
+            ));
+        }
+    }
+    (values, problems)
+}
+
+fn read_json_object(path: &Option<PathBuf>) -> Result<Map<String, Value>, SwimlaneCliError> {
+    match path {
+        Some(path) => match serde_json::from_str(&std::fs::read_to_string(path)?)? {
+            Value::Object(object) => Ok(object),
+            _ => Err(SwimlaneCliError::NotAJsonObject(path.clone())),
+        },
+        None => Ok(Map::new()),
+    }
+}
+
+/// Runs the task script in the local python interpreter, with its inputs built from the sample
+/// record, then validates its outputs. Returns whether the task failed.
+pub fn run_python_task(
+    script_path: &Path,
+    record: &Option<PathBuf>,
+    asset: &Option<PathBuf>,
+    python: &str,
+    config: &RunConfig,
+) -> Result<bool, SwimlaneCliError> {
+    let sidecar = match sidecar_path(script_path).exists() {
+        true => serde_yaml::from_str(&std::fs::read_to_string(sidecar_path(script_path))?)?,
+        false => Value::Null,
+    };
+    let (context, warnings) = TaskContext::new(
+        &sidecar,
+        read_json_object(record)?,
+        read_json_object(asset)?,
+        config,
+    );
+    for warning in &warnings {
+        println!("{}", format!("warning: {}", warning).yellow());
+    }
+
+    let dir = std::env::temp_dir().join(format!("swimlane-cli-run-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let (harness, context_path, outputs_path) = (
+        dir.join("run_task.py"),
+        dir.join("context.json"),
+        dir.join("outputs.json"),
+    );
+    std::fs::write(&harness, HARNESS)?;
+    std::fs::write(&context_path, serde_json::to_string(&context)?)?;
+
+    println!("Running {} with {}", script_path.display(), python);
+    let status = Command::new(python)
+        .arg(&harness)
+        .arg(&context_path)
+        .arg(script_path)
+        .arg(&outputs_path)
+        .status()
+        .map_err(|e| SwimlaneCliError::PythonError(python.to_string(), e));
+    let outputs = std::fs::read_to_string(&outputs_path);
+    std::fs::remove_dir_all(&dir)?;
+
+    if !status?.success() {
+        println!("{}", "The task failed".red());
+        return Ok(true);
+    }
+    let (values, problems) = validate_outputs(&sidecar, &serde_json::from_str(&outputs?)?);
+    for (field, value) in &values {
+        println!("  {} = {}", field.green(), value);
+    }
+    for problem in &problems {
+        println!("{}", format!("error: {}", problem).red());
+    }
+    if problems.is_empty() {
+        println!("{}", "The task ran successfully".green());
+    }
+    Ok(!problems.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn sidecar() -> Value {
+        json!({
+            "applicationId": "Phishing",
+            "inputMapping": [
+                {"type": "record", "key": "url", "value": "URL"},
+                {"type": "record", "key": "host", "value": "Hostname"},
+                {"type": "asset", "key": "api_key", "value": "VirusTotal"},
+                {"type": "static", "key": "timeout", "value": "30"}
+            ],
+            "outputs": [{
+                "type": "update",
+                "mappings": [
+                    {"key": "score", "value": "Risk Score"},
+                    {"key": "verdict", "value": "Verdict"}
+                ]
+            }]
+        })
+    }
+
+    #[test]
+    fn test_task_context_is_built_from_the_record_and_asset() {
+        let record = json!({"id": "r1", "URL": "https://example.com"});
+        let asset = json!({"api_key": "secret"});
+
+        let (context, warnings) = TaskContext::new(
+            &sidecar(),
+            record.as_object().unwrap().clone(),
+            asset.as_object().unwrap().clone(),
+            &RunConfig::default(),
+        );
+
+        assert_eq!(
+            Value::Object(context.inputs),
+            json!({"url": "https://example.com", "host": null, "api_key": "secret", "timeout": "30"})
+        );
+        assert_eq!(
+            Value::Object(context.config),
+            json!({"ApplicationId": "Phishing", "RecordId": "r1"})
+        );
+        assert_eq!(
+            warnings,
+            vec!["'host' is mapped to the field 'Hostname', which isn't in the record"]
+        );
+    }
+
+    #[test]
+    fn test_configured_mapping_types_are_read_from_the_asset() {
+        let sidecar = json!({
+            "inputMapping": [{"type": "credentials", "key": "password", "value": "VirusTotal"}]
+        });
+        let asset = json!({"password": "secret"});
+        let context = |run_config: &RunConfig| {
+            let asset = asset.as_object().unwrap().clone();
+            TaskContext::new(&sidecar, Map::new(), asset, run_config).0
+        };
+
+        assert_eq!(
+            Value::Object(context(&RunConfig::default()).inputs),
+            json!({"password": "VirusTotal"})
+        );
+        let run_config = RunConfig {
+            asset_types: vec!["credentials".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            Value::Object(context(&run_config).inputs),
+            json!({"password": "secret"})
+        );
+    }
+
+    #[test]
+    fn test_outputs_are_validated_against_the_output_mappings() {
+        let (values, problems) =
+            validate_outputs(&sidecar(), &json!([{"score": 80, "extra": true}]));
+
+        assert_eq!(values, vec![("Risk Score".to_string(), json!(80))]);
+        assert_eq!(
+            problems,
+            vec![
+                "'extra' was output, but isn't mapped to a field, so would be discarded",
+                "'verdict' is mapped to the field 'Verdict', but wasn't output"
+            ]
+        );
+
+        let (_, problems) = validate_outputs(&sidecar(), &json!("score"));
+        assert_eq!(
+            problems,
+            vec!["sw_outputs must be a dict, or a list of dicts"]
+        );
+    }
+}
@@ -0,0 +1,22 @@
+"""Runs a task script with an emulated sw_context, then writes its sw_outputs as JSON.
+
+Usage: run_task.py CONTEXT_JSON SCRIPT OUTPUTS_JSON
+"""
+import json
+import sys
+
+
+class SwContext:
+    def __init__(self, context):
+        self.inputs = context["inputs"]
+        self.config = context["config"]
+        self.asset = context["asset"]
+
+
+with open(sys.argv[1]) as f:
+    context = json.load(f)
+namespace = {"__name__": "__main__", "sw_context": SwContext(context)}
+with open(sys.argv[2]) as f:
+    exec(compile(f.read(), sys.argv[2], "exec"), namespace)
+with open(sys.argv[3], "w") as f:
+    json.dump(namespace.get("sw_outputs", []), f, default=str)
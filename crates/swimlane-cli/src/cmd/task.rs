@@ -1,89 +1,116 @@
 use crate::error::SwimlaneCliError;
+use crate::util::{confirm, print_unified_diff};
+use colored::Colorize;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::create_dir;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use swimlane::apps::LightApplication;
 use swimlane::{tasks::Task, SwimlaneClient};
-use tokio::fs::File;
-use tokio::io::{self, AsyncWriteExt};
+use swimlane_migrator::server::SwimlaneServer;
+use swimlane_migrator::snapshot::{SnapshotError, COMMON_TASKS};
+use swimlane_migrator::tasks::{
+    application_folder_names, script_hash, sidecar_path, task_file_names, write_task,
+    write_task_definition, TaskManifest, TaskManifestEntry, TaskReferences,
+};
+
+/// How often `watch_python_tasks` checks the saved scripts for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The entries of the saved tasks in the manifest, keyed by the path of their file
+type ManifestEntries = Vec<(String, TaskManifestEntry)>;
 
 async fn download_tasks_to_folder(
     folder: &PathBuf,
     tasks: Vec<Task>,
-) -> Result<(), SwimlaneCliError> {
-    let downloadable_tasks = tasks
-        .into_iter()
-        .filter(|t| t.action.script.is_some())
-        .collect::<Vec<_>>();
-
-    if !folder.exists() && !downloadable_tasks.is_empty() {
+    references: TaskReferences,
+) -> Result<ManifestEntries, SwimlaneCliError> {
+    if !folder.exists() && !tasks.is_empty() {
         create_dir(folder)
             .unwrap_or_else(|_| panic!("Could not create folder: '{}'", folder.display()));
     }
 
+    let file_names = task_file_names(&tasks);
+    let folder_name = folder
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
     let mut handles = vec![];
 
-    for task in downloadable_tasks {
+    for task in tasks {
         let folder = folder.clone();
+        let references = references.clone();
+        let extension = match task.action.script {
+            Some(_) => "py",
+            None => "yaml",
+        };
+        let task_file_name = format!("{}.{}", file_names[&task.id], extension);
+        let key = TaskManifest::key(&folder_name, &task_file_name);
         let handle = tokio::spawn(async move {
             println!("Downloading task: '{}'", task.name);
-            save_task(&task, &folder)
-                .await
+            save_task(&task, &folder.join(task_file_name), &references)
                 .unwrap_or_else(|_| panic!("Could not save task: '{}'", task.name));
-            println!("Downloaded task: '{}'", task.name);
+            let entry = TaskManifestEntry::new(&task);
+            match &entry.forked_from {
+                Some(plugin) => {
+                    println!("Downloaded task: '{}', forked from {}", task.name, plugin)
+                }
+                None => println!("Downloaded task: '{}'", task.name),
+            }
+            (key, entry)
         });
         handles.push(handle);
     }
 
+    let mut entries = vec![];
     for handle in handles {
-        handle.await.unwrap();
+        entries.push(handle.await.unwrap());
     }
 
-    Ok(())
+    Ok(entries)
 }
 
+/// Downloads the tasks of the application to the folder, named by `application_folder_names`
 pub async fn download_tasks_for_application(
     swimlane_client: &SwimlaneClient,
     application: &LightApplication,
-    path: &impl AsRef<Path>,
-) -> Result<(), SwimlaneCliError> {
+    folder: &Path,
+    references: TaskReferences,
+) -> Result<ManifestEntries, SwimlaneCliError> {
     println!("Downloading tasks for application: '{}'", application.name);
     let tasks = swimlane_client
         .get_tasks_for_application(&application.id)
         .await?;
 
-    let folder = path.as_ref().join(&application.name);
-    download_tasks_to_folder(&folder, tasks).await
+    download_tasks_to_folder(&folder.to_path_buf(), tasks, references).await
 }
 
 pub async fn download_common_tasks(
     swimlane_client: &SwimlaneClient,
     path: &impl AsRef<Path>,
-) -> Result<(), SwimlaneCliError> {
+    references: TaskReferences,
+) -> Result<ManifestEntries, SwimlaneCliError> {
     println!("Downloading common tasks");
     let tasks = swimlane_client.get_common_tasks().await?;
 
-    let folder = path.as_ref().join("common");
-    download_tasks_to_folder(&folder, tasks).await
+    let folder = path.as_ref().join(COMMON_TASKS);
+    download_tasks_to_folder(&folder, tasks, references).await
 }
 
-async fn save_task(task: &Task, path: &impl AsRef<Path>) -> io::Result<()> {
+/// Saves the task's script to the path, and the rest of its definition to the sidecar next to
+/// it. A task without a script, such as a plugin action, is saved as its definition alone.
+fn save_task(task: &Task, path: &Path, references: &TaskReferences) -> Result<(), SnapshotError> {
     match &task.action.script {
-        Some(script) => {
-            let file_path = path.as_ref().join(format!("{}.py", task.name));
-            let mut file = File::create(&file_path).await?;
-            file.write_all(script.as_bytes())
-                .await
-                .unwrap_or_else(|_| panic!("Could not write to file: '{}'", &file_path.display()));
-            Ok(())
-        }
-        None => Err(io::Error::new(
-            io::ErrorKind::Other,
-            format!("Task '{}' has no script", task.name),
-        )),
+        Some(_) => write_task(path, task, references),
+        None => write_task_definition(path, task, references),
     }
 }
 
-/// Saves all python tasks to the specified path in the format '{application_name}/{task_name}.py'
+/// Saves all python tasks to the specified path in the format '{application_name}/{task_name}.py',
+/// alongside a '{task_name}.yaml' sidecar of the rest of each task's definition. Tasks without a
+/// script are saved as '{task_name}.yaml' alone. Names are made safe to use as file names, and
+/// the task each file belongs to is recorded in the manifest. The files of tasks which were
+/// renamed or deleted since they were saved are removed.
 pub async fn save_python_tasks(
     swimlane_client: &SwimlaneClient,
     path: &impl AsRef<Path>,
@@ -99,6 +126,8 @@ pub async fn save_python_tasks(
         .get_applications_light()
         .await
         .expect("Could not get applications");
+    // Folders are named from every application, so they're the same whichever are saved
+    let folder_names = application_folder_names(&applications);
 
     // If an application is specified, filter the applications to only include that application
     if let Some(app) = app {
@@ -108,40 +137,703 @@ pub async fn save_python_tasks(
             .collect::<Vec<_>>();
     }
 
+    // The full applications are needed to reference their fields by name in the sidecars
+    let full_applications = swimlane_client.get_applications().await?;
+
     let mut handles = vec![];
+    let mut saved_folders: Vec<String> = applications
+        .iter()
+        .map(|application| folder_names[&application.id].clone())
+        .collect();
 
     for application in applications.clone() {
         let sw = swimlane_client.clone();
-        let path = path.as_ref().to_path_buf();
+        let folder = path.as_ref().join(&folder_names[&application.id]);
+        let references = TaskReferences::new(&full_applications, Some(&application.id));
         let handle = tokio::spawn(async move {
-            download_tasks_for_application(&sw, &application, &path)
+            download_tasks_for_application(&sw, &application, &folder, references)
                 .await
                 .unwrap_or_else(|_| {
                     panic!(
                         "Could not download tasks for application: '{}'",
                         application.name
                     )
-                });
+                })
         });
         handles.push(handle);
     }
 
     // if common in applications
 
-    if app.is_none() || app == &Some("common".to_string()) {
+    if app.is_none() || app.as_deref() == Some(COMMON_TASKS) {
         let sw = swimlane_client.clone();
         let path = path.as_ref().to_path_buf();
+        let references = TaskReferences::new(&full_applications, None);
+        saved_folders.push(COMMON_TASKS.to_string());
         let handle = tokio::spawn(async move {
-            download_common_tasks(&sw, &path)
+            download_common_tasks(&sw, &path, references)
                 .await
-                .unwrap_or_else(|_| panic!("Could not download common tasks"));
+                .unwrap_or_else(|_| panic!("Could not download common tasks"))
         });
         handles.push(handle);
     }
 
+    let mut manifest = TaskManifest::read(path.as_ref())?;
+    let mut entries = vec![];
     for handle in handles {
-        handle.await.unwrap();
+        entries.extend(handle.await.unwrap());
+    }
+    for application in &applications {
+        manifest.folders.insert(
+            folder_names[&application.id].clone(),
+            application.name.clone(),
+        );
     }
+    // Every folder is saved without an application, so folders of deleted applications go too
+    let removed = update_manifest(
+        path.as_ref(),
+        &mut manifest,
+        |folder| app.is_none() || saved_folders.iter().any(|saved| saved == folder),
+        entries,
+    )?;
+    for file in removed {
+        println!("Removed '{}'", file.display());
+    }
+    manifest.write(path.as_ref())?;
 
     Ok(())
 }
+
+/// Replaces the manifest's entries in the saved folders with the entries of the tasks which were
+/// just saved. The files of the entries which were replaced, as their task was renamed or deleted,
+/// are removed, along with their folder once it's empty. Returns the removed files.
+fn update_manifest(
+    path: &Path,
+    manifest: &mut TaskManifest,
+    is_saved_folder: impl Fn(&str) -> bool,
+    entries: ManifestEntries,
+) -> Result<Vec<PathBuf>, SwimlaneCliError> {
+    let saved_keys: Vec<&String> = entries.iter().map(|(key, _)| key).collect();
+    let stale: Vec<String> = manifest
+        .tasks
+        .keys()
+        .filter(|key| is_saved_folder(TaskManifest::folder(key)) && !saved_keys.contains(key))
+        .cloned()
+        .collect();
+
+    let mut removed = vec![];
+    for key in stale {
+        manifest.tasks.remove(&key);
+        let file = path.join(&key);
+        let sidecar = sidecar_path(&file);
+        for file in [file, sidecar] {
+            if file.is_file() {
+                std::fs::remove_file(&file)?;
+                removed.push(file);
+            }
+        }
+        // Only succeeds once the folder is empty
+        let _ = std::fs::remove_dir(path.join(TaskManifest::folder(&key)));
+    }
+    manifest.tasks.extend(entries);
+    // Folders without any tasks left no longer need their application recorded
+    let folders: HashSet<String> = manifest
+        .tasks
+        .keys()
+        .map(|key| TaskManifest::folder(key).to_string())
+        .collect();
+    manifest
+        .folders
+        .retain(|folder, _| !is_saved_folder(folder) || folders.contains(folder));
+    Ok(removed)
+}
+
+/// A task script saved by `save_python_tasks`
+#[derive(Debug, Clone)]
+pub struct LocalTask {
+    /// The name of the task's application, or `common`, as a file name
+    pub folder: String,
+    pub name: String,
+    pub path: PathBuf,
+    pub script: String,
+    /// The script's entry in the manifest, if it has one
+    pub manifest_entry: Option<TaskManifestEntry>,
+}
+
+impl LocalTask {
+    /// Whether the task on the server is the task the script was saved from. Scripts without a
+    /// manifest entry are matched by their folder and name.
+    fn is_saved_from(&self, folder: &str, task: &Task) -> bool {
+        match &self.manifest_entry {
+            Some(entry) => entry.task_id == task.id,
+            None => self.folder == folder && self.name == task.name,
+        }
+    }
+
+    /// Whether the task's script on the server differs from the script when it was saved or last
+    /// pushed. Scripts without a manifest entry can't tell, so are never considered changed.
+    fn changed_on_server(&self, task: &Task) -> bool {
+        let remote_script = task.action.script.as_deref().unwrap_or_default();
+        self.manifest_entry
+            .as_ref()
+            .is_some_and(|entry| entry.script_hash != Some(script_hash(remote_script)))
+    }
+}
+
+/// Reads the task scripts saved to the path in the format '{application_name}/{task_name}.py'
+pub fn read_local_tasks(
+    path: &Path,
+    app: &Option<String>,
+) -> Result<Vec<LocalTask>, SnapshotError> {
+    let manifest = TaskManifest::read(path)?;
+    let mut tasks = vec![];
+    for folder in std::fs::read_dir(path)? {
+        let folder = folder?;
+        let folder_name = folder.file_name().to_string_lossy().to_string();
+        let other_app = app
+            .as_ref()
+            .is_some_and(|a| !manifest.is_folder_of(&folder_name, a));
+        if !folder.file_type()?.is_dir() || other_app {
+            continue;
+        }
+        for file in std::fs::read_dir(folder.path())? {
+            let file_path = file?.path();
+            if file_path.extension().and_then(|e| e.to_str()) != Some("py") {
+                continue;
+            }
+            let Some(name) = file_path.file_stem() else {
+                continue;
+            };
+            let script_file_name = file_path.file_name().unwrap_or_default().to_string_lossy();
+            let manifest_entry = manifest
+                .tasks
+                .get(&TaskManifest::key(&folder_name, &script_file_name))
+                .cloned();
+            tasks.push(LocalTask {
+                folder: folder_name.clone(),
+                name: manifest_entry
+                    .as_ref()
+                    .map(|entry| entry.name.clone())
+                    .unwrap_or_else(|| name.to_string_lossy().to_string()),
+                script: std::fs::read_to_string(&file_path)?,
+                path: file_path,
+                manifest_entry,
+            });
+        }
+    }
+    tasks.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(tasks)
+}
+
+/// Fetches the tasks on the server, keyed by the folder `save_python_tasks` saves them to
+pub async fn fetch_tasks(
+    swimlane_client: &SwimlaneClient,
+    app: &Option<String>,
+) -> Result<BTreeMap<String, Vec<Task>>, SwimlaneCliError> {
+    let mut tasks = BTreeMap::new();
+    let applications = swimlane_client.get_applications_light().await?;
+    let folder_names = application_folder_names(&applications);
+    for application in applications
+        .iter()
+        .filter(|a| app.is_none() || app.as_ref() == Some(&a.name))
+    {
+        let app_tasks = swimlane_client
+            .get_tasks_for_application(&application.id)
+            .await?;
+        tasks.insert(folder_names[&application.id].clone(), app_tasks);
+    }
+    if app.is_none() || app.as_deref() == Some(COMMON_TASKS) {
+        tasks.insert(
+            COMMON_TASKS.to_string(),
+            swimlane_client.get_common_tasks().await?,
+        );
+    }
+    Ok(tasks)
+}
+
+/// Prints a unified diff from the server's script to the local script
+pub fn print_script_diff(task: &str, remote: &str, local: &str) {
+    println!("{}", format!("--- {} (server)", task).red());
+    println!("{}", format!("+++ {} (local)", task).green());
+    print_unified_diff(remote, local, "");
+}
+
+/// The differences between the task scripts saved to a path and the tasks on the server
+#[derive(Debug, Default)]
+pub struct TaskComparison {
+    /// Saved tasks whose script differs from the server's, with the task on the server
+    pub modified: Vec<(LocalTask, Task)>,
+    /// Saved tasks with no task of the same name on the server
+    pub only_local: Vec<LocalTask>,
+    /// Python tasks on the server which haven't been saved, with their folder
+    pub only_remote: Vec<(String, Task)>,
+}
+
+impl TaskComparison {
+    pub fn is_empty(&self) -> bool {
+        self.modified.is_empty() && self.only_local.is_empty() && self.only_remote.is_empty()
+    }
+}
+
+/// Matches the saved task scripts to the tasks on the server by their folder and name
+pub fn compare_tasks(
+    local_tasks: Vec<LocalTask>,
+    remote_tasks: &BTreeMap<String, Vec<Task>>,
+) -> TaskComparison {
+    let mut comparison = TaskComparison::default();
+    for (folder, tasks) in remote_tasks {
+        for task in tasks.iter().filter(|t| t.action.script.is_some()) {
+            let saved = local_tasks
+                .iter()
+                .any(|local| local.is_saved_from(folder, task));
+            if !saved {
+                comparison.only_remote.push((folder.clone(), task.clone()));
+            }
+        }
+    }
+    for local in local_tasks {
+        let remote = remote_tasks.iter().find_map(|(folder, tasks)| {
+            tasks.iter().find(|task| local.is_saved_from(folder, task))
+        });
+        match remote {
+            Some(remote) if remote.action.script.as_deref() != Some(local.script.as_str()) => {
+                comparison.modified.push((local, remote.clone()))
+            }
+            Some(_) => {}
+            None => comparison.only_local.push(local),
+        }
+    }
+    comparison
+}
+
+/// Compares the task scripts saved to the path with the scripts on the server, printing a
+/// unified diff of each modified task. Returns whether there are any differences.
+pub async fn diff_python_tasks(
+    swimlane_client: &SwimlaneClient,
+    path: &Path,
+    app: &Option<String>,
+) -> Result<bool, SwimlaneCliError> {
+    let local_tasks = read_local_tasks(path, app)?;
+    let remote_tasks = fetch_tasks(swimlane_client, app).await?;
+    let comparison = compare_tasks(local_tasks, &remote_tasks);
+
+    if comparison.is_empty() {
+        println!("{}", "No differences".green());
+        return Ok(false);
+    }
+    for (local, remote) in &comparison.modified {
+        print_script_diff(
+            &format!("{}/{}", local.folder, local.name),
+            remote.action.script.as_deref().unwrap_or_default(),
+            &local.script,
+        );
+    }
+    if !comparison.only_local.is_empty() {
+        println!("{}", "Only local:".bold());
+        for local in &comparison.only_local {
+            println!("{}", format!("  + {}/{}", local.folder, local.name).green());
+        }
+    }
+    if !comparison.only_remote.is_empty() {
+        println!("{}", "Only on the server:".bold());
+        for (folder, task) in &comparison.only_remote {
+            println!("{}", format!("  - {}/{}", folder, task.name).red());
+        }
+    }
+    println!(
+        "{} modified, {} only local, {} only on the server",
+        comparison.modified.len(),
+        comparison.only_local.len(),
+        comparison.only_remote.len()
+    );
+    Ok(true)
+}
+
+/// Uploads the task scripts saved to the path which differ from the scripts on the server.
+/// Scripts are matched to tasks through the manifest, or by their application and name.
+/// Tasks whose script changed on the server since it was saved are skipped, unless forced.
+pub async fn push_python_tasks(
+    swimlane_client: &SwimlaneServer,
+    path: &Path,
+    app: &Option<String>,
+    dry_run: bool,
+    auto_approve: bool,
+    force: bool,
+) -> Result<(), SwimlaneCliError> {
+    let local_tasks = read_local_tasks(path, app)?;
+    let remote_tasks = fetch_tasks(swimlane_client, app).await?;
+    let comparison = compare_tasks(local_tasks, &remote_tasks);
+
+    for local in &comparison.only_local {
+        println!(
+            "{}",
+            format!(
+                "Skipping '{}', as there is no task named '{}' in '{}'",
+                local.path.display(),
+                local.name,
+                local.folder
+            )
+            .yellow()
+        );
+    }
+    let mut changed = vec![];
+    for (local, remote) in comparison.modified {
+        if local.changed_on_server(&remote) && !force {
+            println!(
+                "{}",
+                format!(
+                    "Skipping '{}', as the task was changed on the server since it was saved",
+                    local.path.display()
+                )
+                .yellow()
+            );
+            continue;
+        }
+        changed.push((local, remote));
+    }
+    for (local, remote) in &changed {
+        print_script_diff(
+            &format!("{}/{}", local.folder, local.name),
+            remote.action.script.as_deref().unwrap_or_default(),
+            &local.script,
+        );
+    }
+
+    if changed.is_empty() {
+        println!("No tasks have changed");
+        return Ok(());
+    }
+    println!("{} task(s) to push", changed.len());
+    if dry_run {
+        println!("Dry run enabled, no changes will be made");
+        return Ok(());
+    }
+    if !auto_approve && !confirm("Push these tasks?") {
+        return Ok(());
+    }
+
+    let mut manifest = TaskManifest::read(path)?;
+    for (local, mut task) in changed {
+        let script_file_name = local.path.file_name().unwrap_or_default().to_string_lossy();
+        if let Some(entry) = manifest
+            .tasks
+            .get_mut(&TaskManifest::key(&local.folder, &script_file_name))
+        {
+            entry.script_hash = Some(script_hash(&local.script));
+        }
+        task.action.script = Some(local.script);
+        swimlane_client.update_task(&task).await?;
+        println!("Pushed task: '{}'", task.name);
+    }
+    if !manifest.tasks.is_empty() {
+        manifest.write(path)?;
+    }
+    Ok(())
+}
+
+/// Watches the task scripts saved to the path, and pushes each script to its task on the server
+/// whenever it's saved. Tasks whose script changed on the server since it was saved are skipped,
+/// as with `push_python_tasks`. Prints a line for each upload, and runs until it's interrupted.
+pub async fn watch_python_tasks(
+    swimlane_client: &SwimlaneServer,
+    path: &Path,
+    app: &Option<String>,
+) -> Result<(), SwimlaneCliError> {
+    let mut scripts: HashMap<PathBuf, String> = read_local_tasks(path, app)?
+        .into_iter()
+        .map(|local| (local.path, local.script))
+        .collect();
+    println!(
+        "Watching {} task(s) in '{}', pushing to {}",
+        scripts.len(),
+        path.display(),
+        swimlane_client.base_url
+    );
+
+    loop {
+        tokio::time::sleep(WATCH_INTERVAL).await;
+        let local_tasks = match read_local_tasks(path, app) {
+            Ok(local_tasks) => local_tasks,
+            Err(e) => {
+                println!("{}", format!("Could not read the tasks: {}", e).red());
+                continue;
+            }
+        };
+        let changed: Vec<LocalTask> = local_tasks
+            .into_iter()
+            .filter(|local| scripts.get(&local.path) != Some(&local.script))
+            .collect();
+        if changed.is_empty() {
+            continue;
+        }
+        // The tasks are fetched again, so changes made on the server since are noticed
+        let remote_tasks = match fetch_tasks(swimlane_client, app).await {
+            Ok(remote_tasks) => remote_tasks,
+            Err(e) => {
+                println!("{}", format!("Could not fetch the tasks: {}", e).red());
+                continue;
+            }
+        };
+        for local in changed {
+            scripts.insert(local.path.clone(), local.script.clone());
+            push_watched_task(swimlane_client, path, &local, &remote_tasks).await;
+        }
+    }
+}
+
+/// Pushes the script to its task on the server, and records its hash in the manifest. Failures
+/// are printed, so watching carries on.
+async fn push_watched_task(
+    swimlane_client: &SwimlaneServer,
+    path: &Path,
+    local: &LocalTask,
+    remote_tasks: &BTreeMap<String, Vec<Task>>,
+) {
+    let task_name = format!("{}/{}", local.folder, local.name);
+    let remote = remote_tasks
+        .iter()
+        .find_map(|(folder, tasks)| tasks.iter().find(|task| local.is_saved_from(folder, task)));
+    let Some(remote) = remote else {
+        println!(
+            "{}",
+            format!("Could not push '{}': there is no such task", task_name).red()
+        );
+        return;
+    };
+    if local.changed_on_server(remote) {
+        println!(
+            "{}",
+            format!(
+                "Skipping '{}', as the task was changed on the server since it was saved",
+                task_name
+            )
+            .yellow()
+        );
+        return;
+    }
+
+    let mut task = remote.clone();
+    task.action.script = Some(local.script.clone());
+    if let Err(e) = swimlane_client.update_task(&task).await {
+        println!("{}", format!("Could not push '{}': {}", task_name, e).red());
+        return;
+    }
+    println!("{}", format!("Pushed '{}'", task_name).green());
+
+    let script_file_name = local.path.file_name().unwrap_or_default().to_string_lossy();
+    let recorded = TaskManifest::read(path).and_then(|mut manifest| {
+        match manifest
+            .tasks
+            .get_mut(&TaskManifest::key(&local.folder, &script_file_name))
+        {
+            Some(entry) => {
+                entry.script_hash = Some(script_hash(&local.script));
+                manifest.write(path)
+            }
+            None => Ok(()),
+        }
+    });
+    if let Err(e) = recorded {
+        println!(
+            "{}",
+            format!("Could not record '{}' in the manifest: {}", task_name, e).red()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Python task, as `Task` doesn't implement `Default`
+    fn task(id: &str, name: &str) -> Task {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": name,
+            "valid": true,
+            "disabled": false,
+            "action": {
+                "readonly": false,
+                "type": "script",
+                "descriptor": {
+                    "id": id,
+                    "name": name,
+                    "disabled": false,
+                    "actionType": "python",
+                    "base64Image": ""
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_read_local_tasks_filters_by_application() {
+        let path = std::env::temp_dir().join(format!("swimlane-tasks-{}", std::process::id()));
+        for (folder, name) in [("Phishing", "Enrich URL"), (COMMON_TASKS, "Send Email")] {
+            std::fs::create_dir_all(path.join(folder)).unwrap();
+            std::fs::write(path.join(folder).join(format!("{}.py", name)), "pass\n").unwrap();
+        }
+        std::fs::write(path.join("Phishing").join("notes.txt"), "").unwrap();
+
+        let all = read_local_tasks(&path, &None).unwrap();
+        let phishing = read_local_tasks(&path, &Some("Phishing".to_string())).unwrap();
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert_eq!(all.len(), 2);
+        assert_eq!(phishing.len(), 1);
+        assert_eq!(phishing[0].name, "Enrich URL");
+        assert_eq!(phishing[0].script, "pass\n");
+    }
+
+    #[test]
+    fn test_read_local_tasks_uses_the_manifest() {
+        let path = std::env::temp_dir().join(format!("swimlane-manifest-{}", std::process::id()));
+        std::fs::create_dir_all(path.join("Phishing")).unwrap();
+        std::fs::write(path.join("Phishing").join("Enrich_URL.py"), "pass\n").unwrap();
+        let mut task = task("t1", "Enrich/URL");
+        task.action.script = Some("pass\n".to_string());
+        let manifest = TaskManifest {
+            tasks: BTreeMap::from([(
+                TaskManifest::key("Phishing", "Enrich_URL.py"),
+                TaskManifestEntry::new(&task),
+            )]),
+            ..Default::default()
+        };
+        manifest.write(&path).unwrap();
+
+        let local_tasks = read_local_tasks(&path, &None).unwrap();
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert_eq!(local_tasks[0].name, "Enrich/URL");
+        let remote_tasks = BTreeMap::from([("Phishing".to_string(), vec![task])]);
+        assert!(compare_tasks(local_tasks, &remote_tasks).is_empty());
+    }
+
+    #[test]
+    fn test_read_local_tasks_filters_by_the_folder_of_the_application() {
+        let path = std::env::temp_dir().join(format!("swimlane-folders-{}", std::process::id()));
+        for folder in ["Phishing", "Phishing (2)"] {
+            std::fs::create_dir_all(path.join(folder)).unwrap();
+            std::fs::write(path.join(folder).join("Enrich URL.py"), folder).unwrap();
+        }
+        let manifest = TaskManifest {
+            folders: BTreeMap::from([
+                ("Phishing".to_string(), "Phishing".to_string()),
+                ("Phishing (2)".to_string(), "phishing".to_string()),
+            ]),
+            ..Default::default()
+        };
+        manifest.write(&path).unwrap();
+
+        let local_tasks = read_local_tasks(&path, &Some("phishing".to_string())).unwrap();
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert_eq!(local_tasks.len(), 1);
+        assert_eq!(local_tasks[0].folder, "Phishing (2)");
+    }
+
+    #[test]
+    fn test_update_manifest_removes_renamed_and_deleted_tasks() {
+        let path = std::env::temp_dir().join(format!("swimlane-prune-{}", std::process::id()));
+        let entry = |id: &str, name: &str| {
+            let mut task = task(id, name);
+            task.action.script = Some("pass\n".to_string());
+            TaskManifestEntry::new(&task)
+        };
+        let files = [
+            ("Phishing", "Old Name.py"),
+            ("Phishing", "Old Name.yaml"),
+            ("Phishing", "New Name.py"),
+            ("Phishing", "Untracked.py"),
+            ("Deleted App", "Enrich URL.py"),
+            ("Malware", "Scan.py"),
+        ];
+        for (folder, file) in files {
+            std::fs::create_dir_all(path.join(folder)).unwrap();
+            std::fs::write(path.join(folder).join(file), "pass\n").unwrap();
+        }
+        let mut manifest = TaskManifest {
+            tasks: BTreeMap::from([
+                (
+                    TaskManifest::key("Phishing", "Old Name.py"),
+                    entry("t1", "Old Name"),
+                ),
+                (
+                    TaskManifest::key("Deleted App", "Enrich URL.py"),
+                    entry("t2", "Enrich URL"),
+                ),
+                (TaskManifest::key("Malware", "Scan.py"), entry("t3", "Scan")),
+            ]),
+            folders: BTreeMap::from([("Deleted App".to_string(), "Deleted App".to_string())]),
+        };
+
+        let removed = update_manifest(
+            &path,
+            &mut manifest,
+            |folder| folder != "Malware",
+            vec![(
+                TaskManifest::key("Phishing", "New Name.py"),
+                entry("t1", "New Name"),
+            )],
+        )
+        .unwrap();
+        let exists = |folder: &str, file: &str| path.join(folder).join(file).exists();
+        let (old, sidecar, new, untracked, deleted_app, other_app) = (
+            exists("Phishing", "Old Name.py"),
+            exists("Phishing", "Old Name.yaml"),
+            exists("Phishing", "New Name.py"),
+            exists("Phishing", "Untracked.py"),
+            path.join("Deleted App").exists(),
+            exists("Malware", "Scan.py"),
+        );
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert_eq!(removed.len(), 3);
+        assert!(!old && !sidecar && !deleted_app);
+        assert!(new && untracked && other_app);
+        assert_eq!(
+            manifest.tasks.keys().collect::<Vec<_>>(),
+            ["Malware/Scan.py", "Phishing/New Name.py"]
+        );
+        assert!(manifest.folders.is_empty());
+    }
+
+    #[test]
+    fn test_compare_tasks() {
+        let local = |name: &str, script: &str| LocalTask {
+            folder: "Phishing".to_string(),
+            name: name.to_string(),
+            path: PathBuf::from(format!("Phishing/{}.py", name)),
+            script: script.to_string(),
+            manifest_entry: None,
+        };
+        let remote = |name: &str, script: Option<&str>| {
+            let mut task = task(name, name);
+            task.action.script = script.map(str::to_string);
+            task
+        };
+        let remote_tasks = BTreeMap::from([(
+            "Phishing".to_string(),
+            vec![
+                remote("Unchanged", Some("pass")),
+                remote("Edited", Some("pass")),
+                remote("Remote", Some("pass")),
+                remote("Plugin", None),
+            ],
+        )]);
+        let local_tasks = vec![
+            local("Unchanged", "pass"),
+            local("Edited", "print()"),
+            local("Local", "pass"),
+        ];
+
+        let comparison = compare_tasks(local_tasks, &remote_tasks);
+
+        assert_eq!(comparison.modified.len(), 1);
+        assert_eq!(comparison.modified[0].1.name, "Edited");
+        assert_eq!(comparison.only_local[0].name, "Local");
+        assert_eq!(comparison.only_remote.len(), 1);
+        assert_eq!(comparison.only_remote[0].1.name, "Remote");
+    }
+}
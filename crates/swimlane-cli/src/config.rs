@@ -0,0 +1,127 @@
+use std::path::Path;
+
+use serde::Deserialize;
+use swimlane_migrator::redact::RedactionPolicy;
+use swimlane_migrator::rules::DiffRules;
+
+use crate::error::SwimlaneCliError;
+
+/// Settings read from the file passed with `--config`
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    /// Differences to ignore or normalise, per resource type
+    #[serde(default)]
+    pub diff: DiffRules,
+    /// Fields which are redacted from exports, per resource type
+    #[serde(default)]
+    pub redact: RedactionPolicy,
+    /// The environment the configuration is for, such as `dev` or `prod`
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Settings for `task watch`
+    #[serde(default)]
+    pub watch: WatchConfig,
+    /// Settings for `task lint`
+    #[serde(default)]
+    pub lint: LintConfig,
+    /// Settings for the environment values passed with `--values`
+    #[serde(default)]
+    pub values: ValuesConfig,
+    /// Settings for `task run`
+    #[serde(default)]
+    pub run: RunConfig,
+}
+
+/// The profiles of production environments, which `task watch` refuses to push to
+const PRODUCTION_PROFILES: &[&str] = &["prod", "production"];
+
+#[derive(Debug, Default, Deserialize)]
+pub struct WatchConfig {
+    /// The instance `task watch` pushes to, which must be the instance passed with `--url`
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LintConfig {
+    /// Functions which task scripts mustn't call, such as `eval`. A trailing `.*` denies every
+    /// function of a module, such as `subprocess.*`
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ValuesConfig {
+    /// The keys of the strings values are replaced in, in addition to `script`, `description`
+    /// and `url`
+    #[serde(default)]
+    pub keys: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RunConfig {
+    /// The types of input mappings which are read from the record, in addition to `record`
+    #[serde(default)]
+    pub record_types: Vec<String>,
+    /// The types of input mappings which are read from the asset, in addition to `asset`
+    #[serde(default)]
+    pub asset_types: Vec<String>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, SwimlaneCliError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| SwimlaneCliError::ConfigReadError(path.to_path_buf(), e))?;
+        let config = serde_yaml::from_str(&contents)?;
+        Ok(config)
+    }
+
+    /// Checks that `task watch` may push to the instance at the URL. The instance must be the
+    /// configured watch instance, and the profile mustn't be a production profile.
+    pub fn check_watch_target(&self, url: &str) -> Result<(), SwimlaneCliError> {
+        if let Some(profile) = &self.profile {
+            let production = PRODUCTION_PROFILES
+                .iter()
+                .any(|p| p.eq_ignore_ascii_case(profile));
+            if production {
+                return Err(SwimlaneCliError::WatchProductionProfile(profile.clone()));
+            }
+        }
+        match &self.watch.url {
+            Some(watch_url) if watch_url.trim_end_matches('/') == url.trim_end_matches('/') => {
+                Ok(())
+            }
+            Some(watch_url) => Err(SwimlaneCliError::WatchUrlMismatch(
+                watch_url.clone(),
+                url.to_string(),
+            )),
+            None => Err(SwimlaneCliError::WatchNotConfigured),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_watch_target() {
+        let config: Config =
+            serde_yaml::from_str("profile: dev\nwatch:\n  url: https://dev.example.com/\n")
+                .unwrap();
+        assert!(config.check_watch_target("https://dev.example.com").is_ok());
+        assert!(config
+            .check_watch_target("https://prod.example.com")
+            .is_err());
+        assert!(Config::default()
+            .check_watch_target("https://dev.example.com")
+            .is_err());
+
+        let config: Config =
+            serde_yaml::from_str("profile: PROD\nwatch:\n  url: https://dev.example.com\n")
+                .unwrap();
+        assert!(matches!(
+            config.check_watch_target("https://dev.example.com"),
+            Err(SwimlaneCliError::WatchProductionProfile(_))
+        ));
+    }
+}
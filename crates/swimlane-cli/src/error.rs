@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use thiserror::Error;
 
 use crate::util::ParsePackageVersionError;
@@ -16,6 +18,34 @@ pub enum SwimlaneCliError {
     PackageDoesNotExist(String),
     #[error("Error Parsing package version")]
     ParsePackageError(#[from] ParsePackageVersionError),
+    #[error("Could not read config file: '{}'", .0.display())]
+    ConfigReadError(PathBuf, #[source] std::io::Error),
+    #[error("Could not parse config file")]
+    ConfigParseError(#[from] serde_yaml::Error),
+    #[error("Could not export or read the snapshot")]
+    SnapshotError(#[from] swimlane_migrator::snapshot::SnapshotError),
+    #[error("Could not read or substitute the environment's values")]
+    TemplateError(#[from] swimlane_migrator::template::TemplateError),
+    #[error("--url and --pat, or SWIMLANE_CLI__URL and SWIMLANE_CLI__PAT, must be set")]
+    MissingServer,
+    #[error("--dir, or --url to default it to .swimlane-cli/backups/<host>, must be set")]
+    MissingBackupDir,
+    #[error("Could not read or write a file")]
+    IoError(#[from] std::io::Error),
+    #[error("Could not serialise output")]
+    SerialiseError(#[from] serde_json::Error),
+    #[error("task watch only pushes to the instance set as `watch.url` in the config file")]
+    WatchNotConfigured,
+    #[error("task watch is configured to push to '{0}', not '{1}'")]
+    WatchUrlMismatch(String, String),
+    #[error("task watch refuses to push to the '{0}' profile")]
+    WatchProductionProfile(String),
+    #[error("The backup was taken of '{0}', not '{1}'. Pass --force to restore it anyway")]
+    BackupUrlMismatch(String, String),
+    #[error("'{}' must contain a JSON object", .0.display())]
+    NotAJsonObject(PathBuf),
+    #[error("Could not run the python interpreter '{0}'")]
+    PythonError(String, #[source] std::io::Error),
     #[error("Generic error")]
     GenericError(#[source] Box<dyn std::error::Error>),
 }
@@ -1,15 +1,30 @@
 mod cmd;
+pub mod config;
 pub mod error;
+pub mod python;
 pub mod util;
 
 use clap::{arg, Parser, Subcommand};
-use cmd::commands::{freeze_python_packages, handle_migrate, remove_python_package};
-use cmd::task::save_python_tasks;
+use cmd::app::{export_native_apps, import_native_apps};
+use cmd::backup::{create_backup, list_backups, prune_backups, restore_backup};
+use cmd::commands::{
+    freeze_python_packages, handle_apply, handle_migrate, remove_python_package, MigrateOptions,
+};
+use cmd::deps::report_task_dependencies;
+use cmd::diff::{handle_diff, DiffResource, OutputFormat};
+use cmd::export::{handle_export, ExportFormat};
+use cmd::lint::lint_python_tasks;
+use cmd::migrate::FieldTypeChange;
+use cmd::run::run_python_task;
+use cmd::task::{diff_python_tasks, push_python_tasks, save_python_tasks, watch_python_tasks};
+use config::Config;
 use error::SwimlaneCliError;
 use std::env;
 use std::path::PathBuf;
 use swimlane::SwimlaneClient;
-use util::parse_package_version;
+use swimlane_migrator::server::SwimlaneServer;
+use swimlane_migrator::template::TemplateValues;
+use util::{default_backup_dir, default_baseline_dir, load_values, parse_package_version};
 
 #[derive(Debug, Parser)]
 #[command(name = "swimlane-cli")]
@@ -18,11 +33,17 @@ pub struct Cli {
     #[command(subcommand)]
     command: Command,
 
+    /// The URL of the Swimlane server. Required by every command other than `task run`
     #[arg(long, env = "SWIMLANE_CLI__URL")]
-    url: String,
+    url: Option<String>,
 
+    /// A personal access token for the Swimlane server. Required by every command other than `task run`
     #[arg(long, env = "SWIMLANE_CLI__PAT")]
-    pat: String,
+    pat: Option<String>,
+
+    /// Path to a YAML configuration file
+    #[arg(long, env = "SWIMLANE_CLI__CONFIG")]
+    config: Option<PathBuf>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -50,6 +71,92 @@ pub enum Command {
         dry_run: bool,
         #[arg(long)]
         auto_approve: bool,
+        /// Overwrites resources which were changed on the target since the last migration
+        #[arg(long)]
+        force: bool,
+        /// Applies changes which destroy data on the target, such as removing an application field or deleting a resource
+        #[arg(long)]
+        allow_destructive: bool,
+        /// How application fields which kept their name but changed type are migrated
+        #[arg(long, value_enum, default_value_t = FieldTypeChange::Refuse)]
+        field_type_change: FieldTypeChange,
+        /// Where the state of the target is recorded after each migration. Defaults to .swimlane-cli/baseline/<target host>
+        #[arg(long, env = "SWIMLANE_CLI__BASELINE_DIR")]
+        baseline_dir: Option<PathBuf>,
+        /// A YAML file of the source environment's values, which are replaced with placeholders such as {{ env.SIEM_HOST }}
+        #[arg(long)]
+        source_values: Option<PathBuf>,
+        /// A YAML file of the target environment's values, which are substituted for placeholders such as {{ env.SIEM_HOST }}
+        #[arg(long)]
+        values: Option<PathBuf>,
+    },
+    /// Exports the configuration of the Swimlane server to a directory, one file per resource
+    Export {
+        /// The directory to export to
+        #[arg(long)]
+        path: PathBuf,
+        /// A previous export to compare against. Only the differences from it are written, as an overlay
+        #[arg(long)]
+        base: Option<PathBuf>,
+        /// The format of the exported files
+        #[arg(long, value_enum, default_value_t = ExportFormat::Yaml)]
+        format: ExportFormat,
+        /// A YAML file of the environment's values, which are replaced with placeholders such as {{ env.SIEM_HOST }}
+        #[arg(long)]
+        values: Option<PathBuf>,
+    },
+    /// Reconciles the Swimlane server with the configuration exported to a directory
+    Apply {
+        /// The directory the configuration was exported to
+        #[arg(long)]
+        path: PathBuf,
+        /// A directory of changes to the exported configuration, such as an environment's differences. Can be repeated
+        #[arg(long)]
+        overlay: Vec<PathBuf>,
+        #[arg(long)]
+        dry_run: bool,
+        #[arg(long)]
+        auto_approve: bool,
+        /// Overwrites resources which were changed on the server since the last apply
+        #[arg(long)]
+        force: bool,
+        /// Applies changes which destroy data on the server, such as removing an application field or deleting a resource
+        #[arg(long)]
+        allow_destructive: bool,
+        /// How application fields which kept their name but changed type are applied
+        #[arg(long, value_enum, default_value_t = FieldTypeChange::Refuse)]
+        field_type_change: FieldTypeChange,
+        /// Where the state of the server is recorded after each apply. Defaults to .swimlane-cli/baseline/<host>
+        #[arg(long, env = "SWIMLANE_CLI__BASELINE_DIR")]
+        baseline_dir: Option<PathBuf>,
+        /// A YAML file of the environment's values, which are substituted for placeholders such as {{ env.SIEM_HOST }}
+        #[arg(long)]
+        values: Option<PathBuf>,
+    },
+    /// Subcommands for importing and exporting applications in the format used by the Swimlane UI
+    #[command(arg_required_else_help = true)]
+    App {
+        #[clap(subcommand)]
+        subcommand: App,
+    },
+    /// Subcommands for backing up and restoring the Swimlane server
+    #[command(arg_required_else_help = true)]
+    Backup {
+        #[clap(subcommand)]
+        subcommand: Backup,
+    },
+    /// Compares the resources of the source Swimlane server (A) with the target Swimlane server (B) without making any changes
+    Diff {
+        /// The type of resource to compare
+        #[arg(value_enum, default_value_t = DiffResource::All)]
+        resource: DiffResource,
+        #[arg(long, env = "SWIMLANE_CLI__TARGET_URL")]
+        target_url: String,
+        #[arg(long, env = "SWIMLANE_CLI__TARGET_PAT")]
+        target_pat: String,
+        /// How the differences are displayed
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
     },
 }
 
@@ -75,11 +182,89 @@ pub enum Migrate {
     /// Migrates the specified application from the source Swimlane server to the target Swimlane server
     #[command(arg_required_else_help = true)]
     App { application_name: String },
+    /// Migrates all tasks, including common tasks, from the source Swimlane server to the target Swimlane server
+    Tasks,
 
     /// Migrates all possible content from the source Swimlane server to the target Swimlane server
     All,
 }
 
+#[derive(Debug, Subcommand)]
+pub enum App {
+    /// Exports applications in the format used by the Swimlane UI, one file per application
+    #[command(arg_required_else_help = true)]
+    Export {
+        /// The directory to export to
+        #[arg(long)]
+        path: PathBuf,
+        /// Application to export. Defaults to all applications
+        #[arg(short, long)]
+        app: Option<String>,
+    },
+    /// Imports applications exported from the Swimlane UI, showing the differences first
+    #[command(arg_required_else_help = true)]
+    Import {
+        /// The exported application files
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        #[arg(long)]
+        dry_run: bool,
+        #[arg(long)]
+        auto_approve: bool,
+        /// Applies changes which destroy data on the server, such as removing an application field
+        #[arg(long)]
+        allow_destructive: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Backup {
+    /// Writes every resource on the Swimlane server to a compressed archive
+    Create {
+        /// The directory backups are stored in. Defaults to .swimlane-cli/backups/<host>
+        #[arg(long, env = "SWIMLANE_CLI__BACKUP_DIR")]
+        dir: Option<PathBuf>,
+    },
+    /// Lists the backups in the directory, oldest first
+    List {
+        /// The directory backups are stored in. Defaults to .swimlane-cli/backups/<host>
+        #[arg(long, env = "SWIMLANE_CLI__BACKUP_DIR")]
+        dir: Option<PathBuf>,
+    },
+    /// Removes all but the newest backups in the directory
+    #[command(arg_required_else_help = true)]
+    Prune {
+        /// The number of backups to keep
+        #[arg(long)]
+        keep: usize,
+        /// The directory backups are stored in. Defaults to .swimlane-cli/backups/<host>
+        #[arg(long, env = "SWIMLANE_CLI__BACKUP_DIR")]
+        dir: Option<PathBuf>,
+    },
+    /// Reconciles the Swimlane server with the resources in a backup
+    #[command(arg_required_else_help = true)]
+    Restore {
+        /// The backup archive to restore
+        archive: PathBuf,
+        #[arg(long)]
+        dry_run: bool,
+        #[arg(long)]
+        auto_approve: bool,
+        /// Restores a backup of a different server, and overwrites resources which were changed on the server since the last apply
+        #[arg(long)]
+        force: bool,
+        /// Applies changes which destroy data on the server, such as removing an application field or deleting a resource
+        #[arg(long)]
+        allow_destructive: bool,
+        /// How application fields which kept their name but changed type are restored
+        #[arg(long, value_enum, default_value_t = FieldTypeChange::Refuse)]
+        field_type_change: FieldTypeChange,
+        /// Where the state of the server is recorded after each apply. Defaults to .swimlane-cli/baseline/<host>
+        #[arg(long, env = "SWIMLANE_CLI__BASELINE_DIR")]
+        baseline_dir: Option<PathBuf>,
+    },
+}
+
 #[derive(Debug, Subcommand)]
 pub enum Pip {
     /// Installs a specified package or requirements.txt file
@@ -109,13 +294,122 @@ pub enum Task {
         #[arg(short, long)]
         app: Option<String>,
     },
+    /// Compares the python tasks saved to a path with the tasks on the server. Exits with 1 if they differ
+    Diff {
+        /// Where the tasks were downloaded to. Defaults to .
+        #[arg(short, long, default_value=env::current_dir().unwrap().into_os_string())]
+        path: PathBuf,
+        /// Application to compare tasks for
+        #[arg(short, long)]
+        app: Option<String>,
+    },
+    /// Uploads the python tasks saved to a path which differ from the tasks on the server
+    Push {
+        /// Where the tasks were downloaded to. Defaults to .
+        #[arg(short, long, default_value=env::current_dir().unwrap().into_os_string())]
+        path: PathBuf,
+        /// Application to upload tasks for
+        #[arg(short, long)]
+        app: Option<String>,
+        #[arg(long)]
+        dry_run: bool,
+        #[arg(long)]
+        auto_approve: bool,
+        /// Pushes tasks which were changed on the server since they were saved
+        #[arg(long)]
+        force: bool,
+    },
+    /// Checks the python tasks saved to a path for syntax errors, missing packages, unknown inputs and denied calls. Exits with 1 if there are any problems
+    Lint {
+        /// Where the tasks were downloaded to. Defaults to .
+        #[arg(short, long, default_value=env::current_dir().unwrap().into_os_string())]
+        path: PathBuf,
+        /// Application to check tasks for
+        #[arg(short, long)]
+        app: Option<String>,
+    },
+    /// Reports the packages each python task on the server imports, the imported packages which aren't installed, and the installed packages which no task imports
+    Deps {
+        /// Application to list tasks for. Unused packages and requirements still account for every task
+        #[arg(short, long)]
+        app: Option<String>,
+        /// Writes the packages the tasks need to a requirements.txt file
+        #[arg(short, long)]
+        requirements: Option<PathBuf>,
+    },
+    /// Runs a saved python task in the local python interpreter, with its inputs read from a sample record, and checks its outputs against the task's output mappings. Exits with 1 if the task fails
+    #[command(arg_required_else_help = true)]
+    Run {
+        /// The task's script
+        file: PathBuf,
+        /// A JSON file of the record's values, keyed by field name
+        #[arg(short, long)]
+        record: Option<PathBuf>,
+        /// A JSON file of the asset's values, keyed by input
+        #[arg(long)]
+        asset: Option<PathBuf>,
+        /// The python interpreter to run the task with
+        #[arg(long, default_value = "python3")]
+        python: String,
+    },
+    /// Watches the python tasks saved to a path, and pushes each script to the server when it's saved. The server must be set as `watch.url` in the config file
+    Watch {
+        /// Where the tasks were downloaded to. Defaults to .
+        #[arg(short, long, default_value=env::current_dir().unwrap().into_os_string())]
+        path: PathBuf,
+        /// Application to watch tasks for
+        #[arg(short, long)]
+        app: Option<String>,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<(), SwimlaneCliError> {
     let args = Cli::parse();
 
-    let swimlane_client = SwimlaneClient::new(args.url, args.pat);
+    let config = match &args.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+
+    // Runs the task locally, so doesn't need the server
+    if let Command::Task {
+        subcommand:
+            Task::Run {
+                file,
+                record,
+                asset,
+                python,
+            },
+    } = &args.command
+    {
+        if run_python_task(file, record, asset, python, &config.run)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Only reads the backup directory, so doesn't need the server
+    if let Command::Backup {
+        subcommand: subcommand @ (Backup::List { dir } | Backup::Prune { dir, .. }),
+    } = &args.command
+    {
+        let dir = match (dir, &args.url) {
+            (Some(dir), _) => dir.clone(),
+            (None, Some(url)) => default_backup_dir(url),
+            (None, None) => return Err(SwimlaneCliError::MissingBackupDir),
+        };
+        match subcommand {
+            Backup::Prune { keep, .. } => prune_backups(&dir, *keep)?,
+            _ => list_backups(&dir)?,
+        }
+        return Ok(());
+    }
+
+    let (Some(url), Some(pat)) = (args.url, args.pat) else {
+        return Err(SwimlaneCliError::MissingServer);
+    };
+    let swimlane_client = SwimlaneServer::new(url, pat);
 
     match args.command {
         Command::Task { subcommand } => match subcommand {
@@ -123,6 +417,34 @@ async fn main() -> Result<(), SwimlaneCliError> {
                 println!("Downloading tasks to: '{}'", path.display());
                 save_python_tasks(&swimlane_client, &path, &app).await?
             }
+            Task::Diff { path, app } => {
+                if diff_python_tasks(&swimlane_client, &path, &app).await? {
+                    std::process::exit(1);
+                }
+            }
+            Task::Push {
+                path,
+                app,
+                dry_run,
+                auto_approve,
+                force,
+            } => {
+                push_python_tasks(&swimlane_client, &path, &app, dry_run, auto_approve, force)
+                    .await?
+            }
+            Task::Lint { path, app } => {
+                if lint_python_tasks(&swimlane_client, &path, &app, &config.lint).await? {
+                    std::process::exit(1);
+                }
+            }
+            Task::Deps { app, requirements } => {
+                report_task_dependencies(&swimlane_client, &app, &requirements).await?
+            }
+            Task::Run { .. } => unreachable!("task run is run before the client is built"),
+            Task::Watch { path, app } => {
+                config.check_watch_target(&swimlane_client.base_url)?;
+                watch_python_tasks(&swimlane_client, &path, &app).await?
+            }
         },
         Command::Pip { subcommand } => match subcommand {
             Pip::Install {
@@ -153,9 +475,16 @@ async fn main() -> Result<(), SwimlaneCliError> {
             target_url,
             target_pat,
             dry_run,
-            auto_approve: _,
+            auto_approve,
+            force,
+            allow_destructive,
+            field_type_change,
+            baseline_dir,
+            source_values,
+            values,
         } => {
-            let target_swimlane_client = SwimlaneClient::new(target_url, target_pat);
+            let baseline_dir = baseline_dir.unwrap_or_else(|| default_baseline_dir(&target_url));
+            let target_swimlane_client = SwimlaneServer::new(target_url, target_pat);
 
             let migration_type = migration_type.unwrap_or(Migrate::All);
 
@@ -163,7 +492,138 @@ async fn main() -> Result<(), SwimlaneCliError> {
                 swimlane_client,
                 target_swimlane_client,
                 migration_type,
+                &MigrateOptions {
+                    dry_run,
+                    auto_approve,
+                    force,
+                    allow_destructive,
+                    field_type_change,
+                    baseline_dir,
+                    source_values: load_values(source_values, &config.values)?,
+                    values: load_values(values, &config.values)?,
+                },
+                &config,
+            )
+            .await?
+        }
+        Command::Export {
+            path,
+            base,
+            format,
+            values,
+        } => {
+            handle_export(
+                &swimlane_client,
+                &path,
+                &base,
+                format,
+                &config.redact,
+                &load_values(values, &config.values)?,
+            )
+            .await?
+        }
+        Command::Apply {
+            path,
+            overlay,
+            dry_run,
+            auto_approve,
+            force,
+            allow_destructive,
+            field_type_change,
+            baseline_dir,
+            values,
+        } => {
+            let baseline_dir =
+                baseline_dir.unwrap_or_else(|| default_baseline_dir(&swimlane_client.base_url));
+            handle_apply(
+                &path,
+                &overlay,
+                swimlane_client,
+                &MigrateOptions {
+                    dry_run,
+                    auto_approve,
+                    force,
+                    allow_destructive,
+                    field_type_change,
+                    baseline_dir,
+                    source_values: TemplateValues::default(),
+                    values: load_values(values, &config.values)?,
+                },
+                &config,
+            )
+            .await?
+        }
+        Command::App { subcommand } => match subcommand {
+            App::Export { path, app } => export_native_apps(&swimlane_client, &path, &app).await?,
+            App::Import {
+                files,
                 dry_run,
+                auto_approve,
+                allow_destructive,
+            } => {
+                import_native_apps(
+                    &files,
+                    swimlane_client,
+                    dry_run,
+                    auto_approve,
+                    allow_destructive,
+                    &config,
+                )
+                .await?
+            }
+        },
+        Command::Backup { subcommand } => {
+            let default_dir = default_backup_dir(&swimlane_client.base_url);
+            match subcommand {
+                Backup::Create { dir } => {
+                    create_backup(&swimlane_client, &dir.unwrap_or(default_dir)).await?
+                }
+                Backup::List { .. } | Backup::Prune { .. } => {
+                    unreachable!("handled before the client is built")
+                }
+                Backup::Restore {
+                    archive,
+                    dry_run,
+                    auto_approve,
+                    force,
+                    allow_destructive,
+                    field_type_change,
+                    baseline_dir,
+                } => {
+                    let baseline_dir = baseline_dir
+                        .unwrap_or_else(|| default_baseline_dir(&swimlane_client.base_url));
+                    restore_backup(
+                        &archive,
+                        swimlane_client,
+                        &MigrateOptions {
+                            dry_run,
+                            auto_approve,
+                            force,
+                            allow_destructive,
+                            field_type_change,
+                            baseline_dir,
+                            source_values: TemplateValues::default(),
+                            values: TemplateValues::default(),
+                        },
+                        &config,
+                    )
+                    .await?
+                }
+            }
+        }
+        Command::Diff {
+            resource,
+            target_url,
+            target_pat,
+            format,
+        } => {
+            let target_swimlane_client = SwimlaneClient::new(target_url, target_pat);
+            handle_diff(
+                swimlane_client.client().clone(),
+                target_swimlane_client,
+                resource,
+                format,
+                &config,
             )
             .await?
         }
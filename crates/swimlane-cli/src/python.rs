@@ -0,0 +1,542 @@
+//! Static analysis of python task scripts, which are parsed without running them
+
+use std::collections::HashMap;
+
+use tree_sitter::{Node, Parser, Tree};
+
+/// The object through which Swimlane passes a task its inputs
+const CONTEXT_INPUTS: &str = "sw_context.inputs";
+
+/// The top level modules of the python standard library
+const STDLIB_MODULES: &[&str] = &[
+    "__future__",
+    "_thread",
+    "abc",
+    "aifc",
+    "argparse",
+    "array",
+    "ast",
+    "asynchat",
+    "asyncio",
+    "asyncore",
+    "atexit",
+    "audioop",
+    "base64",
+    "bdb",
+    "binascii",
+    "bisect",
+    "builtins",
+    "bz2",
+    "calendar",
+    "cgi",
+    "cgitb",
+    "chunk",
+    "cmath",
+    "cmd",
+    "code",
+    "codecs",
+    "codeop",
+    "collections",
+    "colorsys",
+    "compileall",
+    "concurrent",
+    "configparser",
+    "contextlib",
+    "contextvars",
+    "copy",
+    "copyreg",
+    "cProfile",
+    "crypt",
+    "csv",
+    "ctypes",
+    "curses",
+    "dataclasses",
+    "datetime",
+    "dbm",
+    "decimal",
+    "difflib",
+    "dis",
+    "distutils",
+    "doctest",
+    "email",
+    "encodings",
+    "ensurepip",
+    "enum",
+    "errno",
+    "faulthandler",
+    "fcntl",
+    "filecmp",
+    "fileinput",
+    "fnmatch",
+    "fractions",
+    "ftplib",
+    "functools",
+    "gc",
+    "getopt",
+    "getpass",
+    "gettext",
+    "glob",
+    "graphlib",
+    "grp",
+    "gzip",
+    "hashlib",
+    "heapq",
+    "hmac",
+    "html",
+    "http",
+    "imaplib",
+    "imghdr",
+    "imp",
+    "importlib",
+    "inspect",
+    "io",
+    "ipaddress",
+    "itertools",
+    "json",
+    "keyword",
+    "linecache",
+    "locale",
+    "logging",
+    "lzma",
+    "mailbox",
+    "mailcap",
+    "marshal",
+    "math",
+    "mimetypes",
+    "mmap",
+    "modulefinder",
+    "msvcrt",
+    "multiprocessing",
+    "netrc",
+    "nntplib",
+    "numbers",
+    "operator",
+    "optparse",
+    "os",
+    "pathlib",
+    "pdb",
+    "pickle",
+    "pickletools",
+    "pipes",
+    "pkgutil",
+    "platform",
+    "plistlib",
+    "poplib",
+    "posix",
+    "pprint",
+    "profile",
+    "pstats",
+    "pty",
+    "pwd",
+    "py_compile",
+    "pyclbr",
+    "pydoc",
+    "queue",
+    "quopri",
+    "random",
+    "re",
+    "readline",
+    "reprlib",
+    "resource",
+    "rlcompleter",
+    "runpy",
+    "sched",
+    "secrets",
+    "select",
+    "selectors",
+    "shelve",
+    "shlex",
+    "shutil",
+    "signal",
+    "site",
+    "smtpd",
+    "smtplib",
+    "sndhdr",
+    "socket",
+    "socketserver",
+    "sqlite3",
+    "ssl",
+    "stat",
+    "statistics",
+    "string",
+    "stringprep",
+    "struct",
+    "subprocess",
+    "sunau",
+    "symtable",
+    "sys",
+    "sysconfig",
+    "syslog",
+    "tabnanny",
+    "tarfile",
+    "telnetlib",
+    "tempfile",
+    "termios",
+    "textwrap",
+    "threading",
+    "time",
+    "timeit",
+    "tkinter",
+    "token",
+    "tokenize",
+    "tomllib",
+    "trace",
+    "traceback",
+    "tracemalloc",
+    "tty",
+    "turtle",
+    "types",
+    "typing",
+    "unicodedata",
+    "unittest",
+    "urllib",
+    "uu",
+    "uuid",
+    "venv",
+    "warnings",
+    "wave",
+    "weakref",
+    "webbrowser",
+    "winreg",
+    "wsgiref",
+    "xdrlib",
+    "xml",
+    "xmlrpc",
+    "zipapp",
+    "zipfile",
+    "zipimport",
+    "zlib",
+    "zoneinfo",
+];
+
+/// Modules which are imported under a different name to the package which installs them
+const DISTRIBUTIONS: &[(&str, &str)] = &[
+    ("attr", "attrs"),
+    ("bs4", "beautifulsoup4"),
+    ("Crypto", "pycryptodome"),
+    ("cv2", "opencv-python"),
+    ("dateutil", "python-dateutil"),
+    ("dns", "dnspython"),
+    ("docx", "python-docx"),
+    ("dotenv", "python-dotenv"),
+    ("git", "GitPython"),
+    ("jose", "python-jose"),
+    ("jwt", "PyJWT"),
+    ("magic", "python-magic"),
+    ("nacl", "PyNaCl"),
+    ("OpenSSL", "pyOpenSSL"),
+    ("PIL", "Pillow"),
+    ("pkg_resources", "setuptools"),
+    ("serial", "pyserial"),
+    ("sklearn", "scikit-learn"),
+    ("slugify", "python-slugify"),
+    ("socks", "PySocks"),
+    ("whois", "python-whois"),
+    ("yaml", "PyYAML"),
+    ("zmq", "pyzmq"),
+];
+
+/// A position in a script, counted from 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl From<tree_sitter::Point> for Location {
+    fn from(point: tree_sitter::Point) -> Self {
+        Self {
+            line: point.row + 1,
+            column: point.column + 1,
+        }
+    }
+}
+
+/// A name in the script, such as an imported module or a called function, and where it is
+#[derive(Debug, Clone, PartialEq)]
+pub struct Name {
+    pub name: String,
+    pub location: Location,
+}
+
+/// A parsed python script
+pub struct Script<'a> {
+    source: &'a str,
+    tree: Tree,
+}
+
+impl<'a> Script<'a> {
+    pub fn parse(source: &'a str) -> Self {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_python::LANGUAGE.into())
+            .expect("The python grammar is compatible with the parser");
+        let tree = parser
+            .parse(source, None)
+            .expect("Parsing without a timeout or cancellation always succeeds");
+        Self { source, tree }
+    }
+
+    /// Describes each syntax error in the script
+    pub fn syntax_errors(&self) -> Vec<Name> {
+        let mut errors = vec![];
+        walk(self.tree.root_node(), &mut |node| {
+            if node.is_missing() {
+                errors.push(Name {
+                    name: format!("expected '{}'", node.kind()),
+                    location: node.start_position().into(),
+                });
+                return false;
+            }
+            if node.is_error() {
+                errors.push(Name {
+                    name: "invalid syntax".to_string(),
+                    location: node.start_position().into(),
+                });
+                return false;
+            }
+            node.has_error()
+        });
+        errors
+    }
+
+    /// The top level module of each absolute import, such as `requests` for `import requests.auth`
+    pub fn imports(&self) -> Vec<Name> {
+        let mut imports = vec![];
+        walk(self.tree.root_node(), &mut |node| {
+            let modules = match node.kind() {
+                "import_statement" => named_children(node, "name"),
+                "import_from_statement" => named_children(node, "module_name"),
+                _ => return true,
+            };
+            for module in modules {
+                let module = match module.kind() {
+                    "aliased_import" => module.child_by_field_name("name").unwrap_or(module),
+                    _ => module,
+                };
+                if module.kind() != "dotted_name" {
+                    continue;
+                }
+                let name = self.text(module);
+                imports.push(Name {
+                    name: name.split('.').next().unwrap_or(name).to_string(),
+                    location: module.start_position().into(),
+                });
+            }
+            false
+        });
+        imports
+    }
+
+    /// The dotted name of each called function, with imported aliases resolved to the name they
+    /// were imported from, such as `os.system` for `system()` after `from os import system`
+    pub fn calls(&self) -> Vec<Name> {
+        let aliases = self.aliases();
+        let mut calls = vec![];
+        walk(self.tree.root_node(), &mut |node| {
+            if node.kind() != "call" {
+                return true;
+            }
+            if let Some(name) = node
+                .child_by_field_name("function")
+                .and_then(|function| self.dotted_name(function))
+            {
+                let (first, rest) = name.split_once('.').unwrap_or((&name, ""));
+                let name = match (aliases.get(first), rest) {
+                    (Some(alias), "") => alias.clone(),
+                    (Some(alias), rest) => format!("{}.{}", alias, rest),
+                    (None, _) => name.clone(),
+                };
+                calls.push(Name {
+                    name,
+                    location: node.start_position().into(),
+                });
+            }
+            true
+        });
+        calls
+    }
+
+    /// The keys of the task's inputs which are read, such as `url` for
+    /// `sw_context.inputs['url']` or `sw_context.inputs.get('url')`
+    pub fn input_keys(&self) -> Vec<Name> {
+        let mut keys = vec![];
+        walk(self.tree.root_node(), &mut |node| {
+            let key = match node.kind() {
+                "subscript" => node
+                    .child_by_field_name("value")
+                    .filter(|value| self.dotted_name(*value).as_deref() == Some(CONTEXT_INPUTS))
+                    .and_then(|_| node.child_by_field_name("subscript")),
+                "call" => node
+                    .child_by_field_name("function")
+                    .filter(|function| {
+                        self.dotted_name(*function).as_deref()
+                            == Some(&format!("{}.get", CONTEXT_INPUTS))
+                    })
+                    .and_then(|_| node.child_by_field_name("arguments"))
+                    .and_then(|arguments| arguments.named_child(0)),
+                _ => None,
+            };
+            if let Some(key) = key.and_then(|key| self.string(key)) {
+                keys.push(Name {
+                    name: key,
+                    location: node.start_position().into(),
+                });
+            }
+            true
+        });
+        keys
+    }
+
+    /// Maps the names bound by imports to the modules or names they were imported from
+    fn aliases(&self) -> HashMap<String, String> {
+        let mut aliases = HashMap::new();
+        walk(self.tree.root_node(), &mut |node| {
+            let module = match node.kind() {
+                "import_statement" => None,
+                "import_from_statement" => node
+                    .child_by_field_name("module_name")
+                    .map(|module| self.text(module)),
+                _ => return true,
+            };
+            for name in named_children(node, "name") {
+                let (imported, alias) = match name.kind() {
+                    "aliased_import" => (
+                        name.child_by_field_name("name").map(|n| self.text(n)),
+                        name.child_by_field_name("alias").map(|n| self.text(n)),
+                    ),
+                    _ => (Some(self.text(name)), None),
+                };
+                let Some(imported) = imported else {
+                    continue;
+                };
+                let full_name = match module {
+                    Some(module) => format!("{}.{}", module, imported),
+                    None => imported.to_string(),
+                };
+                match alias {
+                    Some(alias) => aliases.insert(alias.to_string(), full_name),
+                    None if module.is_some() => aliases.insert(imported.to_string(), full_name),
+                    None => None,
+                };
+            }
+            false
+        });
+        aliases
+    }
+
+    fn text(&self, node: Node) -> &'a str {
+        &self.source[node.byte_range()]
+    }
+
+    /// The name of an identifier, or of an attribute of identifiers such as `os.path.join`
+    fn dotted_name(&self, node: Node) -> Option<String> {
+        match node.kind() {
+            "identifier" => Some(self.text(node).to_string()),
+            "attribute" => {
+                let object = self.dotted_name(node.child_by_field_name("object")?)?;
+                let attribute = node.child_by_field_name("attribute")?;
+                Some(format!("{}.{}", object, self.text(attribute)))
+            }
+            _ => None,
+        }
+    }
+
+    /// The value of a string literal without interpolation
+    fn string(&self, node: Node) -> Option<String> {
+        if node.kind() != "string" {
+            return None;
+        }
+        let mut cursor = node.walk();
+        let mut parts = node.named_children(&mut cursor);
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(start), Some(end), None) if start.kind() == "string_start" => {
+                (end.kind() == "string_end").then(String::new)
+            }
+            (Some(_), Some(content), Some(_)) if content.kind() == "string_content" => {
+                Some(self.text(content).to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Whether the module is part of the python standard library
+pub fn is_stdlib(module: &str) -> bool {
+    STDLIB_MODULES.contains(&module)
+}
+
+/// The name of the package which installs the module. Most packages share their module's name.
+pub fn distribution_name(module: &str) -> &str {
+    DISTRIBUTIONS
+        .iter()
+        .find(|(import, _)| *import == module)
+        .map(|(_, distribution)| *distribution)
+        .unwrap_or(module)
+}
+
+/// Normalises the name of a package, so names which pip treats as the same compare equal
+///
+/// ```
+/// use swimlane_cli::python::normalise_package_name;
+///
+/// assert_eq!(normalise_package_name("Python_Dateutil"), "python-dateutil");
+/// assert_eq!(normalise_package_name("zope.interface"), "zope-interface");
+/// ```
+pub fn normalise_package_name(name: &str) -> String {
+    name.to_lowercase().replace(['_', '.'], "-")
+}
+
+/// Calls the function with each node in the tree, descending into the node's children while
+/// the function returns true
+fn walk<'t>(node: Node<'t>, f: &mut impl FnMut(Node<'t>) -> bool) {
+    if !f(node) {
+        return;
+    }
+    let mut cursor = node.walk();
+    let children: Vec<Node<'t>> = node.children(&mut cursor).collect();
+    for child in children {
+        walk(child, f);
+    }
+}
+
+fn named_children<'t>(node: Node<'t>, field: &str) -> Vec<Node<'t>> {
+    let mut cursor = node.walk();
+    let children = node.children_by_field_name(field, &mut cursor).collect();
+    children
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_imports_and_calls_resolve_aliases() {
+        let script = Script::parse(
+            "import requests.auth\nimport subprocess as sp\nfrom os import system\nfrom . import helpers\n\nsp.run(['ls'])\nsystem('ls')\nprint(requests)\n",
+        );
+
+        let imports: Vec<String> = script.imports().into_iter().map(|i| i.name).collect();
+        let calls: Vec<String> = script.calls().into_iter().map(|c| c.name).collect();
+
+        assert_eq!(imports, vec!["requests", "subprocess", "os"]);
+        assert_eq!(calls, vec!["subprocess.run", "os.system", "print"]);
+        assert!(script.syntax_errors().is_empty());
+    }
+
+    #[test]
+    fn test_input_keys_and_syntax_errors() {
+        let script = Script::parse(
+            "url = sw_context.inputs['url']\nip = sw_context.inputs.get(\"ip\")\nother = sw_context.config['url']\ndef f(:\n    pass\n",
+        );
+
+        let keys: Vec<(String, usize)> = script
+            .input_keys()
+            .into_iter()
+            .map(|k| (k.name, k.location.line))
+            .collect();
+
+        assert_eq!(keys, vec![("url".to_string(), 1), ("ip".to_string(), 2)]);
+        assert_eq!(script.syntax_errors()[0].location.line, 4);
+    }
+}
@@ -1,5 +1,13 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use colored::Colorize;
+use similar::{ChangeTag, TextDiff};
+use swimlane_migrator::template::{TemplateError, TemplateValues};
 use thiserror::Error;
 
+use crate::config::ValuesConfig;
+
 #[derive(Debug, Error, PartialEq)]
 pub enum ParsePackageVersionError {
     #[error("No package version specified")]
@@ -35,6 +43,75 @@ pub fn parse_package_version(package_version: &str) -> Result<&str, ParsePackage
     Ok(split[1])
 }
 
+/// The directory the baseline for the given Swimlane instance is stored in by default
+pub fn default_baseline_dir(url: &str) -> PathBuf {
+    PathBuf::from(".swimlane-cli")
+        .join("baseline")
+        .join(host_dir_name(url))
+}
+
+/// The directory the backups of the given Swimlane instance are stored in by default
+pub fn default_backup_dir(url: &str) -> PathBuf {
+    PathBuf::from(".swimlane-cli")
+        .join("backups")
+        .join(host_dir_name(url))
+}
+
+/// Converts the host of the URL into a name which is safe to use as a directory name
+fn host_dir_name(url: &str) -> String {
+    url.trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_end_matches('/')
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect::<String>()
+}
+
+/// Reads the values of an environment from the file, if one was given, which are replaced in
+/// the strings with the configured keys
+pub fn load_values(
+    path: Option<PathBuf>,
+    config: &ValuesConfig,
+) -> Result<TemplateValues, TemplateError> {
+    match path {
+        Some(path) => Ok(TemplateValues::load(&path)?.with_keys(config.keys.clone())),
+        None => Ok(TemplateValues::default()),
+    }
+}
+
+/// Asks the user a yes/no question, defaulting to no
+pub fn confirm(message: &str) -> bool {
+    print!("{} [y/N] ", message);
+    let _ = std::io::stdout().flush();
+
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Prints the hunks of a unified diff between the two texts, each line prefixed with the indent
+pub fn print_unified_diff(from: &str, to: &str, indent: &str) {
+    let diff = TextDiff::from_lines(from, to);
+    for hunk in diff.unified_diff().iter_hunks() {
+        println!("{}{}", indent, hunk.header().to_string().cyan());
+        for change in hunk.iter_changes() {
+            let line = change.value().trim_end_matches('\n');
+            match change.tag() {
+                ChangeTag::Delete => println!("{}{}", indent, format!("-{}", line).red()),
+                ChangeTag::Insert => println!("{}{}", indent, format!("+{}", line).green()),
+                ChangeTag::Equal => println!("{} {}", indent, line),
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -59,4 +136,12 @@ mod tests {
         );
         assert_eq!(parse_package_version("package==1.0.0"), Ok("1.0.0"));
     }
+
+    #[test]
+    fn test_default_baseline_dir() {
+        assert_eq!(
+            default_baseline_dir("https://swimlane.example.com:8443/"),
+            PathBuf::from(".swimlane-cli/baseline/swimlane.example.com_8443")
+        );
+    }
 }
@@ -0,0 +1,287 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Fields, GenericArgument, LitStr,
+    Path, PathArguments, Type,
+};
+
+/// Derives `swimlane_migrator::equality::LooksLike` for a struct.
+///
+/// Every field is compared unless marked otherwise:
+///
+/// - `#[looks_like(identity)]` - the field is used by `is_same_resource`. At least one is required.
+/// - `#[looks_like(skip)]` - the field is never compared.
+/// - `#[looks_like(complex)]` - a change is reported as `Difference::UpdatingComplexField`.
+/// - `#[looks_like(by_name)]` - a list of resources, reported as added/removed by their `name`.
+/// - `#[looks_like(nested)]` - the field implements `LooksLike` and its differences are included.
+/// - `#[looks_like(rename = "...")]` - the name the field is reported under.
+///
+/// `Option<T>` fields are displayed as an empty string when `None`, and `Vec<String>` fields are
+/// reported as added/removed items.
+///
+/// Types from other crates can't derive the trait directly, so a mirror struct can be declared
+/// with `#[looks_like(remote = "path::to::Type")]`. The impl is then generated for the remote type,
+/// using the fields listed in the mirror struct.
+#[proc_macro_derive(LooksLike, attributes(looks_like))]
+pub fn derive_looks_like(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct ContainerAttributes {
+    remote: Option<Path>,
+}
+
+#[derive(Default, PartialEq)]
+enum Comparison {
+    #[default]
+    Value,
+    Skip,
+    Complex,
+    ByName,
+    Nested,
+}
+
+#[derive(Default)]
+struct FieldAttributes {
+    identity: bool,
+    comparison: Comparison,
+    rename: Option<String>,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let container = parse_container_attributes(&input)?;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    input.ident.span(),
+                    "LooksLike can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                input.ident.span(),
+                "LooksLike can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut differences = vec![];
+    let mut identities = vec![];
+
+    for field in fields {
+        let attributes = parse_field_attributes(field)?;
+        let ident = field
+            .ident
+            .as_ref()
+            .expect("named fields always have an ident");
+        let label = attributes.rename.unwrap_or_else(|| ident.to_string());
+
+        if attributes.identity {
+            identities.push(quote! { self.#ident == other.#ident });
+        }
+
+        let difference = match attributes.comparison {
+            Comparison::Skip => continue,
+            Comparison::Complex => quote! {
+                if self.#ident != other.#ident {
+                    differences.push(::swimlane_migrator::equality::Difference::UpdatingComplexField {
+                        field: #label.to_string(),
+                    });
+                }
+            },
+            Comparison::Nested => quote! {
+                differences.extend(::swimlane_migrator::equality::LooksLike::differences(
+                    &self.#ident,
+                    &other.#ident,
+                ));
+            },
+            Comparison::ByName => item_differences(ident, &label, quote! { item.name.clone() }),
+            Comparison::Value => match classify(&field.ty) {
+                FieldType::Option => quote! {
+                    if self.#ident != other.#ident {
+                        differences.push(::swimlane_migrator::equality::Difference::UpdatingField {
+                            field: #label.to_string(),
+                            current_value: self.#ident.as_ref().map_or("".to_string(), |v| v.to_string()),
+                            new_value: other.#ident.as_ref().map_or("".to_string(), |v| v.to_string()),
+                        });
+                    }
+                },
+                FieldType::StringVec => item_differences(ident, &label, quote! { item.clone() }),
+                FieldType::Vec => {
+                    return Err(Error::new(
+                        field.ty.span(),
+                        "Vec fields must be marked with #[looks_like(by_name)], #[looks_like(nested)], #[looks_like(complex)] or #[looks_like(skip)]",
+                    ))
+                }
+                FieldType::Other => quote! {
+                    if self.#ident != other.#ident {
+                        differences.push(::swimlane_migrator::equality::Difference::UpdatingField {
+                            field: #label.to_string(),
+                            current_value: self.#ident.to_string(),
+                            new_value: other.#ident.to_string(),
+                        });
+                    }
+                },
+            },
+        };
+        differences.push(difference);
+    }
+
+    if identities.is_empty() {
+        return Err(Error::new(
+            input.ident.span(),
+            "At least one field must be marked with #[looks_like(identity)]",
+        ));
+    }
+
+    let target = match container.remote {
+        Some(remote) => quote! { #remote },
+        None => {
+            let ident = &input.ident;
+            quote! { #ident }
+        }
+    };
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::swimlane_migrator::equality::LooksLike for #target #type_generics #where_clause {
+            fn differences(&self, other: &Self) -> Vec<::swimlane_migrator::equality::Difference> {
+                #[allow(unused_imports)]
+                use ::swimlane_migrator::equality::LooksLike;
+
+                let mut differences = vec![];
+                #(#differences)*
+                differences
+            }
+
+            fn is_same_resource(&self, other: &Self) -> bool {
+                #(#identities)&&*
+            }
+        }
+    })
+}
+
+/// Reports items present in only one of the two lists, matched with `is_same_resource`
+fn item_differences(ident: &syn::Ident, label: &str, item_name: TokenStream2) -> TokenStream2 {
+    quote! {
+        differences.extend(self.#ident.iter().filter_map(|item| {
+            match other.#ident.iter().find(|other_item| item.is_same_resource(other_item)) {
+                Some(_) => None,
+                None => Some(::swimlane_migrator::equality::Difference::AddingItem {
+                    field: #label.to_string(),
+                    item: #item_name,
+                }),
+            }
+        }));
+        differences.extend(other.#ident.iter().filter_map(|item| {
+            match self.#ident.iter().find(|other_item| item.is_same_resource(other_item)) {
+                Some(_) => None,
+                None => Some(::swimlane_migrator::equality::Difference::RemovingItem {
+                    field: #label.to_string(),
+                    item: #item_name,
+                }),
+            }
+        }));
+    }
+}
+
+fn parse_container_attributes(input: &DeriveInput) -> syn::Result<ContainerAttributes> {
+    let mut attributes = ContainerAttributes::default();
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("looks_like"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("remote") {
+                let value: LitStr = meta.value()?.parse()?;
+                attributes.remote = Some(value.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported looks_like container attribute"))
+            }
+        })?;
+    }
+    Ok(attributes)
+}
+
+fn parse_field_attributes(field: &syn::Field) -> syn::Result<FieldAttributes> {
+    let mut attributes = FieldAttributes::default();
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("looks_like"))
+    {
+        attr.parse_nested_meta(|meta| {
+            let comparison = if meta.path.is_ident("identity") {
+                attributes.identity = true;
+                return Ok(());
+            } else if meta.path.is_ident("rename") {
+                let value: LitStr = meta.value()?.parse()?;
+                attributes.rename = Some(value.value());
+                return Ok(());
+            } else if meta.path.is_ident("skip") {
+                Comparison::Skip
+            } else if meta.path.is_ident("complex") {
+                Comparison::Complex
+            } else if meta.path.is_ident("by_name") {
+                Comparison::ByName
+            } else if meta.path.is_ident("nested") {
+                Comparison::Nested
+            } else {
+                return Err(meta.error("unsupported looks_like field attribute"));
+            };
+
+            if attributes.comparison != Comparison::Value {
+                return Err(meta.error(
+                    "only one of skip, complex, by_name or nested may be specified per field",
+                ));
+            }
+            attributes.comparison = comparison;
+            Ok(())
+        })?;
+    }
+    Ok(attributes)
+}
+
+enum FieldType {
+    Option,
+    StringVec,
+    Vec,
+    Other,
+}
+
+fn classify(ty: &Type) -> FieldType {
+    let Type::Path(path) = ty else {
+        return FieldType::Other;
+    };
+    let Some(segment) = path.path.segments.last() else {
+        return FieldType::Other;
+    };
+
+    if segment.ident == "Option" {
+        return FieldType::Option;
+    }
+    if segment.ident != "Vec" {
+        return FieldType::Other;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(arguments) => match arguments.args.first() {
+            Some(GenericArgument::Type(Type::Path(inner))) if inner.path.is_ident("String") => {
+                FieldType::StringVec
+            }
+            _ => FieldType::Vec,
+        },
+        _ => FieldType::Vec,
+    }
+}
@@ -44,7 +44,13 @@
 
 use std::collections::HashMap;
 
-use swimlane::{apps::Application, SwimlaneClient};
+use swimlane::roles::{Access, PermissionMatrix, Role};
+use swimlane::tasks::Task;
+use swimlane::{apps::Application, error::SwimlaneClientError, SwimlaneClient};
+
+use crate::apps::field_id;
+use crate::snapshot::SnapshotError;
+use crate::tasks::TaskReferences;
 
 /// Allows for the "Normalization" of Swimlane Resources to convert reference IDs to common references, like name or username
 pub struct SwimlaneResourceNormaliser {
@@ -75,7 +81,12 @@ impl SwimlaneResourceNormaliser {
         app.workspaces = app
             .workspaces
             .iter()
-            .map(|workspace| workspace_hashmap.get(workspace).unwrap().to_string())
+            .map(|workspace| {
+                workspace_hashmap
+                    .get(workspace)
+                    .unwrap_or(workspace)
+                    .to_string()
+            })
             .collect();
 
         // todo: handle permissions
@@ -83,4 +94,150 @@ impl SwimlaneResourceNormaliser {
 
         app
     }
+
+    /// Returns the tasks of every application, as well as the common tasks, with the application
+    /// and fields they reference converted to names
+    pub async fn get_normalised_tasks(&self) -> Result<Vec<Task>, SnapshotError> {
+        let applications = self.swimlane_client.get_applications().await?;
+        let mut tasks = self.swimlane_client.get_common_tasks().await?;
+        for app in &applications {
+            tasks.extend(
+                self.swimlane_client
+                    .get_tasks_for_application(&app.id)
+                    .await?,
+            );
+        }
+        tasks
+            .iter()
+            .map(|task| TaskReferences::for_task(&applications, task).normalise(task))
+            .collect()
+    }
+
+    /// Returns the applications with their references converted to names
+    pub async fn get_normalised_applications(
+        &self,
+    ) -> Result<Vec<Application>, SwimlaneClientError> {
+        let apps_future = self.swimlane_client.get_applications();
+        let workspace_hashmap = self.get_workspace_hashmap();
+
+        let apps = apps_future.await?;
+        let workspace_hashmap = workspace_hashmap.await;
+
+        Ok(apps
+            .into_iter()
+            .map(|app| self.normalise_application(&app, &workspace_hashmap))
+            .collect())
+    }
+
+    pub fn normalise_permissions(
+        &self,
+        permissions: &PermissionMatrix,
+        applications: &HashMap<String, Application>,
+    ) -> PermissionMatrix {
+        let mut permissions = permissions.clone();
+        for permission in permissions.permissions.values_mut() {
+            let Some(app) = applications.get(&permission.id) else {
+                continue;
+            };
+            permission.name = app.name.clone();
+            permission.fields = Access {
+                _type: permission.fields._type.clone(),
+                permissions: permission
+                    .fields
+                    .permissions
+                    .iter()
+                    .map(|(id, access)| {
+                        let field_name = app
+                            .fields
+                            .iter()
+                            .find(|field| &field_id(field) == id)
+                            .map_or(id.clone(), |field| field.name());
+                        (field_name, *access)
+                    })
+                    .collect(),
+            };
+        }
+        permissions
+    }
+
+    /// Returns the roles with their permissions named by application and field names, rather than ids
+    pub async fn get_normalised_roles(&self) -> Result<Vec<Role>, SwimlaneClientError> {
+        let roles_future = self.swimlane_client.get_roles();
+        let apps_future = self.swimlane_client.get_applications();
+
+        let roles = roles_future.await?;
+        let applications: HashMap<String, Application> = apps_future
+            .await?
+            .into_iter()
+            .map(|app| (app.id.clone(), app))
+            .collect();
+
+        Ok(roles
+            .into_iter()
+            .map(|mut role| {
+                role.permissions = self.normalise_permissions(&role.permissions, &applications);
+                role
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use swimlane::roles::{Permission, PermissionType};
+
+    use crate::fixtures;
+
+    use super::*;
+
+    fn permission(id: &str, fields: &[(&str, u16)]) -> Permission {
+        Permission {
+            _type: "permission".to_string(),
+            id: id.to_string(),
+            type_: PermissionType::Role,
+            name: id.to_string(),
+            access: 1,
+            fields: Access {
+                _type: "access".to_string(),
+                permissions: fields
+                    .iter()
+                    .map(|(field, access)| (field.to_string(), *access))
+                    .collect(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_permissions_are_named_by_application_and_field() {
+        let normaliser = SwimlaneResourceNormaliser::new(SwimlaneClient::new(
+            "https://swimlane.example.com".to_string(),
+            "pat".to_string(),
+        ));
+        let mut app = fixtures::application("a1", "Phishing");
+        app.fields = vec![fixtures::text_field("f1", "URL")];
+        let applications = HashMap::from([(app.id.clone(), app)]);
+        let permissions = PermissionMatrix {
+            _type: "PermissionMatrix".to_string(),
+            permissions: HashMap::from([
+                ("a1".to_string(), permission("a1", &[("f1", 1), ("f9", 2)])),
+                ("a9".to_string(), permission("a9", &[("f1", 1)])),
+            ]),
+        };
+
+        let normalised = normaliser.normalise_permissions(&permissions, &applications);
+
+        let phishing = &normalised.permissions["a1"];
+        assert_eq!(phishing.name, "Phishing");
+        assert_eq!(
+            phishing.fields.permissions,
+            HashMap::from([("URL".to_string(), 1), ("f9".to_string(), 2)])
+        );
+        // Applications which can't be found keep their ids
+        let missing = &normalised.permissions["a9"];
+        assert_eq!(missing.name, "a9");
+        assert_eq!(
+            missing.fields.permissions,
+            HashMap::from([("f1".to_string(), 1)])
+        );
+    }
 }
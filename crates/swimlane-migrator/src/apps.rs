@@ -1,9 +1,91 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
 use swimlane::apps::fields::Field;
 use swimlane::apps::layout::Layout;
 use swimlane::apps::Application;
+use swimlane::roles::PermissionMatrix;
 
 use crate::equality::{Difference, LooksLike};
-use crate::{MigrationPlan, SwimlaneMigrator, SwimlaneMigratorError};
+use crate::{
+    check_for_conflicts, MigrationPlan, ResourceType, SwimlaneMigrator, SwimlaneMigratorError,
+};
+
+macro_rules! field_types {
+    ($($variant:ident),*) => {
+        /// The name of the field's type, for example `SingleLineText`
+        pub fn field_type(field: &Field) -> &'static str {
+            match field {
+                $(Field::$variant(_) => stringify!($variant),)*
+            }
+        }
+
+        /// The id of the field, which every field type carries
+        pub(crate) fn field_id(field: &Field) -> String {
+            match field {
+                $(Field::$variant(field) => field.id.clone(),)*
+            }
+        }
+
+        fn rename_field(field: &mut Field, name: String) {
+            match field {
+                $(Field::$variant(field) => field.name = name,)*
+            }
+        }
+
+        fn set_field_id(field: &mut Field, id: String) {
+            match field {
+                $(Field::$variant(field) => field.id = id,)*
+            }
+        }
+    };
+}
+
+field_types!(
+    SingleLineText,
+    MultiLineText,
+    Email,
+    Telephone,
+    Url,
+    IpAddress,
+    RichText,
+    Json,
+    TextList,
+    Numeric,
+    NumericList,
+    DateTime,
+    Date,
+    Time,
+    TimeSpan,
+    FirstCreated,
+    LastUpdated,
+    SingleSelect,
+    MultiSelect,
+    RadioButtons,
+    Checkboxes,
+    SingleUserGroup,
+    MultiUserGroup,
+    CreatedBy,
+    LastUpdatedBy,
+    Correlation,
+    SingleReference,
+    MultiReference,
+    GridReference,
+    TrackingId,
+    Attachment,
+    Comments,
+    History
+);
+
+/// How a field which kept its name but changed type is migrated
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum FieldTypeChangeStrategy {
+    /// The migration is refused
+    #[default]
+    Refuse,
+    /// The existing field is renamed to keep its data, and a new field is created with the new type
+    RenameAndCreate,
+}
 
 impl LooksLike for Field {
     fn differences(&self, other: &Self) -> Vec<Difference> {
@@ -248,8 +330,18 @@ impl LooksLike for Vec<Field> {
             let other_field = other
                 .iter()
                 .find(|other_field| field.is_same_resource(other_field));
+            let renamed_field = other
+                .iter()
+                .find(|other_field| other_field.name() == field.name());
             if let Some(other_field) = other_field {
                 differences.extend(field.differences(other_field));
+            } else if let Some(renamed_field) = renamed_field {
+                differences.push(Difference::ChangingType {
+                    field: "fields".to_string(),
+                    item: field.name(),
+                    current_type: field_type(field).to_string(),
+                    new_type: field_type(renamed_field).to_string(),
+                });
             } else {
                 differences.push(Difference::AddingItem {
                     field: "fields".to_string(),
@@ -259,9 +351,9 @@ impl LooksLike for Vec<Field> {
         });
 
         other.iter().for_each(|field| {
-            let this_field = self
-                .iter()
-                .find(|this_field| field.is_same_resource(this_field));
+            let this_field = self.iter().find(|this_field| {
+                field.is_same_resource(this_field) || field.name() == this_field.name()
+            });
             if this_field.is_none() {
                 differences.push(Difference::RemovingItem {
                     field: "fields".to_string(),
@@ -331,86 +423,103 @@ impl LooksLike for Vec<Layout> {
     }
 }
 
-impl LooksLike for Application {
-    fn differences(&self, other: &Self) -> Vec<Difference> {
-        let mut differences = vec![];
-
-        // Add warning if the name is different
-        push_difference!(differences, "name", &self.name, &other.name);
-        push_difference!(differences, "acronym", &self.acronym, &other.acronym);
-        push_difference!(differences, "disabled", &self.disabled, &other.disabled);
-        push_difference!(differences, "description", &self.description, &other.description, optional: true);
-        push_difference!(
-            differences,
-            "time_tracking_enabled",
-            &self.time_tracking_enabled,
-            &other.time_tracking_enabled
-        );
-
-        differences.extend(self.fields.differences(&other.fields));
-        push_difference!(differences, "workspaces", &self.workspaces, &other.workspaces, str_vec: true);
-
-        if !self.layout.looks_like(&other.layout) {
-            differences.push(Difference::UpdatingComplexField {
-                field: "layout".to_string(),
-            });
-        }
-
-        differences.extend(self.permissions.differences(&other.permissions));
-
-        differences
-    }
-
-    fn is_same_resource(&self, other: &Self) -> bool {
-        self.name == other.name
-    }
+#[allow(dead_code)]
+#[derive(LooksLike)]
+#[looks_like(remote = "Application")]
+struct ApplicationDef {
+    #[looks_like(identity)]
+    name: String,
+    acronym: String,
+    disabled: bool,
+    description: Option<String>,
+    time_tracking_enabled: bool,
+    #[looks_like(nested)]
+    fields: Vec<Field>,
+    workspaces: Vec<String>,
+    #[looks_like(nested)]
+    layout: Vec<Layout>,
+    #[looks_like(nested)]
+    permissions: PermissionMatrix,
 }
 
 impl SwimlaneMigrator {
     pub async fn get_apps_to_migrate(
         &self,
     ) -> Result<Vec<MigrationPlan<Application>>, SwimlaneMigratorError> {
-        let source_apps_future = self.from.get_applications();
-        let target_apps_future = self.to.get_applications();
-        let source_workspace_hashmap = self.from_normaliser.get_workspace_hashmap();
-        let target_workspace_hashmap = self.to_normaliser.get_workspace_hashmap();
-
-        // normalise the apps
-        let source_apps = source_apps_future.await?;
-        let target_apps = target_apps_future.await?;
-        let source_workspace_hashmap = source_workspace_hashmap.await;
-        let target_workspace_hashmap = target_workspace_hashmap.await;
-
-        let source_apps = source_apps
-            .into_iter()
-            .map(|app| {
-                self.from_normaliser
-                    .normalise_application(&app, &source_workspace_hashmap)
-            })
-            .collect::<Vec<Application>>();
-
-        let target_apps = target_apps
-            .into_iter()
-            .map(|app| {
-                self.from_normaliser
-                    .normalise_application(&app, &target_workspace_hashmap)
-            })
-            .collect::<Vec<Application>>();
-
-        self._get_resources_to_migrate(source_apps, target_apps)
+        let source_apps = self.from.get_applications();
+        let target_apps = self.to.get_applications();
+
+        let source_apps = source_apps.await?;
+        let target_apps = target_apps.await?;
+
+        self._get_resources_to_migrate(ResourceType::Application, source_apps, target_apps)
     }
 
     pub async fn migrate_apps(&self) -> Result<(), SwimlaneMigratorError> {
         let plans = self.get_apps_to_migrate().await?;
+        self.apply_apps(plans).await
+    }
+
+    /// Creates the applications missing from the target without their fields or layout, and
+    /// renames the fields which changed type when `FieldTypeChangeStrategy::RenameAndCreate` is used.
+    pub async fn apply_apps(
+        &self,
+        plans: Vec<MigrationPlan<Application>>,
+    ) -> Result<(), SwimlaneMigratorError> {
+        check_for_conflicts(&plans)?;
+        self.check_for_destructive(ResourceType::Application, &plans)?;
+        if self.field_type_change == FieldTypeChangeStrategy::Refuse {
+            check_for_field_type_changes(&plans)?;
+        }
+
+        let mut applied = vec![];
         for plan in &plans {
             if let MigrationPlan::Create { source_resource } = plan {
                 let mut blank_app = source_resource.clone();
                 blank_app.fields = vec![];
                 blank_app.layout = vec![];
                 self.to.create_application(&blank_app).await?;
+                applied.push(source_resource);
             }
         }
-        Ok(())
+
+        let changes_field_types = plans.iter().any(|plan| {
+            matches!(plan, MigrationPlan::Update { differences, .. }
+                if differences.iter().any(|difference| matches!(difference, Difference::ChangingType { .. })))
+        });
+        if changes_field_types {
+            // The target's applications are updated as they're stored, rather than normalised
+            let raw_target_apps = self.to.get_raw_applications().await?;
+            for plan in &plans {
+                let MigrationPlan::Update {
+                    source_resource,
+                    target_resource,
+                    differences,
+                } = plan
+                else {
+                    continue;
+                };
+                let Some(target_app) = raw_target_apps
+                    .iter()
+                    .find(|app| app.id == target_resource.id)
+                else {
+                    continue;
+                };
+                if let Some(app) =
+                    rename_and_create_fields(source_resource, target_app, differences)
+                {
+                    self.to.update_application(&app).await?;
+                    applied.push(source_resource);
+                }
+            }
+        }
+
+        self.save_baseline(
+            ResourceType::Application,
+            &applied,
+            self.to.get_applications(),
+        )
+        .await
 
         // Create empty applications
         // Migrate fields
@@ -419,3 +528,168 @@ impl SwimlaneMigrator {
         // Migrate Layout
     }
 }
+
+/// Returns an error for the first field which kept its name but changed type
+pub(crate) fn check_for_field_type_changes(
+    plans: &[MigrationPlan<Application>],
+) -> Result<(), SwimlaneMigratorError> {
+    for plan in plans {
+        let MigrationPlan::Update {
+            source_resource,
+            differences,
+            ..
+        } = plan
+        else {
+            continue;
+        };
+        for difference in differences {
+            if let Difference::ChangingType {
+                item,
+                current_type,
+                new_type,
+                ..
+            } = difference
+            {
+                return Err(SwimlaneMigratorError::FieldTypeChanged {
+                    application: source_resource.name.clone(),
+                    field: item.clone(),
+                    from_type: new_type.clone(),
+                    to_type: current_type.clone(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A new random field id, in the same format as the ids Swimlane generates
+fn new_field_id() -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+    let mut id = String::new();
+    while id.len() < 17 {
+        let mut value = RandomState::new().build_hasher().finish();
+        while value > 0 && id.len() < 17 {
+            id.push(ALPHABET[(value % ALPHABET.len() as u64) as usize] as char);
+            value /= ALPHABET.len() as u64;
+        }
+    }
+    id
+}
+
+/// Renames the target's fields which changed type, so their data is kept, and adds the source's
+/// field alongside them with a new id. `target_app` is the application as stored on the target,
+/// rather than normalised. Returns None if no fields changed type.
+fn rename_and_create_fields(
+    source_app: &Application,
+    target_app: &Application,
+    differences: &[Difference],
+) -> Option<Application> {
+    let mut app = target_app.clone();
+    let mut changed = false;
+
+    for difference in differences {
+        let Difference::ChangingType { item, new_type, .. } = difference else {
+            continue;
+        };
+        let Some(target_field) = app.fields.iter_mut().find(|field| &field.name() == item) else {
+            continue;
+        };
+        rename_field(target_field, format!("{} ({})", item, new_type));
+        if let Some(source_field) = source_app.fields.iter().find(|field| &field.name() == item) {
+            let mut new_field = source_field.clone();
+            set_field_id(&mut new_field, new_field_id());
+            app.fields.push(new_field);
+        }
+        changed = true;
+    }
+
+    changed.then_some(app)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::MemoryBackend;
+    use crate::fixtures;
+    use crate::snapshot::Snapshot;
+
+    use super::*;
+
+    #[test]
+    fn test_field_type_change_is_refused() {
+        let plans = vec![MigrationPlan::Update {
+            source_resource: fixtures::application("app-1", "App 1"),
+            target_resource: fixtures::application("app-1", "App 1"),
+            differences: vec![Difference::ChangingType {
+                field: "fields".to_string(),
+                item: "Severity".to_string(),
+                current_type: "SingleSelect".to_string(),
+                new_type: "SingleLineText".to_string(),
+            }],
+        }];
+
+        match check_for_field_type_changes(&plans) {
+            Err(SwimlaneMigratorError::FieldTypeChanged {
+                from_type, to_type, ..
+            }) => {
+                assert_eq!(from_type, "SingleLineText");
+                assert_eq!(to_type, "SingleSelect");
+            }
+            _ => panic!("Expected the field type change to be refused"),
+        }
+    }
+
+    fn app(id: &str, fields: Vec<Field>) -> Application {
+        let mut app = fixtures::application(id, "App 1");
+        app.fields = fields;
+        app
+    }
+
+    #[tokio::test]
+    async fn test_field_type_change_renames_and_creates_fields() {
+        let severity_text = fixtures::text_field("source-field", "Severity");
+        let severity_select = fixtures::select_field("target-field", "Severity");
+
+        let backend = |name: &str, app: Application| {
+            let snapshot = Snapshot {
+                applications: vec![app],
+                ..Default::default()
+            };
+            MemoryBackend::new(name, snapshot)
+        };
+        let mut target_app = app("target-app", vec![severity_select]);
+        target_app.workspaces = vec!["workspace-id".to_string()];
+        let migrator = SwimlaneMigrator::new(
+            backend("source", app("source-app", vec![severity_text])),
+            backend("target", target_app),
+            false,
+        )
+        .unwrap()
+        .with_allow_destructive(true)
+        .with_field_type_change(FieldTypeChangeStrategy::RenameAndCreate);
+
+        let plans = migrator.get_apps_to_migrate().await.unwrap();
+        migrator.apply_apps(plans).await.unwrap();
+
+        let apps = migrator.to.get_applications().await.unwrap();
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0].id, "target-app");
+        assert_eq!(apps[0].workspaces, vec!["workspace-id".to_string()]);
+        let fields: Vec<_> = apps[0]
+            .fields
+            .iter()
+            .map(|field| (field_type(field), field.name(), field_id(field)))
+            .collect();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(
+            fields[0],
+            (
+                "SingleSelect",
+                "Severity (SingleSelect)".to_string(),
+                "target-field".to_string()
+            )
+        );
+        assert_eq!(fields[1].0, "SingleLineText");
+        assert_eq!(fields[1].1, "Severity");
+        assert!(!["", "source-field", "target-field"].contains(&fields[1].2.as_str()));
+    }
+}
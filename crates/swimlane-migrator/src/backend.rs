@@ -0,0 +1,474 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use swimlane::apps::Application;
+use swimlane::groups::Group;
+use swimlane::roles::Role;
+use swimlane::tasks::Task;
+use swimlane::users::{User, UserCreationRequest};
+use swimlane::workspaces::Workspace;
+
+use crate::adapt::SwimlaneResourceNormaliser;
+use crate::server::SwimlaneServer;
+use crate::snapshot::{Snapshot, SnapshotDirectory, COMMON_TASKS};
+use crate::tasks::{Asset, TaskReferences};
+use crate::workspaces::clone_workspaces;
+use crate::SwimlaneMigratorError;
+
+/// Somewhere Swimlane resources can be read from and written to, such as a live Swimlane server,
+/// a snapshot directory or an in-memory store
+///
+/// References to other resources are normalised to names, so resources read from different
+/// backends can be compared.
+#[async_trait]
+pub trait Backend: Send + Sync {
+    /// Where the resources are stored, for example the URL of the Swimlane server
+    fn location(&self) -> String;
+
+    async fn get_users(&self) -> Result<Vec<User>, SwimlaneMigratorError>;
+
+    async fn get_groups(&self) -> Result<Vec<Group>, SwimlaneMigratorError>;
+
+    /// Returns the roles with their permissions named by application and field names
+    async fn get_roles(&self) -> Result<Vec<Role>, SwimlaneMigratorError>;
+
+    /// Returns the applications with their references converted to names
+    async fn get_applications(&self) -> Result<Vec<Application>, SwimlaneMigratorError>;
+
+    /// Returns the applications as they're stored, so they can be modified and updated
+    async fn get_raw_applications(&self) -> Result<Vec<Application>, SwimlaneMigratorError> {
+        self.get_applications().await
+    }
+
+    async fn get_workspaces(&self) -> Result<Vec<Workspace>, SwimlaneMigratorError>;
+
+    /// Returns the tasks of every application, as well as the common tasks
+    async fn get_tasks(&self) -> Result<Vec<Task>, SwimlaneMigratorError>;
+
+    /// Returns the assets tasks can reference. Backends which can't list their assets return
+    /// none, so references to them are left as they are.
+    async fn get_assets(&self) -> Result<Vec<Asset>, SwimlaneMigratorError> {
+        Ok(vec![])
+    }
+
+    async fn create_user(&self, user: &User) -> Result<(), SwimlaneMigratorError>;
+
+    async fn update_user(&self, user: &User) -> Result<(), SwimlaneMigratorError>;
+
+    async fn delete_user(&self, user: &User) -> Result<(), SwimlaneMigratorError>;
+
+    async fn create_group(&self, group: &Group) -> Result<(), SwimlaneMigratorError>;
+
+    async fn create_application(&self, app: &Application) -> Result<(), SwimlaneMigratorError>;
+
+    async fn update_application(&self, app: &Application) -> Result<(), SwimlaneMigratorError>;
+
+    /// Prepares tasks whose application and fields are referenced by name to be created or
+    /// updated, such as by replacing the names with the backend's ids
+    async fn resolve_tasks(&self, tasks: Vec<Task>) -> Result<Vec<Task>, SwimlaneMigratorError> {
+        Ok(tasks)
+    }
+
+    /// Creates the task, prepared by `resolve_tasks`
+    async fn create_task(&self, task: &Task) -> Result<(), SwimlaneMigratorError>;
+
+    /// Updates the task, prepared by `resolve_tasks`
+    async fn update_task(&self, task: &Task) -> Result<(), SwimlaneMigratorError>;
+
+    async fn delete_task(&self, task: &Task) -> Result<(), SwimlaneMigratorError>;
+}
+
+#[async_trait]
+impl Backend for SwimlaneServer {
+    fn location(&self) -> String {
+        self.base_url.clone()
+    }
+
+    async fn get_users(&self) -> Result<Vec<User>, SwimlaneMigratorError> {
+        Ok(self.client().get_users().await?)
+    }
+
+    async fn get_groups(&self) -> Result<Vec<Group>, SwimlaneMigratorError> {
+        Ok(self.client().get_groups().await?)
+    }
+
+    async fn get_roles(&self) -> Result<Vec<Role>, SwimlaneMigratorError> {
+        let normaliser = SwimlaneResourceNormaliser::new(self.client().clone());
+        Ok(normaliser.get_normalised_roles().await?)
+    }
+
+    async fn get_applications(&self) -> Result<Vec<Application>, SwimlaneMigratorError> {
+        let normaliser = SwimlaneResourceNormaliser::new(self.client().clone());
+        Ok(normaliser.get_normalised_applications().await?)
+    }
+
+    async fn get_raw_applications(&self) -> Result<Vec<Application>, SwimlaneMigratorError> {
+        Ok(self.client().get_applications().await?)
+    }
+
+    async fn get_workspaces(&self) -> Result<Vec<Workspace>, SwimlaneMigratorError> {
+        Ok(self.client().get_workspaces().await?)
+    }
+
+    async fn get_tasks(&self) -> Result<Vec<Task>, SwimlaneMigratorError> {
+        let normaliser = SwimlaneResourceNormaliser::new(self.client().clone());
+        Ok(normaliser.get_normalised_tasks().await?)
+    }
+
+    async fn get_assets(&self) -> Result<Vec<Asset>, SwimlaneMigratorError> {
+        Ok(SwimlaneServer::get_assets(self).await?)
+    }
+
+    /// Replaces the names of the application and fields each task references with their ids.
+    /// The applications are fetched once for all of the tasks.
+    async fn resolve_tasks(&self, tasks: Vec<Task>) -> Result<Vec<Task>, SwimlaneMigratorError> {
+        let applications = self.client().get_applications().await?;
+        tasks
+            .iter()
+            .map(|task| {
+                if let Some(application_name) = &task.application_id {
+                    if !applications.iter().any(|app| &app.name == application_name) {
+                        return Err(SwimlaneMigratorError::MissingApplication {
+                            application_name: application_name.clone(),
+                        });
+                    }
+                }
+                Ok(TaskReferences::for_task(&applications, task).resolve(task)?)
+            })
+            .collect()
+    }
+
+    async fn create_user(&self, user: &User) -> Result<(), SwimlaneMigratorError> {
+        let user_creation_request = UserCreationRequest::from(user.clone());
+        self.client().create_user(&user_creation_request).await?;
+        Ok(())
+    }
+
+    async fn update_user(&self, user: &User) -> Result<(), SwimlaneMigratorError> {
+        self.client().update_user(user).await?;
+        Ok(())
+    }
+
+    async fn delete_user(&self, user: &User) -> Result<(), SwimlaneMigratorError> {
+        self.client().delete_user(&user.id).await?;
+        Ok(())
+    }
+
+    async fn create_group(&self, group: &Group) -> Result<(), SwimlaneMigratorError> {
+        self.client().create_group(group).await?;
+        Ok(())
+    }
+
+    async fn create_application(&self, app: &Application) -> Result<(), SwimlaneMigratorError> {
+        self.client().create_application(app).await?;
+        Ok(())
+    }
+
+    async fn update_application(&self, app: &Application) -> Result<(), SwimlaneMigratorError> {
+        SwimlaneServer::update_application(self, app).await?;
+        Ok(())
+    }
+
+    async fn create_task(&self, task: &Task) -> Result<(), SwimlaneMigratorError> {
+        SwimlaneServer::create_task(self, task).await?;
+        Ok(())
+    }
+
+    async fn update_task(&self, task: &Task) -> Result<(), SwimlaneMigratorError> {
+        SwimlaneServer::update_task(self, task).await?;
+        Ok(())
+    }
+
+    async fn delete_task(&self, task: &Task) -> Result<(), SwimlaneMigratorError> {
+        SwimlaneServer::delete_task(self, &task.id).await?;
+        Ok(())
+    }
+}
+
+/// Stores the resources in memory. Resources created without an id are given their name as
+/// their id, as they are when read from a snapshot directory.
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    name: String,
+    snapshot: Mutex<Snapshot>,
+    assets: Vec<Asset>,
+}
+
+impl MemoryBackend {
+    pub fn new(name: impl Into<String>, snapshot: Snapshot) -> Self {
+        Self {
+            name: name.into(),
+            snapshot: Mutex::new(snapshot),
+            assets: vec![],
+        }
+    }
+
+    /// Sets the assets the stored tasks can reference
+    pub fn with_assets(mut self, assets: Vec<Asset>) -> Self {
+        self.assets = assets;
+        self
+    }
+
+    /// Returns a copy of the stored resources
+    pub fn snapshot(&self) -> Snapshot {
+        self.snapshot.lock().unwrap().clone()
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&Snapshot) -> T) -> Result<T, SwimlaneMigratorError> {
+        Ok(f(&self.snapshot.lock().unwrap()))
+    }
+
+    fn write(&self, f: impl FnOnce(&mut Snapshot)) -> Result<(), SwimlaneMigratorError> {
+        f(&mut self.snapshot.lock().unwrap());
+        Ok(())
+    }
+}
+
+fn id_or_name(id: &str, name: &str) -> String {
+    match id.is_empty() {
+        true => name.to_string(),
+        false => id.to_string(),
+    }
+}
+
+#[async_trait]
+impl Backend for MemoryBackend {
+    fn location(&self) -> String {
+        format!("memory://{}", self.name)
+    }
+
+    async fn get_users(&self) -> Result<Vec<User>, SwimlaneMigratorError> {
+        self.read(|snapshot| snapshot.users.clone())
+    }
+
+    async fn get_groups(&self) -> Result<Vec<Group>, SwimlaneMigratorError> {
+        self.read(|snapshot| snapshot.groups.clone())
+    }
+
+    async fn get_roles(&self) -> Result<Vec<Role>, SwimlaneMigratorError> {
+        self.read(|snapshot| snapshot.roles.clone())
+    }
+
+    async fn get_applications(&self) -> Result<Vec<Application>, SwimlaneMigratorError> {
+        self.read(|snapshot| snapshot.applications.clone())
+    }
+
+    async fn get_workspaces(&self) -> Result<Vec<Workspace>, SwimlaneMigratorError> {
+        self.read(|snapshot| clone_workspaces(&snapshot.workspaces))
+    }
+
+    async fn get_tasks(&self) -> Result<Vec<Task>, SwimlaneMigratorError> {
+        self.read(|snapshot| snapshot.tasks.values().flatten().cloned().collect())
+    }
+
+    async fn get_assets(&self) -> Result<Vec<Asset>, SwimlaneMigratorError> {
+        Ok(self.assets.clone())
+    }
+
+    async fn create_user(&self, user: &User) -> Result<(), SwimlaneMigratorError> {
+        let mut user = user.clone();
+        user.id = id_or_name(&user.id, &user.user_name);
+        self.write(|snapshot| snapshot.users.push(user))
+    }
+
+    async fn update_user(&self, user: &User) -> Result<(), SwimlaneMigratorError> {
+        self.write(|snapshot| {
+            if let Some(existing) = snapshot.users.iter_mut().find(|u| u.id == user.id) {
+                *existing = user.clone();
+            }
+        })
+    }
+
+    async fn delete_user(&self, user: &User) -> Result<(), SwimlaneMigratorError> {
+        self.write(|snapshot| snapshot.users.retain(|u| u.id != user.id))
+    }
+
+    async fn create_group(&self, group: &Group) -> Result<(), SwimlaneMigratorError> {
+        let mut group = group.clone();
+        group.id = id_or_name(&group.id, &group.name);
+        self.write(|snapshot| snapshot.groups.push(group))
+    }
+
+    async fn create_application(&self, app: &Application) -> Result<(), SwimlaneMigratorError> {
+        let mut app = app.clone();
+        app.id = id_or_name(&app.id, &app.name);
+        self.write(|snapshot| snapshot.applications.push(app))
+    }
+
+    async fn update_application(&self, app: &Application) -> Result<(), SwimlaneMigratorError> {
+        self.write(|snapshot| {
+            if let Some(existing) = snapshot.applications.iter_mut().find(|a| a.id == app.id) {
+                *existing = app.clone();
+            }
+        })
+    }
+
+    async fn create_task(&self, task: &Task) -> Result<(), SwimlaneMigratorError> {
+        let mut task = task.clone();
+        task.id = id_or_name(&task.id, &task.name);
+        let folder = task
+            .application_id
+            .clone()
+            .unwrap_or(COMMON_TASKS.to_string());
+        self.write(|snapshot| snapshot.tasks.entry(folder).or_default().push(task))
+    }
+
+    async fn update_task(&self, task: &Task) -> Result<(), SwimlaneMigratorError> {
+        self.write(|snapshot| {
+            if let Some(existing) = snapshot
+                .tasks
+                .values_mut()
+                .flatten()
+                .find(|t| t.id == task.id)
+            {
+                *existing = task.clone();
+            }
+        })
+    }
+
+    async fn delete_task(&self, task: &Task) -> Result<(), SwimlaneMigratorError> {
+        self.write(|snapshot| {
+            for tasks in snapshot.tasks.values_mut() {
+                tasks.retain(|t| t.id != task.id);
+            }
+            snapshot.tasks.retain(|_, tasks| !tasks.is_empty());
+        })
+    }
+}
+
+/// Changes are made in memory, then the directory is re-written so it always reflects them
+#[async_trait]
+impl Backend for SnapshotDirectory {
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    async fn get_users(&self) -> Result<Vec<User>, SwimlaneMigratorError> {
+        self.store.get_users().await
+    }
+
+    async fn get_groups(&self) -> Result<Vec<Group>, SwimlaneMigratorError> {
+        self.store.get_groups().await
+    }
+
+    async fn get_roles(&self) -> Result<Vec<Role>, SwimlaneMigratorError> {
+        self.store.get_roles().await
+    }
+
+    async fn get_applications(&self) -> Result<Vec<Application>, SwimlaneMigratorError> {
+        self.store.get_applications().await
+    }
+
+    async fn get_workspaces(&self) -> Result<Vec<Workspace>, SwimlaneMigratorError> {
+        self.store.get_workspaces().await
+    }
+
+    async fn get_tasks(&self) -> Result<Vec<Task>, SwimlaneMigratorError> {
+        self.store.get_tasks().await
+    }
+
+    async fn get_assets(&self) -> Result<Vec<Asset>, SwimlaneMigratorError> {
+        self.store.get_assets().await
+    }
+
+    async fn create_user(&self, user: &User) -> Result<(), SwimlaneMigratorError> {
+        self.store.create_user(user).await?;
+        self.save()
+    }
+
+    async fn update_user(&self, user: &User) -> Result<(), SwimlaneMigratorError> {
+        self.store.update_user(user).await?;
+        self.save()
+    }
+
+    async fn delete_user(&self, user: &User) -> Result<(), SwimlaneMigratorError> {
+        self.store.delete_user(user).await?;
+        self.save()
+    }
+
+    async fn create_group(&self, group: &Group) -> Result<(), SwimlaneMigratorError> {
+        self.store.create_group(group).await?;
+        self.save()
+    }
+
+    async fn create_application(&self, app: &Application) -> Result<(), SwimlaneMigratorError> {
+        self.store.create_application(app).await?;
+        self.save()
+    }
+
+    async fn update_application(&self, app: &Application) -> Result<(), SwimlaneMigratorError> {
+        self.store.update_application(app).await?;
+        self.save()
+    }
+
+    async fn create_task(&self, task: &Task) -> Result<(), SwimlaneMigratorError> {
+        self.store.create_task(task).await?;
+        self.save()
+    }
+
+    async fn update_task(&self, task: &Task) -> Result<(), SwimlaneMigratorError> {
+        self.store.update_task(task).await?;
+        self.save()
+    }
+
+    async fn delete_task(&self, task: &Task) -> Result<(), SwimlaneMigratorError> {
+        self.store.delete_task(task).await?;
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{MigrationPlan, SwimlaneMigrator};
+
+    use super::*;
+
+    fn group(name: &str) -> Group {
+        Group {
+            id: name.to_string(),
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn backend(name: &str, groups: Vec<Group>) -> MemoryBackend {
+        let snapshot = Snapshot {
+            groups,
+            ..Default::default()
+        };
+        MemoryBackend::new(name, snapshot)
+    }
+
+    #[tokio::test]
+    async fn test_migrates_groups_between_memory_backends() {
+        let source = backend("source", vec![group("Group 1"), group("Group 2")]);
+        let target = backend("target", vec![group("Group 2")]);
+        let migrator = SwimlaneMigrator::new(source, target, false).unwrap();
+
+        let plans = migrator.get_groups_to_migrate().await.unwrap();
+        assert_eq!(plans.len(), 1);
+        assert!(
+            matches!(&plans[0], MigrationPlan::Create { source_resource } if source_resource.name == "Group 1")
+        );
+
+        migrator.apply_groups(plans).await.unwrap();
+
+        let mut names: Vec<_> = migrator
+            .to
+            .get_groups()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|group| group.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["Group 1", "Group 2"]);
+        assert!(migrator.get_groups_to_migrate().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_identical_locations_are_refused() {
+        let result = SwimlaneMigrator::new(backend("a", vec![]), backend("a", vec![]), false);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use tempfile::TempDir;
+
+use crate::snapshot::{Snapshot, SnapshotError, SnapshotFormat};
+
+/// The extension of backup archives
+pub const BACKUP_EXTENSION: &str = "tar.gz";
+
+/// The name of the manifest within a backup archive
+pub(crate) const MANIFEST: &str = "manifest.json";
+
+/// Describes the contents of a backup, so backups can be listed without being extracted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// The URL of the Swimlane server which was backed up
+    pub url: String,
+    pub created: DateTime<Utc>,
+    /// The number of each type of resource in the backup
+    pub counts: BTreeMap<String, usize>,
+}
+
+impl BackupManifest {
+    fn new(url: &str, snapshot: &Snapshot) -> Self {
+        let counts = [
+            ("applications", snapshot.applications.len()),
+            ("roles", snapshot.roles.len()),
+            ("groups", snapshot.groups.len()),
+            ("users", snapshot.users.len()),
+            ("workspaces", snapshot.workspaces.len()),
+            ("tasks", snapshot.tasks.values().map(Vec::len).sum()),
+            ("pip_packages", snapshot.pip_packages.len()),
+        ]
+        .into_iter()
+        .map(|(name, count)| (name.to_string(), count))
+        .collect();
+        Self {
+            url: url.to_string(),
+            created: Utc::now(),
+            counts,
+        }
+    }
+}
+
+/// A compressed archive of a snapshot, alongside a manifest describing it
+#[derive(Debug, Clone)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub manifest: BackupManifest,
+}
+
+impl Backup {
+    /// Writes the snapshot to a new archive in the directory, named after the time it was created.
+    /// The resources are written raw, with their ids and dates, so they're restored as they were.
+    pub fn create(dir: &Path, url: &str, snapshot: &Snapshot) -> Result<Self, SnapshotError> {
+        let manifest = BackupManifest::new(url, snapshot);
+        let path = dir.join(format!(
+            "swimlane-{}.{}",
+            manifest.created.format("%Y%m%dT%H%M%S%.3fZ"),
+            BACKUP_EXTENSION
+        ));
+
+        let staging = staging_dir(&path)?;
+        snapshot.write_raw(staging.path(), SnapshotFormat::Json)?;
+        std::fs::write(
+            staging.path().join(MANIFEST),
+            serde_json::to_string_pretty(&manifest)?,
+        )?;
+
+        std::fs::create_dir_all(dir)?;
+        let encoder = GzEncoder::new(File::create(&path)?, Compression::default());
+        let mut archive = tar::Builder::new(encoder);
+        archive.append_dir_all(".", staging.path())?;
+        archive.into_inner()?.finish()?;
+
+        Ok(Self { path, manifest })
+    }
+
+    /// Reads the manifest of an archive
+    pub fn open(path: &Path) -> Result<Self, SnapshotError> {
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(path)?));
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if entry.path()?.file_name() == Some(MANIFEST.as_ref()) {
+                let mut contents = String::new();
+                entry.read_to_string(&mut contents)?;
+                return Ok(Self {
+                    path: path.to_path_buf(),
+                    manifest: serde_json::from_str(&contents)?,
+                });
+            }
+        }
+        Err(SnapshotError::MissingManifest(path.to_path_buf()))
+    }
+
+    /// Returns the backups in the directory, oldest first. Archives which can't be read are
+    /// skipped and returned alongside them.
+    pub fn list(dir: &Path) -> Result<BackupList, SnapshotError> {
+        let mut list = BackupList::default();
+        if !dir.is_dir() {
+            return Ok(list);
+        }
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let is_backup = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(&format!(".{}", BACKUP_EXTENSION)));
+            if !is_backup {
+                continue;
+            }
+            match Self::open(&path) {
+                Ok(backup) => list.backups.push(backup),
+                Err(error) => list.unreadable.push((path, error)),
+            }
+        }
+        list.backups.sort_by_key(|backup| backup.manifest.created);
+        Ok(list)
+    }
+
+    /// Removes all but the newest `keep` backups in the directory, returning the removed backups.
+    /// Archives which can't be read are left in place and returned alongside them.
+    pub fn prune(dir: &Path, keep: usize) -> Result<BackupList, SnapshotError> {
+        let mut list = Self::list(dir)?;
+        let removed = list.backups.len().saturating_sub(keep);
+        list.backups.drain(removed..);
+        for backup in &list.backups {
+            std::fs::remove_file(&backup.path)?;
+        }
+        Ok(list)
+    }
+
+    /// Extracts the snapshot from the archive
+    pub fn snapshot(&self) -> Result<Snapshot, SnapshotError> {
+        let staging = staging_dir(&self.path)?;
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(&self.path)?));
+        archive.unpack(staging.path())?;
+        Snapshot::read(staging.path())
+    }
+}
+
+/// The backups in a directory, and the archives which couldn't be read
+#[derive(Debug, Default)]
+pub struct BackupList {
+    pub backups: Vec<Backup>,
+    pub unreadable: Vec<(PathBuf, SnapshotError)>,
+}
+
+/// An empty temporary directory the snapshot is written to or extracted to, which is removed
+/// when it's dropped, even if writing or extracting the snapshot failed
+fn staging_dir(archive: &Path) -> Result<TempDir, SnapshotError> {
+    let name = archive
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Ok(tempfile::Builder::new()
+        .prefix(&format!("{}-", name))
+        .tempdir()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use swimlane::groups::Group;
+
+    use super::*;
+
+    #[test]
+    fn test_backups_round_trip_and_prune() {
+        let dir = std::env::temp_dir().join(format!("swimlane-backups-{}", std::process::id()));
+        let snapshot = Snapshot {
+            groups: vec![Group {
+                id: "aBc123".to_string(),
+                name: "Group 1".to_string(),
+                created_date: "2023-08-01T00:00:00Z".parse().unwrap(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let first = Backup::create(&dir, "https://swimlane.example.com", &snapshot).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let second = Backup::create(&dir, "https://swimlane.example.com", &snapshot).unwrap();
+
+        let backups = Backup::list(&dir).unwrap().backups;
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0].manifest, first.manifest);
+        assert_eq!(backups[0].manifest.counts["groups"], 1);
+        let restored = backups[1].snapshot().unwrap();
+        assert_eq!(restored.groups[0].name, "Group 1");
+        assert_eq!(restored.groups[0].id, "aBc123");
+        assert_eq!(
+            restored.groups[0].created_date,
+            snapshot.groups[0].created_date
+        );
+
+        let removed = Backup::prune(&dir, 1).unwrap().backups;
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].path, first.path);
+        assert_eq!(Backup::list(&dir).unwrap().backups[0].path, second.path);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_unreadable_backups_are_skipped() {
+        let dir = std::env::temp_dir().join(format!("swimlane-unreadable-{}", std::process::id()));
+        let backup =
+            Backup::create(&dir, "https://swimlane.example.com", &Snapshot::default()).unwrap();
+        let corrupt = dir.join(format!("swimlane-corrupt.{}", BACKUP_EXTENSION));
+        std::fs::write(&corrupt, "not an archive").unwrap();
+
+        let list = Backup::list(&dir).unwrap();
+        assert_eq!(list.backups.len(), 1);
+        assert_eq!(list.backups[0].path, backup.path);
+        assert_eq!(list.unreadable.len(), 1);
+        assert_eq!(list.unreadable[0].0, corrupt);
+
+        let pruned = Backup::prune(&dir, 0).unwrap();
+        assert_eq!(pruned.backups.len(), 1);
+        assert!(corrupt.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
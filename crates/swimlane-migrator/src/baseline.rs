@@ -0,0 +1,320 @@
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::path::PathBuf;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::equality::{Difference, LooksLike};
+use crate::rules::ResourceDiffRules;
+use crate::{MigrationPlan, ResourceType, SwimlaneMigrator, SwimlaneMigratorError};
+
+/// A snapshot of the target's resources, taken after the last successful migration.
+///
+/// Comparing the target against the baseline shows which changes were made directly on the
+/// target, rather than migrated from the source.
+#[derive(Debug, Clone)]
+pub struct Baseline {
+    path: PathBuf,
+}
+
+impl Baseline {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn file_path(&self, resource_type: ResourceType) -> PathBuf {
+        self.path.join(format!("{}s.json", resource_type))
+    }
+
+    /// Returns the resources recorded for the resource type, or None if a migration of the
+    /// resource type hasn't completed yet
+    pub fn load<T: DeserializeOwned>(
+        &self,
+        resource_type: ResourceType,
+    ) -> Result<Option<Vec<T>>, SwimlaneMigratorError> {
+        let file_path = self.file_path(resource_type);
+        if !file_path.exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(&file_path)?;
+        Ok(Some(serde_json::from_str(&contents)?))
+    }
+
+    pub fn save<T: Serialize>(
+        &self,
+        resource_type: ResourceType,
+        resources: &[T],
+    ) -> Result<(), SwimlaneMigratorError> {
+        std::fs::create_dir_all(&self.path)?;
+        let contents = serde_json::to_string_pretty(resources)?;
+        std::fs::write(self.file_path(resource_type), contents)?;
+        Ok(())
+    }
+}
+
+/// A change made directly on the target since the baseline was taken
+#[derive(Debug, Clone, PartialEq)]
+pub enum Drift {
+    /// The resource was created on the target
+    Created,
+    /// The resource was modified on the target
+    Modified { differences: Vec<Difference> },
+    /// The resource was deleted from the target
+    Deleted,
+}
+
+impl Display for Drift {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Drift::Created => write!(f, "created on the target"),
+            Drift::Modified { .. } => write!(f, "modified on the target"),
+            Drift::Deleted => write!(f, "deleted from the target"),
+        }
+    }
+}
+
+/// Marks the plans which would overwrite a change made directly on the target as conflicts
+pub fn detect_drift<T: LooksLike + Clone>(
+    plans: Vec<MigrationPlan<T>>,
+    baseline_resources: &[T],
+    rules: &ResourceDiffRules,
+) -> Vec<MigrationPlan<T>> {
+    let find_baseline = |resource: &T| {
+        baseline_resources
+            .iter()
+            .find(|baseline_resource| baseline_resource.is_same_resource(resource))
+    };
+    let drift_from_baseline = |target_resource: &T| match find_baseline(target_resource) {
+        None => Some(Drift::Created),
+        Some(baseline_resource) => {
+            let differences = target_resource.differences_with_rules(baseline_resource, rules);
+            match differences.is_empty() {
+                true => None,
+                false => Some(Drift::Modified { differences }),
+            }
+        }
+    };
+
+    plans
+        .into_iter()
+        .map(|plan| {
+            let drift = match &plan {
+                MigrationPlan::Create { source_resource } => {
+                    find_baseline(source_resource).map(|_| Drift::Deleted)
+                }
+                MigrationPlan::Update {
+                    target_resource, ..
+                }
+                | MigrationPlan::Delete { target_resource } => drift_from_baseline(target_resource),
+                MigrationPlan::Conflict { .. } => None,
+            };
+            match drift {
+                Some(drift) => MigrationPlan::Conflict {
+                    plan: Box::new(plan),
+                    drift,
+                },
+                None => plan,
+            }
+        })
+        .collect()
+}
+
+/// Advances the baseline for the resources which were written to the target, and keeps the
+/// previous entry of every other resource. Skipped conflicts, and plans which can't be applied
+/// yet, leave the target unchanged, so their differences are still reported on the next migration.
+pub fn advance_baseline<T: LooksLike>(
+    baseline_resources: Vec<T>,
+    target_resources: Vec<T>,
+    applied: &[&T],
+) -> Vec<T> {
+    let is_applied = |resource: &T| {
+        applied
+            .iter()
+            .any(|applied| applied.is_same_resource(resource))
+    };
+
+    let mut resources: Vec<T> = baseline_resources
+        .into_iter()
+        .filter(|resource| !is_applied(resource))
+        .collect();
+    resources.extend(
+        target_resources
+            .into_iter()
+            .filter(|resource| is_applied(resource)),
+    );
+    resources
+}
+
+impl SwimlaneMigrator {
+    pub(crate) fn apply_baseline<T: LooksLike + Clone + DeserializeOwned>(
+        &self,
+        resource_type: ResourceType,
+        plans: Vec<MigrationPlan<T>>,
+        rules: &ResourceDiffRules,
+    ) -> Result<Vec<MigrationPlan<T>>, SwimlaneMigratorError> {
+        let Some(baseline) = &self.baseline else {
+            return Ok(plans);
+        };
+        match baseline.load::<T>(resource_type)? {
+            Some(baseline_resources) => Ok(detect_drift(plans, &baseline_resources, rules)),
+            None => Ok(plans),
+        }
+    }
+
+    /// Records the state of the target after the plans were applied as the baseline for the next
+    /// migration. The first baseline records every resource on the target, later ones are only
+    /// advanced for the `applied` resources, which were written to the target.
+    pub(crate) async fn save_baseline<T, E, Fut>(
+        &self,
+        resource_type: ResourceType,
+        applied: &[&T],
+        target_resource_getter: Fut,
+    ) -> Result<(), SwimlaneMigratorError>
+    where
+        T: LooksLike + Serialize + DeserializeOwned,
+        Fut: Future<Output = Result<Vec<T>, E>>,
+        SwimlaneMigratorError: From<E>,
+    {
+        let Some(baseline) = &self.baseline else {
+            return Ok(());
+        };
+        let target_resources = target_resource_getter.await?;
+        let resources = match baseline.load::<T>(resource_type)? {
+            Some(baseline_resources) => {
+                advance_baseline(baseline_resources, target_resources, applied)
+            }
+            None => target_resources,
+        };
+        baseline.save(resource_type, &resources)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use swimlane::groups::Group;
+
+    use crate::backend::MemoryBackend;
+    use crate::snapshot::Snapshot;
+
+    use super::*;
+
+    fn group(name: &str, description: &str) -> Group {
+        Group {
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_update_of_unchanged_target_is_not_a_conflict() {
+        let plans = vec![MigrationPlan::Update {
+            source_resource: group("Group 1", "new"),
+            target_resource: group("Group 1", "old"),
+            differences: vec![],
+        }];
+        let baseline = vec![group("Group 1", "old")];
+
+        let plans = detect_drift(plans, &baseline, &ResourceDiffRules::default());
+        assert!(matches!(plans[0], MigrationPlan::Update { .. }));
+    }
+
+    #[test]
+    fn test_update_of_modified_target_is_a_conflict() {
+        let plans = vec![MigrationPlan::Update {
+            source_resource: group("Group 1", "new"),
+            target_resource: group("Group 1", "hotfix"),
+            differences: vec![],
+        }];
+        let baseline = vec![group("Group 1", "old")];
+
+        let plans = detect_drift(plans, &baseline, &ResourceDiffRules::default());
+        match &plans[0] {
+            MigrationPlan::Conflict { drift, .. } => {
+                assert!(matches!(drift, Drift::Modified { differences } if differences.len() == 1))
+            }
+            _ => panic!("Expected a conflict"),
+        }
+    }
+
+    #[test]
+    fn test_delete_of_resource_created_on_target_is_a_conflict() {
+        let plans = vec![MigrationPlan::Delete {
+            target_resource: group("Group 1", ""),
+        }];
+
+        let plans = detect_drift(plans, &[], &ResourceDiffRules::default());
+        assert!(matches!(
+            plans[0],
+            MigrationPlan::Conflict {
+                drift: Drift::Created,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_create_of_resource_deleted_from_target_is_a_conflict() {
+        let plans = vec![MigrationPlan::Create {
+            source_resource: group("Group 1", ""),
+        }];
+        let baseline = vec![group("Group 1", "")];
+
+        let plans = detect_drift(plans, &baseline, &ResourceDiffRules::default());
+        assert!(matches!(
+            plans[0],
+            MigrationPlan::Conflict {
+                drift: Drift::Deleted,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_declined_conflict_is_reported_again() {
+        let path = std::env::temp_dir().join(format!("swimlane-baseline-{}", std::process::id()));
+        let baseline = Baseline::new(&path);
+        baseline
+            .save(ResourceType::Group, &[group("Group 1", "old")])
+            .unwrap();
+        let backend = |name: &str, groups: Vec<Group>| {
+            let snapshot = Snapshot {
+                groups,
+                ..Default::default()
+            };
+            MemoryBackend::new(name, snapshot)
+        };
+        let migrator = SwimlaneMigrator::new(
+            backend(
+                "source",
+                vec![group("Group 1", "new"), group("Group 2", "")],
+            ),
+            backend("target", vec![group("Group 1", "hotfix")]),
+            false,
+        )
+        .unwrap()
+        .with_baseline(baseline.clone());
+
+        for _ in 0..2 {
+            let plans = migrator.get_groups_to_migrate().await.unwrap();
+            assert!(plans.iter().any(|plan| matches!(
+                plan,
+                MigrationPlan::Conflict { plan, .. } if plan.resource().name == "Group 1"
+            )));
+            let plans = plans
+                .into_iter()
+                .filter_map(|plan| plan.resolve(false))
+                .collect();
+            migrator.apply_groups(plans).await.unwrap();
+        }
+
+        let baseline_groups: Vec<Group> = baseline.load(ResourceType::Group).unwrap().unwrap();
+        std::fs::remove_dir_all(&path).unwrap();
+        let group_1 = baseline_groups
+            .iter()
+            .find(|g| g.name == "Group 1")
+            .unwrap();
+        assert_eq!(group_1.description.as_deref(), Some("old"));
+        assert!(baseline_groups.iter().any(|g| g.name == "Group 2"));
+    }
+}
@@ -0,0 +1,218 @@
+use serde::Serialize;
+use swimlane::SwimlaneClient;
+
+use crate::adapt::SwimlaneResourceNormaliser;
+use crate::equality::{Difference, LooksLike};
+use crate::rules::{DiffRules, ResourceDiffRules};
+use crate::{ResourceType, SwimlaneMigratorError};
+
+/// The differences between the resources of a single type on two Swimlane servers
+#[derive(Debug, Serialize)]
+pub struct ResourceDiff {
+    pub resource_type: ResourceType,
+    pub only_in_a: Vec<String>,
+    pub only_in_b: Vec<String>,
+    pub changed: Vec<ChangedResource>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangedResource {
+    pub name: String,
+    pub differences: Vec<Difference>,
+}
+
+impl ResourceDiff {
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compares two lists of resources. Unlike a migration plan, neither side is treated as the source of truth.
+/// Resources are reported by the name `name` gives them.
+pub fn diff_resources<T: LooksLike>(
+    resource_type: ResourceType,
+    a: &[T],
+    b: &[T],
+    rules: &ResourceDiffRules,
+    name: impl Fn(&T) -> String,
+) -> ResourceDiff {
+    let mut only_in_a = vec![];
+    let mut changed = vec![];
+
+    for a_resource in a {
+        match b
+            .iter()
+            .find(|b_resource| b_resource.is_same_resource(a_resource))
+        {
+            Some(b_resource) => {
+                let differences = a_resource.differences_with_rules(b_resource, rules);
+                if !differences.is_empty() {
+                    changed.push(ChangedResource {
+                        name: name(a_resource),
+                        differences,
+                    });
+                }
+            }
+            None => only_in_a.push(name(a_resource)),
+        }
+    }
+
+    let only_in_b = b
+        .iter()
+        .filter(|b_resource| {
+            !a.iter()
+                .any(|a_resource| a_resource.is_same_resource(b_resource))
+        })
+        .map(&name)
+        .collect();
+
+    ResourceDiff {
+        resource_type,
+        only_in_a,
+        only_in_b,
+        changed,
+    }
+}
+
+/// Compares the resources on two Swimlane servers without making any changes to either
+pub struct SwimlaneDiffer {
+    pub a: SwimlaneClient,
+    pub b: SwimlaneClient,
+    pub diff_rules: DiffRules,
+    a_normaliser: SwimlaneResourceNormaliser,
+    b_normaliser: SwimlaneResourceNormaliser,
+}
+
+impl SwimlaneDiffer {
+    pub fn new(a: SwimlaneClient, b: SwimlaneClient) -> Self {
+        let a_normaliser = SwimlaneResourceNormaliser::new(a.clone());
+        let b_normaliser = SwimlaneResourceNormaliser::new(b.clone());
+        Self {
+            a,
+            b,
+            diff_rules: DiffRules::default(),
+            a_normaliser,
+            b_normaliser,
+        }
+    }
+
+    /// Sets the rules used to ignore or normalise differences
+    pub fn with_diff_rules(mut self, diff_rules: DiffRules) -> Self {
+        self.diff_rules = diff_rules;
+        self
+    }
+
+    pub async fn diff(
+        &self,
+        resource_type: ResourceType,
+    ) -> Result<ResourceDiff, SwimlaneMigratorError> {
+        let rules = self.diff_rules.rules_for(resource_type);
+        let diff = match resource_type {
+            ResourceType::User => {
+                let (a, b) = (self.a.get_users(), self.b.get_users());
+                diff_resources(resource_type, &a.await?, &b.await?, &rules, |user| {
+                    user.user_name.clone()
+                })
+            }
+            ResourceType::Group => {
+                let (a, b) = (self.a.get_groups(), self.b.get_groups());
+                diff_resources(resource_type, &a.await?, &b.await?, &rules, |group| {
+                    group.name.clone()
+                })
+            }
+            ResourceType::Role => {
+                let (a, b) = (
+                    self.a_normaliser.get_normalised_roles(),
+                    self.b_normaliser.get_normalised_roles(),
+                );
+                diff_resources(resource_type, &a.await?, &b.await?, &rules, |role| {
+                    role.name.clone()
+                })
+            }
+            ResourceType::Application => {
+                let (a, b) = (
+                    self.a_normaliser.get_normalised_applications(),
+                    self.b_normaliser.get_normalised_applications(),
+                );
+                diff_resources(resource_type, &a.await?, &b.await?, &rules, |app| {
+                    app.name.clone()
+                })
+            }
+            ResourceType::Workspace => {
+                let (a, b) = (self.a.get_workspaces(), self.b.get_workspaces());
+                diff_resources(resource_type, &a.await?, &b.await?, &rules, |workspace| {
+                    workspace.name.clone()
+                })
+            }
+            ResourceType::Task => {
+                let (a, b) = (
+                    self.a_normaliser.get_normalised_tasks(),
+                    self.b_normaliser.get_normalised_tasks(),
+                );
+                // Tasks are named within their application, whose name they're normalised to
+                diff_resources(
+                    resource_type,
+                    &a.await?,
+                    &b.await?,
+                    &rules,
+                    |task| match &task.application_id {
+                        Some(application) => format!("{}/{}", application, task.name),
+                        None => task.name.clone(),
+                    },
+                )
+            }
+        };
+        Ok(diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use swimlane::groups::Group;
+
+    use super::*;
+
+    fn group(name: &str, disabled: bool) -> Group {
+        Group {
+            name: name.to_string(),
+            disabled,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff_resources_reports_each_side() {
+        let a = vec![group("Group 1", false), group("Group 2", false)];
+        let b = vec![group("Group 2", true), group("Group 3", false)];
+
+        let diff = diff_resources(
+            ResourceType::Group,
+            &a,
+            &b,
+            &ResourceDiffRules::default(),
+            |group| group.name.clone(),
+        );
+
+        assert_eq!(diff.only_in_a, vec!["Group 1".to_string()]);
+        assert_eq!(diff.only_in_b, vec!["Group 3".to_string()]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].name, "Group 2");
+        assert_eq!(diff.changed[0].differences.len(), 1);
+    }
+
+    #[test]
+    fn test_diff_resources_identical_is_empty() {
+        let a = vec![group("Group 1", false)];
+        let b = vec![group("Group 1", false)];
+
+        let diff = diff_resources(
+            ResourceType::Group,
+            &a,
+            &b,
+            &ResourceDiffRules::default(),
+            |group| group.name.clone(),
+        );
+
+        assert!(diff.is_empty());
+    }
+}
@@ -1,8 +1,14 @@
 use std::fmt::{Display, Formatter};
 
+use serde::Serialize;
 use swimlane::BaseEntity;
 
-#[derive(Debug, Clone, PartialEq)]
+use crate::rules::ResourceDiffRules;
+
+pub use swimlane_migrator_derive::LooksLike;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum Difference {
     UpdatingField {
         field: String,
@@ -17,6 +23,19 @@ pub enum Difference {
         field: String,
         item: String,
     },
+    /// An item which kept its name but changed type, such as an application field
+    ChangingType {
+        field: String,
+        item: String,
+        current_type: String,
+        new_type: String,
+    },
+    /// A script which changed, such as the script of a python task
+    UpdatingScript {
+        field: String,
+        current_script: String,
+        new_script: String,
+    },
     /// Used for when the field is a complex type. For example the application type,
     /// where it wouldn't be possible to display the difference coherently.
     UpdatingComplexField {
@@ -24,80 +43,18 @@ pub enum Difference {
     },
 }
 
-macro_rules! push_difference {
-    ($differences:expr, $field:literal , $current_value:expr, $new_value:expr) => {
-        if $current_value != $new_value {
-            $differences.push(Difference::UpdatingField {
-                field: $field.to_string(),
-                current_value: $current_value.to_string(),
-                new_value: $new_value.to_string(),
-            });
-        }
-    };
-    ($differences:expr, $field:literal, $current_value:expr, $new_value:expr, optional: true) => {
-        if $current_value != $new_value {
-            $differences.push(Difference::UpdatingField {
-                field: $field.to_string(),
-                current_value: $current_value
-                    .clone()
-                    .map_or("".to_string(), |v| v.to_string()),
-                new_value: $new_value.clone().map_or("".to_string(), |v| v.to_string()),
-            });
+impl Difference {
+    /// The path of the field the difference applies to
+    pub fn field(&self) -> &str {
+        match self {
+            Difference::UpdatingField { field, .. }
+            | Difference::AddingItem { field, .. }
+            | Difference::RemovingItem { field, .. }
+            | Difference::ChangingType { field, .. }
+            | Difference::UpdatingScript { field, .. }
+            | Difference::UpdatingComplexField { field } => field,
         }
-    };
-    ($differences:expr, $field:literal, $source_vec:expr, $target_vec:expr, vec: true) => {
-        $differences.extend($source_vec.iter().filter_map(|item| {
-            let item_exists = $target_vec
-                .iter()
-                .find(|other_item| item.is_same_resource(other_item));
-            match item_exists {
-                Some(_) => None,
-                None => Some(Difference::AddingItem {
-                    field: $field.to_string(),
-                    item: item.name.clone(),
-                }),
-            }
-        }));
-        $differences.extend($target_vec.iter().filter_map(|item| {
-            let item_exists = $source_vec
-                .iter()
-                .find(|other_item| item.is_same_resource(other_item));
-            match item_exists {
-                Some(_) => None,
-                None => Some(Difference::RemovingItem {
-                    field: $field.to_string(),
-                    item: item.name.clone(),
-                }),
-            }
-        }));
-    };
-
-    ($differences:expr, $field:literal, $source_vec:expr, $target_vec:expr, str_vec: true) => {
-        $differences.extend($source_vec.iter().filter_map(|item| {
-            let item_exists = $target_vec
-                .iter()
-                .find(|other_item| item.is_same_resource(other_item));
-            match item_exists {
-                Some(_) => None,
-                None => Some(Difference::AddingItem {
-                    field: $field.to_string(),
-                    item: item.clone(),
-                }),
-            }
-        }));
-        $differences.extend($target_vec.iter().filter_map(|item| {
-            let item_exists = $source_vec
-                .iter()
-                .find(|other_item| item.is_same_resource(other_item));
-            match item_exists {
-                Some(_) => None,
-                None => Some(Difference::RemovingItem {
-                    field: $field.to_string(),
-                    item: item.clone(),
-                }),
-            }
-        }));
-    };
+    }
 }
 
 impl Display for Difference {
@@ -114,6 +71,19 @@ impl Display for Difference {
             Difference::RemovingItem { field, item } => {
                 write!(f, "-{}: {}", field, item)
             }
+            Difference::ChangingType {
+                field,
+                item,
+                current_type,
+                new_type,
+            } => write!(
+                f,
+                "~{}: {} changes type '{}' -> '{}'",
+                field, item, new_type, current_type
+            ),
+            Difference::UpdatingScript { field, .. } => {
+                write!(f, "{} will be updated", field)
+            }
             Difference::UpdatingComplexField { field } => {
                 write!(f, "{} will be updated", field)
             }
@@ -121,6 +91,11 @@ impl Display for Difference {
     }
 }
 
+/// Compares two Swimlane resources.
+///
+/// Rather than implementing this by hand, structs can use `#[derive(LooksLike)]`. Types from
+/// the `swimlane` crate can be covered by deriving on a mirror struct marked with
+/// `#[looks_like(remote = "...")]`.
 pub trait LooksLike {
     fn differences(&self, other: &Self) -> Vec<Difference>;
 
@@ -171,6 +146,16 @@ pub trait LooksLike {
         self.differences(other).is_empty()
     }
 
+    /// The differences between the two objects, excluding those which the rules ignore
+    fn differences_with_rules(&self, other: &Self, rules: &ResourceDiffRules) -> Vec<Difference> {
+        rules.apply(self.differences(other))
+    }
+
+    /// Whether the two objects have no differences once the rules have been applied
+    fn looks_like_with_rules(&self, other: &Self, rules: &ResourceDiffRules) -> bool {
+        self.differences_with_rules(other, rules).is_empty()
+    }
+
     /// Whether the two objects are the same resource
     /// For example,
     ///
@@ -238,3 +223,112 @@ impl LooksLike for BaseEntity {
         self.name == other.name
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(LooksLike, Default)]
+    struct Resource {
+        #[looks_like(identity)]
+        name: String,
+        #[looks_like(rename = "enabled")]
+        active: bool,
+        description: Option<String>,
+        tags: Vec<String>,
+        #[looks_like(by_name)]
+        members: Vec<BaseEntity>,
+        #[looks_like(complex)]
+        avatar: Option<String>,
+        #[looks_like(skip)]
+        id: String,
+    }
+
+    #[test]
+    fn test_derive_identity_determines_same_resource() {
+        let a = Resource {
+            name: "a".to_string(),
+            id: "1".to_string(),
+            ..Default::default()
+        };
+        let b = Resource {
+            name: "a".to_string(),
+            id: "2".to_string(),
+            ..Default::default()
+        };
+        let c = Resource {
+            name: "c".to_string(),
+            ..Default::default()
+        };
+
+        assert_ne!(a.id, b.id);
+        assert!(a.is_same_resource(&b));
+        assert!(!a.is_same_resource(&c));
+        assert!(a.looks_like(&b));
+    }
+
+    #[test]
+    fn test_derive_field_differences() {
+        let a = Resource {
+            active: true,
+            description: Some("Description".to_string()),
+            avatar: Some("abc".to_string()),
+            ..Default::default()
+        };
+        let b = Resource::default();
+
+        assert_eq!(
+            a.differences(&b),
+            vec![
+                Difference::UpdatingField {
+                    field: "enabled".to_string(),
+                    current_value: "true".to_string(),
+                    new_value: "false".to_string(),
+                },
+                Difference::UpdatingField {
+                    field: "description".to_string(),
+                    current_value: "Description".to_string(),
+                    new_value: "".to_string(),
+                },
+                Difference::UpdatingComplexField {
+                    field: "avatar".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_derive_list_differences() {
+        let a = Resource {
+            tags: vec!["tag 1".to_string()],
+            members: vec![BaseEntity {
+                id: "1".to_string(),
+                name: "Member 1".to_string(),
+                disabled: false,
+            }],
+            ..Default::default()
+        };
+        let b = Resource {
+            tags: vec!["tag 2".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            a.differences(&b),
+            vec![
+                Difference::AddingItem {
+                    field: "tags".to_string(),
+                    item: "tag 1".to_string(),
+                },
+                Difference::RemovingItem {
+                    field: "tags".to_string(),
+                    item: "tag 2".to_string(),
+                },
+                Difference::AddingItem {
+                    field: "members".to_string(),
+                    item: "Member 1".to_string(),
+                },
+            ]
+        );
+    }
+}
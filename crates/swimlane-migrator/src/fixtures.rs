@@ -0,0 +1,170 @@
+//! Resources for tests. Most of the `swimlane` types don't implement `Default`, so they're
+//! deserialised from the fewest keys they can be read from, and tests set the fields they need.
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use swimlane::apps::fields::Field;
+use swimlane::apps::{Application, LightApplication};
+use swimlane::roles::Role;
+use swimlane::tasks::Task;
+use swimlane::users::User;
+use swimlane::workspaces::Workspace;
+
+const DATE: &str = "2023-08-01T00:00:00Z";
+
+fn from_json<T: DeserializeOwned>(value: Value) -> T {
+    serde_json::from_value(value).unwrap()
+}
+
+pub(crate) fn user(user_name: &str) -> User {
+    from_json(json!({
+        "id": user_name,
+        "userName": user_name,
+        "email": format!("{}@example.com", user_name),
+        "name": user_name,
+        "disabled": false,
+        "favorites": {},
+        "active": true,
+        "lastPasswordChangedDate": DATE,
+        "passwordResetRequired": false,
+        "sessionTimeoutType": "minutes",
+        "groups": [],
+        "roles": [],
+        "createdDate": DATE,
+        "modifiedDate": DATE,
+        "isSystemUser": false,
+        "timeZoneId": "UTC",
+        "isOTPVerified": false,
+        "isOtpUser": false,
+        "isOtpEnforced": false,
+        "isOtpExempted": false,
+        "isLdapUser": false,
+        "isLocked": false,
+        "currentFailedLogInAttempts": 0
+    }))
+}
+
+pub(crate) fn role(id: &str, name: &str) -> Role {
+    from_json(json!({
+        "id": id,
+        "name": name,
+        "disabled": false,
+        "permissions": {"$type": "PermissionMatrix"},
+        "createdDate": DATE,
+        "modifiedDate": DATE,
+        "groups": [],
+        "users": []
+    }))
+}
+
+pub(crate) fn application(id: &str, name: &str) -> Application {
+    from_json(json!({
+        "$type": "Application",
+        "id": id,
+        "name": name,
+        "acronym": "",
+        "trackingFieldId": "",
+        "layout": [],
+        "fields": [],
+        "maxTrackingId": 0.0,
+        "workspaces": [],
+        "createWorkspace": false,
+        "createdDate": DATE,
+        "createdByUser": {},
+        "modifiedDate": DATE,
+        "modifiedByUser": {},
+        "timeTrackingEnabled": false,
+        "permissions": {"$type": "PermissionMatrix"},
+        "uid": id,
+        "version": 1,
+        "disabled": false
+    }))
+}
+
+pub(crate) fn light_application(id: &str, name: &str) -> LightApplication {
+    from_json(json!({
+        "$type": "LightApplication",
+        "id": id,
+        "name": name,
+        "acronym": "",
+        "createdDate": DATE,
+        "createdByUser": {},
+        "modifiedDate": DATE,
+        "modifiedByUser": {}
+    }))
+}
+
+pub(crate) fn workspace(id: &str, name: &str) -> Workspace {
+    from_json(json!({
+        "$type": "Workspace",
+        "id": id,
+        "uid": id,
+        "version": 1,
+        "name": name,
+        "disabled": false,
+        "applications": [],
+        "dashboards": [],
+        "modifiedDate": DATE,
+        "createdDate": DATE,
+        "permissions": {"$type": "PermissionMatrix"}
+    }))
+}
+
+/// A field of the type, with the keys every type of field has as well as its own
+fn field(id: &str, name: &str, field_type: &str, keys: Value) -> Field {
+    let mut value = json!({
+        "$type": "Field",
+        "id": id,
+        "name": name,
+        "key": name,
+        "supportsMultipleOutputMappings": false,
+        "required": false,
+        "readOnly": false,
+        "fieldType": field_type
+    });
+    if let (Value::Object(map), Value::Object(keys)) = (&mut value, keys) {
+        map.extend(keys);
+    }
+    from_json(value)
+}
+
+pub(crate) fn text_field(id: &str, name: &str) -> Field {
+    let keys = json!({
+        "prefix": "",
+        "suffix": "",
+        "placeholder": "",
+        "inputType": "text",
+        "lengthType": "none",
+        "unique": false,
+        "visualize": false,
+        "visualizeMode": 0
+    });
+    field(id, name, "text", keys)
+}
+
+pub(crate) fn select_field(id: &str, name: &str) -> Field {
+    let keys = json!({"values": [], "controlType": "select", "selectionType": "single"});
+    field(id, name, "valuesList", keys)
+}
+
+/// A Python task, which belongs to the application if it has one
+pub(crate) fn task(id: &str, name: &str, application_id: Option<&str>) -> Task {
+    from_json(json!({
+        "id": id,
+        "name": name,
+        "valid": true,
+        "disabled": false,
+        "applicationId": application_id,
+        "action": {
+            "readonly": false,
+            "type": "script",
+            "descriptor": {
+                "id": id,
+                "name": name,
+                "disabled": false,
+                "actionType": "python",
+                "base64Image": ""
+            }
+        }
+    }))
+}
@@ -1,29 +1,27 @@
 use std::collections::HashMap;
 
-use crate::equality::{Difference, LooksLike};
-use crate::{MigrationPlan, SwimlaneMigrator, SwimlaneMigratorError};
+use crate::equality::LooksLike;
+use crate::{
+    check_for_conflicts, MigrationPlan, ResourceType, SwimlaneMigrator, SwimlaneMigratorError,
+};
 
 use swimlane::groups::Group;
-
-impl LooksLike for Group {
-    /// Hello world
-    fn differences(&self, other: &Self) -> Vec<Difference> {
-        let mut differences = vec![];
-
-        // Add warning if the name is different
-        push_difference!(differences, "name", &self.name, &other.name);
-        push_difference!(differences, "disabled", &self.disabled, &other.disabled);
-        push_difference!(differences, "description", &self.description, &other.description, optional: true);
-        push_difference!(differences, "users", &self.users, &other.users, vec: true);
-        push_difference!(differences, "roles", &self.roles, &other.roles, vec: true);
-        push_difference!(differences, "groups", &self.groups, &other.groups, vec: true);
-
-        differences
-    }
-
-    fn is_same_resource(&self, other: &Self) -> bool {
-        self.name == other.name
-    }
+use swimlane::BaseEntity;
+
+#[allow(dead_code)]
+#[derive(LooksLike)]
+#[looks_like(remote = "Group")]
+struct GroupDef {
+    #[looks_like(identity)]
+    name: String,
+    disabled: bool,
+    description: Option<String>,
+    #[looks_like(by_name)]
+    users: Vec<BaseEntity>,
+    #[looks_like(by_name)]
+    roles: Vec<BaseEntity>,
+    #[looks_like(by_name)]
+    groups: Vec<BaseEntity>,
 }
 
 impl SwimlaneMigrator {
@@ -33,8 +31,12 @@ impl SwimlaneMigrator {
         let source_groups_future = self.from.get_groups();
         let target_groups_future = self.to.get_groups();
 
-        self.get_resources_to_migrate(source_groups_future, target_groups_future)
-            .await
+        self.get_resources_to_migrate(
+            ResourceType::Group,
+            source_groups_future,
+            target_groups_future,
+        )
+        .await
     }
 
     // Migrate group
@@ -70,12 +72,23 @@ impl SwimlaneMigrator {
     // }
 
     pub async fn migrate_groups(&self) -> Result<(), SwimlaneMigratorError> {
-        let groups_to_migrate = self.get_groups_to_migrate();
+        let groups_to_migrate = self.get_groups_to_migrate().await?;
+        self.apply_groups(groups_to_migrate).await
+    }
+
+    /// Creates the groups missing from the target, with their nested groups, roles and users
+    /// mapped to the target's ids. Updates are only listed.
+    pub async fn apply_groups(
+        &self,
+        groups_to_migrate: Vec<MigrationPlan<Group>>,
+    ) -> Result<(), SwimlaneMigratorError> {
+        check_for_conflicts(&groups_to_migrate)?;
+        self.check_for_destructive(ResourceType::Group, &groups_to_migrate)?;
+
         let group_id_hashmap = self.get_group_id_hashmap();
         let user_id_hashmap = self.get_user_id_hashmap();
         let role_id_hashmap = self.get_role_id_hashmap();
 
-        let groups_to_migrate = groups_to_migrate.await?;
         let group_id_hashmap = group_id_hashmap.await?;
         let user_id_hashmap = user_id_hashmap.await?;
         let role_id_hashmap = role_id_hashmap.await?;
@@ -142,6 +155,7 @@ impl SwimlaneMigrator {
                 MigrationPlan::Update {
                     source_resource,
                     target_resource,
+                    ..
                 } => {
                     println!(
                         "  {} (update) - {}",
@@ -152,7 +166,14 @@ impl SwimlaneMigrator {
             }
         }
 
-        Ok(())
+        // Only the created groups were written to the target
+        let created: Vec<&Group> = groups_to_migrate
+            .iter()
+            .filter(|plan| matches!(plan, MigrationPlan::Create { .. }))
+            .map(MigrationPlan::resource)
+            .collect();
+        self.save_baseline(ResourceType::Group, &created, self.to.get_groups())
+            .await
     }
 
     /// Adapts a group from a source system to a target system
@@ -163,7 +184,7 @@ impl SwimlaneMigrator {
     ///
     /// ```rust
     /// use swimlane_migrator::SwimlaneMigrator;
-    /// use swimlane::SwimlaneClient;
+    /// use swimlane_migrator::server::SwimlaneServer;
     /// use swimlane::groups::Group;
     /// use std::collections::HashMap;
     ///
@@ -178,9 +199,9 @@ impl SwimlaneMigrator {
     ///
     /// let role_id_hashmap = HashMap::new();
     ///
-    /// let source_swimlane = SwimlaneClient::new("https://source.swimlane.com".to_string(), "source_api_key".to_string());
+    /// let source_swimlane = SwimlaneServer::new("https://source.swimlane.com".to_string(), "source_api_key".to_string());
     ///
-    /// let target_swimlane = SwimlaneClient::new("https://target.swimlane.com".to_string(), "target_api_key".to_string());
+    /// let target_swimlane = SwimlaneServer::new("https://target.swimlane.com".to_string(), "target_api_key".to_string());
     ///
     /// let migrator = SwimlaneMigrator::new(source_swimlane, target_swimlane, false).expect("Failed to create migrator");
     /// migrator.adapt_group(&mut group, &group_id_hashmap, &user_id_hashmap, &role_id_hashmap);
@@ -220,7 +241,7 @@ impl SwimlaneMigrator {
 
 #[cfg(test)]
 mod tests {
-    use swimlane::BaseEntity;
+    use crate::equality::Difference;
 
     use super::*;
 
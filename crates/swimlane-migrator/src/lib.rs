@@ -1,50 +1,98 @@
+extern crate self as swimlane_migrator;
+
 mod adapt;
-#[macro_use]
-pub mod equality;
 pub mod apps;
+pub mod backend;
+pub mod backup;
+pub mod baseline;
+pub mod diff;
+pub mod equality;
+#[cfg(test)]
+mod fixtures;
 pub mod groups;
+pub mod native;
+pub mod overlay;
+pub mod redact;
+pub mod risk;
 pub mod roles;
+pub mod rules;
+pub mod server;
+pub mod snapshot;
+pub mod tasks;
+pub mod template;
 pub mod users;
 pub mod util;
+pub mod workspaces;
+
+use std::fmt::{Display, Formatter};
 
-use equality::LooksLike;
-use swimlane::SwimlaneClient;
+use backend::Backend;
+use baseline::{Baseline, Drift};
+use equality::{Difference, LooksLike};
+use rules::DiffRules;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 pub struct SwimlaneMigrator {
-    pub from: SwimlaneClient,
-    pub to: SwimlaneClient,
+    pub from: Box<dyn Backend>,
+    pub to: Box<dyn Backend>,
     pub dry_run: bool,
-    from_normaliser: adapt::SwimlaneResourceNormaliser,
-    to_normaliser: adapt::SwimlaneResourceNormaliser,
+    pub diff_rules: DiffRules,
+    pub baseline: Option<Baseline>,
+    pub allow_destructive: bool,
+    pub field_type_change: apps::FieldTypeChangeStrategy,
 }
 
 impl SwimlaneMigrator {
     pub fn new(
-        from: SwimlaneClient,
-        to: SwimlaneClient,
+        from: impl Backend + 'static,
+        to: impl Backend + 'static,
         dry_run: bool,
     ) -> Result<Self, SwimlaneMigratorNewError> {
-        if from.base_url == to.base_url {
+        if from.location() == to.location() {
             return Err(SwimlaneMigratorNewError::SourceAndDestinationAreIdentical);
         }
-        let from_normaliser = adapt::SwimlaneResourceNormaliser::new(from.clone());
-        let to_normaliser = adapt::SwimlaneResourceNormaliser::new(to.clone());
         Ok(SwimlaneMigrator {
-            from,
-            to,
+            from: Box::new(from),
+            to: Box::new(to),
             dry_run,
-            from_normaliser,
-            to_normaliser,
+            diff_rules: DiffRules::default(),
+            baseline: None,
+            allow_destructive: false,
+            field_type_change: apps::FieldTypeChangeStrategy::default(),
         })
     }
+
+    /// Sets the rules used to ignore or normalise differences when planning a migration
+    pub fn with_diff_rules(mut self, diff_rules: DiffRules) -> Self {
+        self.diff_rules = diff_rules;
+        self
+    }
+
+    /// Sets the baseline used to detect changes made directly on the target. The baseline is
+    /// updated after each successful migration.
+    pub fn with_baseline(mut self, baseline: Baseline) -> Self {
+        self.baseline = Some(baseline);
+        self
+    }
+
+    /// Sets whether plans which destroy data on the target, such as removing an application field,
+    /// may be applied
+    pub fn with_allow_destructive(mut self, allow_destructive: bool) -> Self {
+        self.allow_destructive = allow_destructive;
+        self
+    }
+
+    /// Sets how application fields which kept their name but changed type are migrated
+    pub fn with_field_type_change(mut self, strategy: apps::FieldTypeChangeStrategy) -> Self {
+        self.field_type_change = strategy;
+        self
+    }
 }
 
 #[derive(Error, Debug)]
 pub enum SwimlaneMigratorNewError {
-    #[error(
-        "The source and target Swimlane servers are the same. Please specify different servers."
-    )]
+    #[error("The source and target are the same. Please specify a different source or target.")]
     SourceAndDestinationAreIdentical,
 }
 
@@ -58,6 +106,34 @@ pub enum SwimlaneMigratorError {
     MissingRole { role_name: String },
     #[error("User not found: {user_name}")]
     MissingUser { user_name: String },
+    #[error("Application not found: {application_name}")]
+    MissingApplication { application_name: String },
+    #[error("Asset not found: {asset_name}")]
+    MissingAsset { asset_name: String },
+    #[error("Workspace not found: {workspace} (used by {application_name})")]
+    MissingWorkspace {
+        workspace: String,
+        application_name: String,
+    },
+    #[error("{count} resource(s) were changed on the target since the last migration and must be resolved first")]
+    UnresolvedConflicts { count: usize },
+    #[error("{count} resource(s) would have data destroyed on the target and destructive changes are not allowed")]
+    DestructiveChanges { count: usize },
+    #[error("The field {field} in {application} would change type from {from_type} to {to_type}")]
+    FieldTypeChanged {
+        application: String,
+        field: String,
+        from_type: String,
+        to_type: String,
+    },
+    #[error("Could not read or write the snapshot")]
+    SnapshotError(#[from] snapshot::SnapshotError),
+    #[error("Could not substitute the environment's values")]
+    TemplateError(#[from] template::TemplateError),
+    #[error("Could not read or write the baseline")]
+    BaselineIoError(#[from] std::io::Error),
+    #[error("Could not serialise or deserialise the baseline")]
+    BaselineSerdeError(#[from] serde_json::Error),
 }
 
 pub enum MigrationPlan<T: LooksLike> {
@@ -67,8 +143,76 @@ pub enum MigrationPlan<T: LooksLike> {
     Update {
         source_resource: T,
         target_resource: T,
+        differences: Vec<Difference>,
     },
     Delete {
         target_resource: T,
     },
+    /// The plan would overwrite a change made directly on the target since the last migration
+    Conflict {
+        plan: Box<MigrationPlan<T>>,
+        drift: Drift,
+    },
+}
+
+impl<T: LooksLike> MigrationPlan<T> {
+    /// The resource the plan applies to
+    pub fn resource(&self) -> &T {
+        match self {
+            MigrationPlan::Create { source_resource } => source_resource,
+            MigrationPlan::Update {
+                source_resource, ..
+            } => source_resource,
+            MigrationPlan::Delete { target_resource } => target_resource,
+            MigrationPlan::Conflict { plan, .. } => plan.resource(),
+        }
+    }
+
+    /// Resolves a conflict by either applying the plan anyway, or skipping the resource
+    pub fn resolve(self, overwrite: bool) -> Option<Self> {
+        match self {
+            MigrationPlan::Conflict { plan, .. } => overwrite.then_some(*plan),
+            plan => Some(plan),
+        }
+    }
+}
+
+/// Returns an error if any of the plans are unresolved conflicts
+pub fn check_for_conflicts<T: LooksLike>(
+    plans: &[MigrationPlan<T>],
+) -> Result<(), SwimlaneMigratorError> {
+    let count = plans
+        .iter()
+        .filter(|plan| matches!(plan, MigrationPlan::Conflict { .. }))
+        .count();
+    match count {
+        0 => Ok(()),
+        count => Err(SwimlaneMigratorError::UnresolvedConflicts { count }),
+    }
+}
+
+/// The types of resource which can be compared and migrated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceType {
+    User,
+    Group,
+    Role,
+    Application,
+    Workspace,
+    Task,
+}
+
+impl Display for ResourceType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ResourceType::User => "user",
+            ResourceType::Group => "group",
+            ResourceType::Role => "role",
+            ResourceType::Application => "application",
+            ResourceType::Workspace => "workspace",
+            ResourceType::Task => "task",
+        };
+        write!(f, "{}", name)
+    }
 }
@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use swimlane::apps::Application;
+
+use crate::apps::{check_for_field_type_changes, field_id};
+use crate::snapshot::{file_name, SnapshotError};
+use crate::{
+    check_for_conflicts, MigrationPlan, ResourceType, SwimlaneMigrator, SwimlaneMigratorError,
+};
+
+/// Reads applications in the format the Swimlane UI exports them, which is the application
+/// definition as returned by the API. A file may contain a single application or a list of them.
+pub fn read_native_applications(path: &Path) -> Result<Vec<Application>, SnapshotError> {
+    let contents = std::fs::read_to_string(path)?;
+    match serde_json::from_str(&contents)? {
+        Value::Array(apps) => apps
+            .into_iter()
+            .map(|app| Ok(serde_json::from_value(app)?))
+            .collect(),
+        app => Ok(vec![serde_json::from_value(app)?]),
+    }
+}
+
+/// Writes the application to `{name}.json` in the directory, in the format the Swimlane UI
+/// exports it, so it can be imported through the UI. Unlike a snapshot, ids are kept.
+pub fn write_native_application(dir: &Path, app: &Application) -> Result<PathBuf, SnapshotError> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}.json", file_name(&app.name)));
+    std::fs::write(&path, serde_json::to_string_pretty(app)? + "\n")?;
+    Ok(path)
+}
+
+impl SwimlaneMigrator {
+    /// Plans the import of the source's applications, read with `read_native_applications`.
+    /// Applications on the target which aren't being imported are left alone, so deletions are
+    /// never planned. Fails if an application is in a workspace which isn't on the target.
+    pub async fn get_native_apps_to_import(
+        &self,
+    ) -> Result<Vec<MigrationPlan<Application>>, SwimlaneMigratorError> {
+        let workspace_names: HashMap<String, String> = self
+            .to
+            .get_workspaces()
+            .await?
+            .into_iter()
+            .map(|workspace| (workspace.id, workspace.name))
+            .collect();
+        let source_apps: Vec<Application> = self
+            .from
+            .get_applications()
+            .await?
+            .into_iter()
+            .map(|app| map_workspaces(app, &workspace_names))
+            .collect();
+        for app in &source_apps {
+            let unmapped = app
+                .workspaces
+                .iter()
+                .find(|workspace| !workspace_names.values().any(|name| &name == workspace));
+            if let Some(workspace) = unmapped {
+                return Err(SwimlaneMigratorError::MissingWorkspace {
+                    workspace: workspace.clone(),
+                    application_name: app.name.clone(),
+                });
+            }
+        }
+        let target_apps = self.to.get_applications().await?;
+
+        let plans =
+            self._get_resources_to_migrate(ResourceType::Application, source_apps, target_apps)?;
+        Ok(plans
+            .into_iter()
+            .filter(|plan| !matches!(plan, MigrationPlan::Delete { .. }))
+            .collect())
+    }
+
+    /// Creates or replaces the definitions of the applications on the target. Fields which kept
+    /// their name but changed type are always refused, as the definition replaces the field.
+    /// When replacing a definition, the ids of the application and of the fields found on the
+    /// target by name are swapped for the target's, so the fields keep their data.
+    pub async fn import_native_apps(
+        &self,
+        plans: Vec<MigrationPlan<Application>>,
+    ) -> Result<(), SwimlaneMigratorError> {
+        check_for_conflicts(&plans)?;
+        self.check_for_destructive(ResourceType::Application, &plans)?;
+        check_for_field_type_changes(&plans)?;
+
+        let workspace_ids: HashMap<String, String> = self
+            .to
+            .get_workspaces()
+            .await?
+            .into_iter()
+            .map(|workspace| (workspace.name, workspace.id))
+            .collect();
+        let has_updates = plans
+            .iter()
+            .any(|plan| matches!(plan, MigrationPlan::Update { .. }));
+        let raw_target_apps = match has_updates {
+            true => self.to.get_raw_applications().await?,
+            false => vec![],
+        };
+
+        for plan in plans {
+            match plan {
+                MigrationPlan::Create { source_resource } => {
+                    let app = map_workspaces(source_resource, &workspace_ids);
+                    self.to.create_application(&app).await?;
+                }
+                MigrationPlan::Update {
+                    source_resource,
+                    target_resource,
+                    ..
+                } => {
+                    let target_app = raw_target_apps
+                        .iter()
+                        .find(|app| app.id == target_resource.id)
+                        .unwrap_or(&target_resource);
+                    let app = map_workspaces(source_resource, &workspace_ids);
+                    let app = map_ids(app, target_app)?;
+                    self.to.update_application(&app).await?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Replaces the workspaces found in the hashmap, keeping any which aren't, such as the ids of
+/// workspaces on the instance the application was exported from
+fn map_workspaces(mut app: Application, workspaces: &HashMap<String, String>) -> Application {
+    app.workspaces = app
+        .workspaces
+        .iter()
+        .map(|workspace| workspaces.get(workspace).unwrap_or(workspace).clone())
+        .collect();
+    app
+}
+
+/// Replaces the ids of the application, and of each field with the same name as one of the
+/// target's, with the target's ids wherever they're referenced in the definition, such as in
+/// the layout. Fields which aren't on the target keep the ids they were exported with.
+fn map_ids(
+    app: Application,
+    target_app: &Application,
+) -> Result<Application, SwimlaneMigratorError> {
+    let mut ids = HashMap::from([(app.id.clone(), target_app.id.clone())]);
+    for field in &app.fields {
+        let target_field = target_app
+            .fields
+            .iter()
+            .find(|target_field| target_field.name() == field.name());
+        if let Some(target_field) = target_field {
+            ids.insert(field_id(field), field_id(target_field));
+        }
+    }
+
+    let mut definition = serde_json::to_value(app)?;
+    replace_ids(&mut definition, &ids);
+    Ok(serde_json::from_value(definition)?)
+}
+
+fn replace_ids(value: &mut Value, ids: &HashMap<String, String>) {
+    match value {
+        Value::String(string) => {
+            if let Some(id) = ids.get(string.as_str()) {
+                *string = id.clone();
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(|value| replace_ids(value, ids)),
+        Value::Object(map) => map.values_mut().for_each(|value| replace_ids(value, ids)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::MemoryBackend;
+    use crate::fixtures;
+    use crate::snapshot::Snapshot;
+
+    use super::*;
+
+    fn app(name: &str, workspaces: &[&str]) -> Application {
+        let mut app = fixtures::application(&format!("{} id", name), name);
+        app.workspaces = workspaces.iter().map(|w| w.to_string()).collect();
+        app
+    }
+
+    #[test]
+    fn test_reads_a_single_application_or_a_list() {
+        let dir = std::env::temp_dir().join(format!("swimlane-native-{}", std::process::id()));
+        let path = write_native_application(&dir, &app("Phishing", &[])).unwrap();
+        let single = read_native_applications(&path).unwrap();
+
+        let list = serde_json::to_string(&vec![app("Phishing", &[]), app("Malware", &[])]).unwrap();
+        std::fs::write(&path, list).unwrap();
+        let list = read_native_applications(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(single[0].id, "Phishing id");
+        assert_eq!(list.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_import_leaves_other_applications_alone() {
+        let source = MemoryBackend::new(
+            "phishing.json",
+            Snapshot {
+                applications: vec![app("Phishing", &["ws1"])],
+                ..Default::default()
+            },
+        );
+        let target = MemoryBackend::new(
+            "target",
+            Snapshot {
+                applications: vec![app("Malware", &["SOC"])],
+                workspaces: vec![fixtures::workspace("ws1", "SOC")],
+                ..Default::default()
+            },
+        );
+        let migrator = SwimlaneMigrator::new(source, target, false).unwrap();
+
+        let plans = migrator.get_native_apps_to_import().await.unwrap();
+        assert_eq!(plans.len(), 1);
+        assert!(
+            matches!(&plans[0], MigrationPlan::Create { source_resource } if source_resource.workspaces == ["SOC"])
+        );
+
+        migrator.import_native_apps(plans).await.unwrap();
+        let apps = migrator.to.get_applications().await.unwrap();
+        assert_eq!(apps.len(), 2);
+        assert_eq!(apps[1].workspaces, ["ws1"]);
+    }
+
+    #[tokio::test]
+    async fn test_import_keeps_the_ids_of_the_targets_fields() {
+        let mut vendor_app = app("Phishing", &[]);
+        vendor_app.fields = vec![
+            fixtures::text_field("vendor-url", "URL"),
+            fixtures::text_field("vendor-new", "New"),
+        ];
+        vendor_app.tracking_field_id = "vendor-url".to_string();
+        let mut target_app = app("Phishing", &[]);
+        target_app.id = "target-app".to_string();
+        target_app.fields = vec![fixtures::text_field("target-url", "URL")];
+        target_app.tracking_field_id = "target-url".to_string();
+
+        let migrator = SwimlaneMigrator::new(
+            MemoryBackend::new(
+                "phishing.json",
+                Snapshot {
+                    applications: vec![vendor_app],
+                    ..Default::default()
+                },
+            ),
+            MemoryBackend::new(
+                "target",
+                Snapshot {
+                    applications: vec![target_app],
+                    ..Default::default()
+                },
+            ),
+            false,
+        )
+        .unwrap();
+
+        let plans = migrator.get_native_apps_to_import().await.unwrap();
+        assert!(matches!(&plans[0], MigrationPlan::Update { .. }));
+        migrator.import_native_apps(plans).await.unwrap();
+
+        let apps = migrator.to.get_applications().await.unwrap();
+        assert_eq!(apps[0].id, "target-app");
+        assert_eq!(apps[0].tracking_field_id, "target-url");
+        let field_ids: Vec<String> = apps[0].fields.iter().map(field_id).collect();
+        assert_eq!(field_ids, ["target-url", "vendor-new"]);
+    }
+
+    #[tokio::test]
+    async fn test_import_into_a_missing_workspace_fails() {
+        let migrator = SwimlaneMigrator::new(
+            MemoryBackend::new(
+                "phishing.json",
+                Snapshot {
+                    applications: vec![app("Phishing", &["vendor-ws"])],
+                    ..Default::default()
+                },
+            ),
+            MemoryBackend::new("target", Snapshot::default()),
+            false,
+        )
+        .unwrap();
+
+        match migrator.get_native_apps_to_import().await {
+            Err(SwimlaneMigratorError::MissingWorkspace { workspace, .. }) => {
+                assert_eq!(workspace, "vendor-ws")
+            }
+            _ => panic!("Expected the missing workspace to be reported"),
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! Overlays patch the files of a base snapshot, so environments which share most of their
+//! configuration can keep only their differences
+//!
+//! An overlay has the same layout as a snapshot, and each file patches the base file of the same
+//! name, or adds a resource if there isn't one:
+//!
+//! - Objects are merged recursively, and a `null` value removes the key. A file containing only
+//!   `null` removes the resource.
+//! - Lists of named items, such as fields or group members, are merged by name. An item of
+//!   `{"name": ..., "$patch": "delete"}` removes the item.
+//! - Any other value replaces the base value.
+
+use std::collections::HashSet;
+
+use serde_json::{Map, Value};
+
+use crate::snapshot::SnapshotFiles;
+
+const PATCH_KEY: &str = "$patch";
+const DELETE: &str = "delete";
+
+/// The keys which name the items of a list
+const NAME_KEYS: &[&str] = &["name", "userName"];
+
+/// Patches the files with each file of the overlay
+pub(crate) fn apply(files: &mut SnapshotFiles, overlay: SnapshotFiles) {
+    for (key, patch) in overlay {
+        match patch {
+            Value::Null => {
+                files.remove(&key);
+            }
+            patch => merge(files.entry(key).or_insert(Value::Null), patch),
+        }
+    }
+}
+
+/// Returns the overlay which patches the base files into the target files
+pub(crate) fn diff_files(base: &SnapshotFiles, target: &SnapshotFiles) -> SnapshotFiles {
+    let mut overlay = SnapshotFiles::new();
+    for (key, target_value) in target {
+        let patch = match base.get(key) {
+            Some(base_value) => diff(base_value, target_value),
+            None => Some(target_value.clone()),
+        };
+        if let Some(patch) = patch {
+            overlay.insert(key.clone(), patch);
+        }
+    }
+    for key in base.keys().filter(|key| !target.contains_key(*key)) {
+        overlay.insert(key.clone(), Value::Null);
+    }
+    overlay
+}
+
+pub(crate) fn merge(base: &mut Value, patch: Value) {
+    match (base, patch) {
+        (Value::Object(base), Value::Object(patch)) => {
+            for (key, value) in patch {
+                match value {
+                    Value::Null => {
+                        base.remove(&key);
+                    }
+                    value => merge(base.entry(key).or_insert(Value::Null), value),
+                }
+            }
+        }
+        (Value::Array(base), Value::Array(patch)) if are_named(base) && are_named(&patch) => {
+            for item in patch {
+                let name = item_name(&item).map(str::to_string);
+                let position = base.iter().position(|b| item_name(b) == name.as_deref());
+                match (position, item.get(PATCH_KEY).and_then(Value::as_str)) {
+                    (Some(position), Some(DELETE)) => {
+                        base.remove(position);
+                    }
+                    (None, Some(DELETE)) => {}
+                    (Some(position), _) => merge(&mut base[position], item),
+                    (None, _) => base.push(item),
+                }
+            }
+        }
+        (base, Value::Object(patch)) => {
+            *base = Value::Object(Map::new());
+            merge(base, Value::Object(patch));
+        }
+        (base, patch) => *base = patch,
+    }
+}
+
+/// Returns the patch which merges the base into the target, or `None` if they're the same
+pub(crate) fn diff(base: &Value, target: &Value) -> Option<Value> {
+    match (base, target) {
+        (Value::Object(base), Value::Object(target)) => {
+            let mut patch = Map::new();
+            for key in base.keys().filter(|key| !target.contains_key(*key)) {
+                patch.insert(key.clone(), Value::Null);
+            }
+            for (key, target_value) in target {
+                let value = match base.get(key) {
+                    Some(base_value) => diff(base_value, target_value),
+                    None => Some(target_value.clone()),
+                };
+                if let Some(value) = value {
+                    patch.insert(key.clone(), value);
+                }
+            }
+            (!patch.is_empty()).then_some(Value::Object(patch))
+        }
+        (Value::Array(base_items), Value::Array(target_items))
+            if are_named(base_items) && are_named(target_items) =>
+        {
+            let mut patch = vec![];
+            for target_item in target_items {
+                let base_item = base_items
+                    .iter()
+                    .find(|base_item| item_name(base_item) == item_name(target_item));
+                match base_item {
+                    Some(base_item) => {
+                        if let Some(Value::Object(mut item_patch)) = diff(base_item, target_item) {
+                            let (key, name) = name_entry(target_item);
+                            item_patch.insert(key, name);
+                            patch.push(Value::Object(item_patch));
+                        }
+                    }
+                    None => patch.push(target_item.clone()),
+                }
+            }
+            for base_item in base_items {
+                let removed = !target_items
+                    .iter()
+                    .any(|target_item| item_name(target_item) == item_name(base_item));
+                if removed {
+                    let (key, name) = name_entry(base_item);
+                    let mut item_patch = Map::new();
+                    item_patch.insert(key, name);
+                    item_patch.insert(PATCH_KEY.to_string(), Value::String(DELETE.to_string()));
+                    patch.push(Value::Object(item_patch));
+                }
+            }
+            (!patch.is_empty()).then_some(Value::Array(patch))
+        }
+        (base, target) => (base != target).then(|| target.clone()),
+    }
+}
+
+fn item_name(item: &Value) -> Option<&str> {
+    NAME_KEYS
+        .iter()
+        .find_map(|key| item.get(*key).and_then(Value::as_str))
+}
+
+fn name_entry(item: &Value) -> (String, Value) {
+    NAME_KEYS
+        .iter()
+        .find_map(|key| Some((key.to_string(), item.get(*key)?.clone())))
+        .unwrap_or_default()
+}
+
+/// Whether every item of the list is an object with a unique name
+fn are_named(items: &[Value]) -> bool {
+    let mut names = HashSet::new();
+    items
+        .iter()
+        .all(|item| item.is_object() && item_name(item).is_some_and(|name| names.insert(name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn files(files: &[(&str, Value)]) -> SnapshotFiles {
+        files
+            .iter()
+            .map(|(key, value)| (PathBuf::from(key), value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_patches_objects_and_named_lists() {
+        let mut base = json!({
+            "name": "Phishing",
+            "description": "Triage",
+            "fields": [{"name": "Severity", "key": "sev"}, {"name": "Debug", "key": "dbg"}]
+        });
+        merge(
+            &mut base,
+            json!({
+                "description": null,
+                "fields": [
+                    {"name": "Severity", "key": "severity"},
+                    {"name": "Debug", "$patch": "delete"},
+                    {"name": "Notes", "key": "notes"}
+                ]
+            }),
+        );
+
+        assert_eq!(
+            base,
+            json!({
+                "name": "Phishing",
+                "fields": [{"name": "Severity", "key": "severity"}, {"name": "Notes", "key": "notes"}]
+            })
+        );
+    }
+
+    #[test]
+    fn test_applying_the_diff_produces_the_target() {
+        let base = files(&[
+            (
+                "groups/Analysts",
+                json!({"name": "Analysts", "users": [{"name": "alice"}]}),
+            ),
+            ("groups/Admins", json!({"name": "Admins", "users": []})),
+            ("pip", json!([{"name": "requests", "version": "2.31.0"}])),
+        ]);
+        let target = files(&[
+            (
+                "groups/Analysts",
+                json!({"name": "Analysts", "users": [{"name": "bob"}]}),
+            ),
+            (
+                "groups/Debuggers",
+                json!({"name": "Debuggers", "users": []}),
+            ),
+            ("pip", json!([{"name": "requests", "version": "2.31.0"}])),
+        ]);
+
+        let overlay = diff_files(&base, &target);
+        assert_eq!(overlay.len(), 3);
+        assert_eq!(overlay[&PathBuf::from("groups/Admins")], Value::Null);
+
+        let mut merged = base.clone();
+        apply(&mut merged, overlay);
+        assert_eq!(merged, target);
+    }
+}
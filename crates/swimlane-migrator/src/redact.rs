@@ -0,0 +1,453 @@
+use std::collections::HashMap;
+
+use hmac::{Hmac, Mac};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+
+use crate::ResourceType;
+
+/// Keys which reference other resources by name, and the type of resource they reference
+const REFERENCE_KEYS: &[(&str, ResourceType)] = &[
+    ("users", ResourceType::User),
+    ("groups", ResourceType::Group),
+    ("primaryGroup", ResourceType::Group),
+    ("roles", ResourceType::Role),
+];
+
+/// Fields which are redacted before resources are written, keyed by resource type
+///
+/// ```yaml
+/// salt: a-secret-value
+/// user:
+///   email: pseudonymise
+///   phone_number: drop
+///   avatar: drop
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RedactionPolicy {
+    /// The key hashes and pseudonyms are computed with, which is required if any field is hashed
+    /// or pseudonymised. Anyone who knows it can reverse them by hashing likely values, so it
+    /// must be kept secret.
+    #[serde(default)]
+    pub salt: String,
+    #[serde(flatten)]
+    resources: HashMap<ResourceType, HashMap<String, Redaction>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Redaction {
+    /// The value is blanked: strings are emptied, lists are emptied and anything else is
+    /// replaced with null. The key is kept, as some fields, such as a user's email, are required.
+    Drop,
+    /// The value is replaced with a hash of it
+    Hash,
+    /// The value is replaced with a readable pseudonym, such as `user-1a2b3c4d`. Email addresses
+    /// remain valid email addresses.
+    Pseudonymise,
+}
+
+impl RedactionPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.resources.values().all(HashMap::is_empty)
+    }
+
+    /// Whether any field is hashed or pseudonymised, so a salt is required
+    pub fn needs_salt(&self) -> bool {
+        self.resources
+            .values()
+            .flat_map(HashMap::values)
+            .any(|redaction| *redaction != Redaction::Drop)
+    }
+
+    fn redact(&self, resource_type: ResourceType, redaction: Redaction, value: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.salt.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(value.as_bytes());
+        let hash: String = mac.finalize().into_bytes()[..8]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        match redaction {
+            Redaction::Drop => String::new(),
+            Redaction::Hash => hash,
+            Redaction::Pseudonymise => match value.contains('@') {
+                true => format!("{}-{}@example.invalid", resource_type, &hash[..8]),
+                false => format!("{}-{}", resource_type, &hash[..8]),
+            },
+        }
+    }
+}
+
+/// Redacts resources of several types, so that references to a redacted resource are given the
+/// same replacement as the resource itself
+///
+/// The fields of every type are redacted first, then the references, as a resource may be
+/// referenced before it's redacted.
+pub struct Redactor<'a> {
+    policy: &'a RedactionPolicy,
+    /// The original and redacted value of each redacted field, per resource type
+    replacements: HashMap<ResourceType, HashMap<String, String>>,
+    /// The redacted name of each resource whose name was redacted, keyed by its id, per resource
+    /// type. References which name the resource some other way, such as by a user's display
+    /// name, are given this name.
+    names: HashMap<ResourceType, HashMap<String, String>>,
+}
+
+/// The key of the field a resource is referenced by
+fn name_key(resource_type: ResourceType) -> &'static str {
+    match resource_type {
+        ResourceType::User => "userName",
+        _ => "name",
+    }
+}
+
+impl<'a> Redactor<'a> {
+    pub fn new(policy: &'a RedactionPolicy) -> Self {
+        Self {
+            policy,
+            replacements: HashMap::new(),
+            names: HashMap::new(),
+        }
+    }
+
+    pub fn redact_fields<T: Serialize>(
+        &mut self,
+        resource_type: ResourceType,
+        resources: &[T],
+    ) -> Result<Vec<Value>, serde_json::Error> {
+        let fields = self
+            .policy
+            .resources
+            .get(&resource_type)
+            .cloned()
+            .unwrap_or_default();
+        let replacements = self.replacements.entry(resource_type).or_default();
+        let names = self.names.entry(resource_type).or_default();
+        let policy = self.policy;
+        let mut redact = |redaction: Redaction, s: &mut String| {
+            let redacted = policy.redact(resource_type, redaction, s);
+            replacements.insert(s.clone(), redacted.clone());
+            *s = redacted;
+        };
+
+        let mut values = vec![];
+        for resource in resources {
+            let mut value = serde_json::to_value(resource)?;
+            let Value::Object(map) = &mut value else {
+                values.push(value);
+                continue;
+            };
+            for (field, redaction) in &fields {
+                let key = camel_case(field);
+                if *redaction == Redaction::Drop {
+                    if let Some(value) = map.get_mut(&key) {
+                        blank(value);
+                    }
+                    continue;
+                }
+                match map.get_mut(&key) {
+                    Some(Value::String(s)) => redact(*redaction, s),
+                    Some(Value::Array(items)) => {
+                        for item in items {
+                            if let Value::String(s) = item {
+                                redact(*redaction, s);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            let name_field = fields
+                .keys()
+                .any(|field| camel_case(field) == name_key(resource_type));
+            if let (true, Some(Value::String(id)), Some(Value::String(name))) =
+                (name_field, map.get("id"), map.get(name_key(resource_type)))
+            {
+                names.insert(id.clone(), name.clone());
+            }
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    /// Gives the references to redacted resources the same replacement as the resource
+    pub fn redact_references<T: DeserializeOwned>(
+        &self,
+        values: Vec<Value>,
+    ) -> Result<Vec<T>, serde_json::Error> {
+        values
+            .into_iter()
+            .map(|mut value| {
+                self.replace_references(&mut value);
+                serde_json::from_value(value)
+            })
+            .collect()
+    }
+
+    fn replace_references(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    let referenced_type = REFERENCE_KEYS
+                        .iter()
+                        .find(|(reference_key, _)| reference_key == key)
+                        .map(|(_, resource_type)| *resource_type);
+                    match referenced_type {
+                        Some(resource_type) => match value {
+                            Value::Array(items) => items
+                                .iter_mut()
+                                .for_each(|item| self.replace_name(item, resource_type)),
+                            value => self.replace_name(value, resource_type),
+                        },
+                        None => self.replace_references(value),
+                    }
+                }
+            }
+            Value::Array(items) => items
+                .iter_mut()
+                .for_each(|item| self.replace_references(item)),
+            _ => {}
+        }
+    }
+
+    /// Gives the reference the redacted name of the resource it references by id, or otherwise
+    /// the replacement of its name
+    fn replace_name(&self, reference: &mut Value, resource_type: ResourceType) {
+        let redacted_name = reference
+            .get("id")
+            .and_then(Value::as_str)
+            .and_then(|id| self.names.get(&resource_type)?.get(id))
+            .cloned();
+        let Some(Value::String(name)) = reference.get_mut("name") else {
+            return;
+        };
+        let replacement = redacted_name.or_else(|| {
+            self.replacements
+                .get(&resource_type)?
+                .get(name.as_str())
+                .cloned()
+        });
+        if let Some(replacement) = replacement {
+            *name = replacement;
+        }
+    }
+}
+
+/// Empties strings and lists, and replaces anything else with null
+fn blank(value: &mut Value) {
+    *value = match value {
+        Value::String(_) => Value::String(String::new()),
+        Value::Array(_) => Value::Array(vec![]),
+        _ => Value::Null,
+    };
+}
+
+/// Converts a field name, as used in the diff rules, to the key it's serialised as
+fn camel_case(field: &str) -> String {
+    let mut parts = field.split('_');
+    let first = parts.next().unwrap_or_default().to_string();
+    parts.fold(first, |mut key, part| {
+        let mut chars = part.chars();
+        if let Some(c) = chars.next() {
+            key.extend(c.to_uppercase());
+            key.push_str(chars.as_str());
+        }
+        key
+    })
+}
+
+/// A 64-bit FNV-1a hash, which unlike the standard library's hasher is stable between releases
+pub(crate) fn fnv1a(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use swimlane::apps::Application;
+    use swimlane::groups::Group;
+    use swimlane::users::User;
+    use swimlane::BaseEntity;
+
+    use crate::fixtures;
+
+    use super::*;
+
+    fn policy() -> RedactionPolicy {
+        serde_yaml::from_str(
+            "
+            salt: pepper
+            user:
+              user_name: pseudonymise
+              email: pseudonymise
+              phone_number: drop
+              avatar: hash
+            ",
+        )
+        .unwrap()
+    }
+
+    fn user() -> User {
+        User {
+            phone_number: Some("+44 7700 900000".to_string()),
+            avatar: Some("aGVsbG8=".to_string()),
+            ..fixtures::user("jane.doe")
+        }
+    }
+
+    #[test]
+    fn test_fields_are_redacted() {
+        let policy = policy();
+        let mut redactor = Redactor::new(&policy);
+        let users = redactor
+            .redact_fields(ResourceType::User, &[user()])
+            .unwrap();
+        let users: Vec<User> = redactor.redact_references(users).unwrap();
+
+        assert!(users[0].user_name.starts_with("user-"));
+        assert!(users[0].email.ends_with("@example.invalid"));
+        assert_ne!(users[0].email, "jane.doe@example.com");
+        assert_eq!(users[0].phone_number, Some(String::new()));
+        assert_eq!(users[0].avatar.as_ref().map(String::len), Some(16));
+    }
+
+    #[test]
+    fn test_references_are_redacted_consistently() {
+        let policy = policy();
+        let group = Group {
+            name: "Analysts".to_string(),
+            users: vec![BaseEntity {
+                id: "jane.doe".to_string(),
+                name: "jane.doe".to_string(),
+                disabled: false,
+            }],
+            ..Default::default()
+        };
+
+        let mut redactor = Redactor::new(&policy);
+        let groups = redactor
+            .redact_fields(ResourceType::Group, &[group])
+            .unwrap();
+        let users = redactor
+            .redact_fields(ResourceType::User, &[user()])
+            .unwrap();
+        let groups: Vec<Group> = redactor.redact_references(groups).unwrap();
+        let users: Vec<User> = redactor.redact_references(users).unwrap();
+
+        assert_eq!(groups[0].name, "Analysts");
+        assert_eq!(groups[0].users[0].name, users[0].user_name);
+    }
+
+    #[test]
+    fn test_references_by_another_name_are_redacted_by_id() {
+        let policy = policy();
+        let user = User {
+            id: "u1".to_string(),
+            display_name: Some("Jane Doe".to_string()),
+            ..user()
+        };
+        let group = Group {
+            name: "Analysts".to_string(),
+            users: vec![BaseEntity {
+                id: "u1".to_string(),
+                name: "Jane Doe".to_string(),
+                disabled: false,
+            }],
+            ..Default::default()
+        };
+
+        let mut redactor = Redactor::new(&policy);
+        let groups = redactor
+            .redact_fields(ResourceType::Group, &[group])
+            .unwrap();
+        let users = redactor.redact_fields(ResourceType::User, &[user]).unwrap();
+        let groups: Vec<Group> = redactor.redact_references(groups).unwrap();
+        let users: Vec<User> = redactor.redact_references(users).unwrap();
+
+        assert_eq!(groups[0].users[0].name, users[0].user_name);
+    }
+
+    #[test]
+    fn test_list_items_are_recorded_as_replacements() {
+        let policy: RedactionPolicy = serde_yaml::from_str(
+            "
+            application:
+              workspaces: pseudonymise
+            ",
+        )
+        .unwrap();
+        let app = Application {
+            workspaces: vec!["SOC".to_string()],
+            ..fixtures::application("a1", "Phishing")
+        };
+
+        let mut redactor = Redactor::new(&policy);
+        let apps = redactor
+            .redact_fields(ResourceType::Application, &[app])
+            .unwrap();
+
+        let redacted = apps[0]["workspaces"][0].as_str().unwrap();
+        assert!(redacted.starts_with("application-"));
+        assert_eq!(
+            redactor.replacements[&ResourceType::Application]["SOC"],
+            redacted
+        );
+    }
+
+    #[test]
+    fn test_salt_changes_pseudonyms() {
+        let policy = policy();
+        let unsalted = RedactionPolicy {
+            salt: String::new(),
+            ..policy.clone()
+        };
+
+        let redact = |policy: &RedactionPolicy| {
+            policy.redact(ResourceType::User, Redaction::Pseudonymise, "jane.doe")
+        };
+        assert_eq!(redact(&policy), redact(&policy));
+        assert_ne!(redact(&policy), redact(&unsalted));
+    }
+
+    #[test]
+    fn test_required_fields_are_blanked_rather_than_removed() {
+        let policy: RedactionPolicy = serde_yaml::from_str(
+            "
+            user:
+              email: drop
+              primary_group: drop
+            ",
+        )
+        .unwrap();
+
+        let mut redactor = Redactor::new(&policy);
+        let users = redactor
+            .redact_fields(ResourceType::User, &[user()])
+            .unwrap();
+
+        assert_eq!(users[0]["email"], "");
+        assert_eq!(users[0]["primaryGroup"], Value::Null);
+        let users: Vec<User> = redactor.redact_references(users).unwrap();
+        assert_eq!(users[0].email, "");
+    }
+
+    #[test]
+    fn test_only_hashes_and_pseudonyms_need_a_salt() {
+        let policy = |yaml: &str| serde_yaml::from_str::<RedactionPolicy>(yaml).unwrap();
+
+        assert!(policy("user: {email: pseudonymise}").needs_salt());
+        assert!(policy("user: {avatar: hash}").needs_salt());
+        assert!(!policy("user: {avatar: drop}").needs_salt());
+    }
+
+    #[test]
+    fn test_camel_case() {
+        assert_eq!(camel_case("phone_number"), "phoneNumber");
+        assert_eq!(camel_case("email"), "email");
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt::{Display, Formatter};
+
+use serde::Serialize;
+
+use crate::equality::{Difference, LooksLike};
+use crate::{MigrationPlan, ResourceType, SwimlaneMigrator, SwimlaneMigratorError};
+
+/// How much damage applying a change could do to the target, from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Risk {
+    /// Only changes how the resource is displayed
+    Cosmetic,
+    /// Changes how the resource behaves, without affecting access or existing data
+    Behavioural,
+    /// Changes who can see or do what
+    AccessAffecting,
+    /// Destroys data on the target, such as the records of a removed application field
+    Destructive,
+}
+
+impl Display for Risk {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Risk::Cosmetic => "cosmetic",
+            Risk::Behavioural => "behavioural",
+            Risk::AccessAffecting => "access-affecting",
+            Risk::Destructive => "destructive",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+const COSMETIC_FIELDS: &[&str] = &[
+    "description",
+    "display_name",
+    "first_name",
+    "middle_initial",
+    "last_name",
+    "avatar",
+    "layout",
+];
+
+const ACCESS_FIELDS: &[&str] = &[
+    "permissions",
+    "roles",
+    "groups",
+    "users",
+    "primary_group",
+    "disabled",
+];
+
+impl Difference {
+    /// Classifies the risk of applying the difference to a resource of the given type
+    pub fn risk(&self, resource_type: ResourceType) -> Risk {
+        let field = self.field();
+        let root = field.split('.').next().unwrap_or(field);
+
+        match (resource_type, self) {
+            // The records stored in a removed field are lost
+            (ResourceType::Application, Difference::RemovingItem { .. }) if root == "fields" => {
+                Risk::Destructive
+            }
+            // The records stored in a field can't be kept when its type changes
+            (_, Difference::ChangingType { .. }) => Risk::Destructive,
+            (ResourceType::Application, _) if root == "permissions" => Risk::AccessAffecting,
+            (ResourceType::User | ResourceType::Group | ResourceType::Role, _)
+                if ACCESS_FIELDS.contains(&root) =>
+            {
+                Risk::AccessAffecting
+            }
+            _ if COSMETIC_FIELDS.contains(&root) => Risk::Cosmetic,
+            _ => Risk::Behavioural,
+        }
+    }
+}
+
+impl<T: LooksLike> MigrationPlan<T> {
+    /// The highest risk of the changes the plan makes to the target
+    pub fn risk(&self, resource_type: ResourceType) -> Risk {
+        match self {
+            MigrationPlan::Create { .. } => Risk::Behavioural,
+            MigrationPlan::Update { differences, .. } => differences
+                .iter()
+                .map(|difference| difference.risk(resource_type))
+                .max()
+                .unwrap_or(Risk::Cosmetic),
+            MigrationPlan::Delete { .. } => Risk::Destructive,
+            MigrationPlan::Conflict { plan, .. } => plan.risk(resource_type),
+        }
+    }
+}
+
+impl SwimlaneMigrator {
+    /// Returns an error if any of the plans are destructive, unless destructive changes are allowed.
+    ///
+    /// Every `apply_*` function runs this after `check_for_conflicts`, before writing anything to
+    /// the target. Its plans must have their conflicts resolved, for example with
+    /// `MigrationPlan::resolve`, and any deletions need `with_allow_destructive`.
+    pub(crate) fn check_for_destructive<T: LooksLike>(
+        &self,
+        resource_type: ResourceType,
+        plans: &[MigrationPlan<T>],
+    ) -> Result<(), SwimlaneMigratorError> {
+        if self.allow_destructive {
+            return Ok(());
+        }
+        let count = plans
+            .iter()
+            .filter(|plan| plan.risk(resource_type) == Risk::Destructive)
+            .count();
+        match count {
+            0 => Ok(()),
+            count => Err(SwimlaneMigratorError::DestructiveChanges { count }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use swimlane::groups::Group;
+
+    use super::*;
+
+    #[test]
+    fn test_removing_application_field_is_destructive() {
+        let difference = Difference::RemovingItem {
+            field: "fields".to_string(),
+            item: "Severity".to_string(),
+        };
+
+        assert_eq!(
+            difference.risk(ResourceType::Application),
+            Risk::Destructive
+        );
+    }
+
+    #[test]
+    fn test_changing_field_type_is_destructive() {
+        let difference = Difference::ChangingType {
+            field: "fields".to_string(),
+            item: "Severity".to_string(),
+            current_type: "SingleSelect".to_string(),
+            new_type: "SingleLineText".to_string(),
+        };
+
+        assert_eq!(
+            difference.risk(ResourceType::Application),
+            Risk::Destructive
+        );
+    }
+
+    #[test]
+    fn test_difference_risks() {
+        let updating = |field: &str| Difference::UpdatingField {
+            field: field.to_string(),
+            current_value: "".to_string(),
+            new_value: "".to_string(),
+        };
+
+        assert_eq!(
+            updating("description").risk(ResourceType::Group),
+            Risk::Cosmetic
+        );
+        assert_eq!(
+            updating("permissions.App 1.access").risk(ResourceType::Role),
+            Risk::AccessAffecting
+        );
+        assert_eq!(
+            updating("disabled").risk(ResourceType::User),
+            Risk::AccessAffecting
+        );
+        assert_eq!(
+            updating("acronym").risk(ResourceType::Application),
+            Risk::Behavioural
+        );
+    }
+
+    #[test]
+    fn test_plan_risk_is_the_highest_difference_risk() {
+        let plan = MigrationPlan::Update {
+            source_resource: Group::default(),
+            target_resource: Group::default(),
+            differences: vec![
+                Difference::UpdatingField {
+                    field: "description".to_string(),
+                    current_value: "".to_string(),
+                    new_value: "".to_string(),
+                },
+                Difference::AddingItem {
+                    field: "users".to_string(),
+                    item: "user1".to_string(),
+                },
+            ],
+        };
+
+        assert_eq!(plan.risk(ResourceType::Group), Risk::AccessAffecting);
+        assert_eq!(
+            MigrationPlan::Delete {
+                target_resource: Group::default()
+            }
+            .risk(ResourceType::Group),
+            Risk::Destructive
+        );
+    }
+}
@@ -1,24 +1,25 @@
 use swimlane::roles::{Access, PermissionMatrix, Role};
+use swimlane::BaseEntity;
 
 use crate::equality::{Difference, LooksLike};
-use crate::{MigrationPlan, SwimlaneMigrator, SwimlaneMigratorError};
-
-impl LooksLike for Role {
-    fn differences(&self, other: &Self) -> Vec<Difference> {
-        let mut differences = vec![];
-
-        // Add warning if the name is different
-        push_difference!(differences, "name", &self.name, &other.name);
-        push_difference!(differences, "disabled", &self.disabled, &other.disabled);
-        push_difference!(differences, "description", &self.description, &other.description, optional: true);
-        push_difference!(differences, "users", &self.users, &other.users, vec: true);
-
-        differences
-    }
-
-    fn is_same_resource(&self, other: &Self) -> bool {
-        self.name == other.name
-    }
+use crate::{
+    check_for_conflicts, MigrationPlan, ResourceType, SwimlaneMigrator, SwimlaneMigratorError,
+};
+
+#[allow(dead_code)]
+#[derive(LooksLike)]
+#[looks_like(remote = "Role")]
+struct RoleDef {
+    #[looks_like(identity)]
+    name: String,
+    disabled: bool,
+    description: Option<String>,
+    #[looks_like(by_name)]
+    users: Vec<BaseEntity>,
+    #[looks_like(by_name)]
+    groups: Vec<BaseEntity>,
+    #[looks_like(nested)]
+    permissions: PermissionMatrix,
 }
 
 impl LooksLike for Access {
@@ -78,12 +79,57 @@ impl LooksLike for PermissionMatrix {
                 }
 
                 // Also check each iteration of the field permissions
-                let field_differences = value.fields.differences(&other_permission.fields);
-                if !field_differences.is_empty() {
-                    differences.push(Difference::UpdatingComplexField {
-                        field: format!("permissions.{}.fields", value.name),
-                    });
-                }
+                let field = format!("permissions.{}.fields", value.name);
+                differences.extend(
+                    value
+                        .fields
+                        .differences(&other_permission.fields)
+                        .into_iter()
+                        .map(|difference| match difference {
+                            Difference::UpdatingField {
+                                field: field_name,
+                                current_value,
+                                new_value,
+                            } => Difference::UpdatingField {
+                                field: format!("{}.{}", field, field_name),
+                                current_value,
+                                new_value,
+                            },
+                            Difference::AddingItem { item, .. } => Difference::AddingItem {
+                                field: field.clone(),
+                                item,
+                            },
+                            Difference::RemovingItem { item, .. } => Difference::RemovingItem {
+                                field: field.clone(),
+                                item,
+                            },
+                            Difference::ChangingType {
+                                item,
+                                current_type,
+                                new_type,
+                                ..
+                            } => Difference::ChangingType {
+                                field: field.clone(),
+                                item,
+                                current_type,
+                                new_type,
+                            },
+                            Difference::UpdatingScript {
+                                current_script,
+                                new_script,
+                                ..
+                            } => Difference::UpdatingScript {
+                                field: field.clone(),
+                                current_script,
+                                new_script,
+                            },
+                            Difference::UpdatingComplexField { .. } => {
+                                Difference::UpdatingComplexField {
+                                    field: field.clone(),
+                                }
+                            }
+                        }),
+                );
             } else {
                 differences.push(Difference::AddingItem {
                     field: "permissions".to_string(),
@@ -118,12 +164,24 @@ impl SwimlaneMigrator {
         let source_roles_future = self.from.get_roles();
         let target_roles_future = self.to.get_roles();
 
-        self.get_resources_to_migrate(source_roles_future, target_roles_future)
+        self.get_resources_to_migrate(ResourceType::Role, source_roles_future, target_roles_future)
             .await
     }
 
     pub async fn migrate_roles(&self) -> Result<(), SwimlaneMigratorError> {
-        todo!()
+        let roles_to_migrate = self.get_roles_to_migrate().await?;
+        self.apply_roles(roles_to_migrate).await
+    }
+
+    /// Roles can't be written to the target yet, so none of the plans are applied. The baseline
+    /// is still recorded, so changes made directly to the target's roles are detected.
+    pub async fn apply_roles(
+        &self,
+        roles_to_migrate: Vec<MigrationPlan<Role>>,
+    ) -> Result<(), SwimlaneMigratorError> {
+        check_for_conflicts(&roles_to_migrate)?;
+        self.save_baseline(ResourceType::Role, &[], self.to.get_roles())
+            .await
     }
 }
 
@@ -132,6 +190,8 @@ mod tests {
     use std::collections::HashMap;
     use swimlane::roles::{Access, Permission, PermissionType};
 
+    use crate::fixtures;
+
     use super::*;
 
     #[test]
@@ -370,4 +430,75 @@ mod tests {
         let differences = matrix1.differences(&matrix2);
         assert_eq!(differences.len(), 1);
     }
+
+    fn role_with_access(app_name: &str, access: u16) -> Role {
+        let permission = Permission {
+            _type: "permission".to_string(),
+            id: format!("{}-id", app_name),
+            type_: PermissionType::Role,
+            name: app_name.to_string(),
+            access,
+            fields: Access {
+                _type: "access".to_string(),
+                permissions: HashMap::new(),
+            },
+        };
+        let mut role = fixtures::role("role-1", "Role 1");
+        role.permissions.permissions = HashMap::from([(permission.id.clone(), permission)]);
+        role
+    }
+
+    #[test]
+    fn test_role_permission_change_is_a_difference() {
+        let role1 = role_with_access("App 1", 0);
+        let role2 = role_with_access("App 1", 1);
+
+        assert_eq!(
+            role1.differences(&role2),
+            vec![Difference::UpdatingField {
+                field: "permissions.App 1.access".to_string(),
+                current_value: "0".to_string(),
+                new_value: "1".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_role_group_membership_is_a_difference() {
+        let mut role1 = fixtures::role("role-1", "Role 1");
+        role1.groups = vec![BaseEntity {
+            id: "1".to_string(),
+            name: "Group 1".to_string(),
+            disabled: false,
+        }];
+        let role2 = fixtures::role("role-1", "Role 1");
+
+        assert_eq!(
+            role1.differences(&role2),
+            vec![Difference::AddingItem {
+                field: "groups".to_string(),
+                item: "Group 1".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_field_permission_difference_is_named_by_field() {
+        let mut role1 = role_with_access("App 1", 0);
+        let role2 = role_with_access("App 1", 0);
+        for permission in role1.permissions.permissions.values_mut() {
+            permission
+                .fields
+                .permissions
+                .insert("Severity".to_string(), 1);
+        }
+
+        assert_eq!(
+            role1.differences(&role2),
+            vec![Difference::AddingItem {
+                field: "permissions.App 1.fields".to_string(),
+                item: "Severity".to_string(),
+            }]
+        );
+    }
 }
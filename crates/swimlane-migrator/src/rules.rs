@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::equality::Difference;
+use crate::ResourceType;
+
+/// Differences which should be ignored or normalised when comparing resources, keyed by resource type
+///
+/// ```yaml
+/// user:
+///   ignore:
+///     - timezone_id
+///     - avatar
+///   normalise:
+///     email: [trim, case_fold]
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct DiffRules {
+    resources: HashMap<ResourceType, ResourceDiffRules>,
+}
+
+impl DiffRules {
+    pub fn rules_for(&self, resource_type: ResourceType) -> ResourceDiffRules {
+        self.resources
+            .get(&resource_type)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ResourceDiffRules {
+    /// Field paths which are never reported as a difference. A trailing `*` matches any suffix,
+    /// for example `permissions.*`
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    /// Normalisers applied to both values of a field before they're compared
+    #[serde(default)]
+    pub normalise: HashMap<String, Vec<Normaliser>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Normaliser {
+    /// Removes leading and trailing whitespace
+    Trim,
+    /// Compares values case-insensitively
+    CaseFold,
+    /// Treats a missing value the same as an empty one. Both are displayed as an empty string, so
+    /// without it they're reported as a difference between two empty values.
+    NoneAsEmpty,
+}
+
+impl Normaliser {
+    fn apply(&self, value: &str) -> String {
+        match self {
+            Normaliser::Trim => value.trim().to_string(),
+            Normaliser::CaseFold => value.to_lowercase(),
+            Normaliser::NoneAsEmpty => value.to_string(),
+        }
+    }
+}
+
+fn matches_path(pattern: &str, field: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => field.starts_with(prefix),
+        None => pattern == field,
+    }
+}
+
+impl ResourceDiffRules {
+    fn is_ignored(&self, field: &str) -> bool {
+        self.ignore
+            .iter()
+            .any(|pattern| matches_path(pattern, field))
+    }
+
+    /// The normalisers of every pattern which matches the field, ordered by pattern so they're
+    /// applied in the same order on every run
+    fn normalisers_for(&self, field: &str) -> Option<Vec<Normaliser>> {
+        let mut patterns: Vec<&String> = self
+            .normalise
+            .keys()
+            .filter(|pattern| matches_path(pattern, field))
+            .collect();
+        if patterns.is_empty() {
+            return None;
+        }
+        patterns.sort();
+
+        let mut normalisers = vec![];
+        for normaliser in patterns
+            .into_iter()
+            .flat_map(|pattern| &self.normalise[pattern])
+        {
+            if !normalisers.contains(normaliser) {
+                normalisers.push(*normaliser);
+            }
+        }
+        Some(normalisers)
+    }
+
+    fn normalise(normalisers: &[Normaliser], value: &str) -> String {
+        normalisers
+            .iter()
+            .fold(value.to_string(), |value, normaliser| {
+                normaliser.apply(&value)
+            })
+    }
+
+    /// Removes the differences which are ignored, or which are identical once normalised
+    pub fn apply(&self, differences: Vec<Difference>) -> Vec<Difference> {
+        differences
+            .into_iter()
+            .filter(|difference| {
+                let field = difference.field();
+                if self.is_ignored(field) {
+                    return false;
+                }
+
+                match (difference, self.normalisers_for(field)) {
+                    (
+                        Difference::UpdatingField {
+                            current_value,
+                            new_value,
+                            ..
+                        },
+                        Some(normalisers),
+                    ) => {
+                        // Only a missing value and an empty one are displayed the same
+                        if current_value == new_value {
+                            return !normalisers.contains(&Normaliser::NoneAsEmpty);
+                        }
+                        Self::normalise(&normalisers, current_value)
+                            != Self::normalise(&normalisers, new_value)
+                    }
+                    _ => true,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::equality::LooksLike;
+
+    use super::*;
+
+    fn updating_field(field: &str, current_value: &str, new_value: &str) -> Difference {
+        Difference::UpdatingField {
+            field: field.to_string(),
+            current_value: current_value.to_string(),
+            new_value: new_value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_ignored_field_is_removed() {
+        let rules = ResourceDiffRules {
+            ignore: vec!["timezone_id".to_string()],
+            ..Default::default()
+        };
+
+        let differences = rules.apply(vec![
+            updating_field("timezone_id", "UTC", "Europe/London"),
+            updating_field("email", "a@example.com", "b@example.com"),
+        ]);
+
+        assert_eq!(
+            differences,
+            vec![updating_field("email", "a@example.com", "b@example.com")]
+        );
+    }
+
+    #[test]
+    fn test_ignored_wildcard_matches_prefix() {
+        let rules = ResourceDiffRules {
+            ignore: vec!["permissions.*".to_string()],
+            ..Default::default()
+        };
+
+        let differences = rules.apply(vec![
+            updating_field("permissions.TIER-1.access", "0", "1"),
+            Difference::AddingItem {
+                field: "permissions".to_string(),
+                item: "TIER-2".to_string(),
+            },
+        ]);
+
+        assert_eq!(differences.len(), 1);
+    }
+
+    #[test]
+    fn test_normalised_values_which_match_are_removed() {
+        let mut normalise = HashMap::new();
+        normalise.insert(
+            "email".to_string(),
+            vec![Normaliser::Trim, Normaliser::CaseFold],
+        );
+        let rules = ResourceDiffRules {
+            normalise,
+            ..Default::default()
+        };
+
+        let differences = rules.apply(vec![
+            updating_field("email", " User@Example.com", "user@example.com"),
+            updating_field("first_name", "User", "user"),
+        ]);
+
+        assert_eq!(
+            differences,
+            vec![updating_field("first_name", "User", "user")]
+        );
+    }
+
+    #[test]
+    fn test_normalisers_of_every_matching_pattern_are_applied() {
+        let mut normalise = HashMap::new();
+        normalise.insert("email".to_string(), vec![Normaliser::Trim]);
+        normalise.insert("e*".to_string(), vec![Normaliser::CaseFold]);
+        let rules = ResourceDiffRules {
+            normalise,
+            ..Default::default()
+        };
+
+        let differences = rules.apply(vec![updating_field(
+            "email",
+            " User@Example.com",
+            "user@example.com",
+        )]);
+
+        assert!(differences.is_empty());
+    }
+
+    #[test]
+    fn test_missing_and_empty_values_only_match_when_normalised_as_empty() {
+        #[derive(LooksLike)]
+        struct Resource {
+            #[looks_like(identity)]
+            name: String,
+            description: Option<String>,
+        }
+        let resource = |description: Option<&str>| Resource {
+            name: "Resource".to_string(),
+            description: description.map(str::to_string),
+        };
+        let rules = |normalisers: Vec<Normaliser>| ResourceDiffRules {
+            normalise: HashMap::from([("description".to_string(), normalisers)]),
+            ..Default::default()
+        };
+        let (empty, missing) = (resource(Some("")), resource(None));
+
+        assert_eq!(
+            empty.differences_with_rules(&missing, &ResourceDiffRules::default()),
+            vec![updating_field("description", "", "")]
+        );
+        assert_eq!(
+            empty
+                .differences_with_rules(&missing, &rules(vec![Normaliser::Trim]))
+                .len(),
+            1
+        );
+        assert!(empty
+            .differences_with_rules(&missing, &rules(vec![Normaliser::NoneAsEmpty]))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_rules_deserialise_by_resource_type() {
+        let rules: DiffRules = serde_json::from_str(
+            r#"{"user": {"ignore": ["avatar"], "normalise": {"phone_number": ["trim", "none_as_empty"]}}}"#,
+        )
+        .unwrap();
+
+        let user_rules = rules.rules_for(ResourceType::User);
+        assert_eq!(user_rules.ignore, vec!["avatar".to_string()]);
+        assert_eq!(
+            user_rules.normalise["phone_number"],
+            vec![Normaliser::Trim, Normaliser::NoneAsEmpty]
+        );
+        assert!(rules.rules_for(ResourceType::Group).ignore.is_empty());
+    }
+}
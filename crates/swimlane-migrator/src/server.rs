@@ -0,0 +1,112 @@
+use std::ops::Deref;
+
+use reqwest::header::HeaderMap;
+use reqwest::{Client, ClientBuilder};
+use swimlane::apps::Application;
+use swimlane::error::SwimlaneClientError;
+use swimlane::tasks::Task;
+use swimlane::SwimlaneClient;
+
+use crate::tasks::Asset;
+
+/// A live Swimlane server. Dereferences to its `SwimlaneClient`, and adds the endpoints the
+/// client doesn't have yet, such as updating an application.
+#[derive(Clone)]
+pub struct SwimlaneServer {
+    client: SwimlaneClient,
+    http_client: Client,
+}
+
+impl SwimlaneServer {
+    pub fn new(base_url: String, pat: String) -> Self {
+        let client = SwimlaneClient::new(base_url, pat.clone());
+
+        let mut headers = HeaderMap::new();
+        headers.insert("Private-Token", pat.parse().unwrap());
+        headers.insert("Content-Type", "application/json".parse().unwrap());
+        headers.insert("Accept", "application/json".parse().unwrap());
+
+        let http_client = ClientBuilder::new()
+            .default_headers(headers)
+            .build()
+            .expect("Could not build http client");
+
+        Self {
+            client,
+            http_client,
+        }
+    }
+
+    /// The client for the endpoints the `swimlane` crate supports
+    pub fn client(&self) -> &SwimlaneClient {
+        &self.client
+    }
+
+    /// Replaces the definition of the application with the same id
+    pub async fn update_application(&self, app: &Application) -> Result<(), SwimlaneClientError> {
+        let url = format!("{}/api/app/{}", self.base_url, app.id);
+        self.http_client
+            .put(url)
+            .json(app)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Creates the task, in the application its `applicationId` references
+    pub async fn create_task(&self, task: &Task) -> Result<(), SwimlaneClientError> {
+        let url = format!("{}/api/task", self.base_url);
+        self.http_client
+            .post(url)
+            .json(task)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Replaces the task with the same id, such as to upload a new version of its script
+    pub async fn update_task(&self, task: &Task) -> Result<(), SwimlaneClientError> {
+        let url = format!("{}/api/task/{}", self.base_url, task.id);
+        self.http_client
+            .put(url)
+            .json(task)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    pub async fn delete_task(&self, id: &str) -> Result<(), SwimlaneClientError> {
+        let url = format!("{}/api/task/{}", self.base_url, id);
+        self.http_client
+            .delete(url)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Returns the assets, such as the credentials of integrations, which tasks can reference
+    pub async fn get_assets(&self) -> Result<Vec<Asset>, SwimlaneClientError> {
+        let url = format!("{}/api/asset", self.base_url);
+        let assets = self
+            .http_client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(assets)
+    }
+}
+
+impl Deref for SwimlaneServer {
+    type Target = SwimlaneClient;
+
+    fn deref(&self) -> &SwimlaneClient {
+        &self.client
+    }
+}
@@ -0,0 +1,872 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use swimlane::apps::Application;
+use swimlane::error::SwimlaneClientError;
+use swimlane::groups::Group;
+use swimlane::python::PipPackage;
+use swimlane::roles::{PermissionMatrix, Role};
+use swimlane::tasks::Task;
+use swimlane::users::User;
+use swimlane::workspaces::Workspace;
+use swimlane::SwimlaneClient;
+use thiserror::Error;
+
+use crate::adapt::SwimlaneResourceNormaliser;
+use crate::backend::MemoryBackend;
+use crate::redact::{RedactionPolicy, Redactor};
+use crate::tasks::TaskReferences;
+use crate::template::{TemplateError, TemplateValues};
+use crate::workspaces::clone_workspaces;
+use crate::{backup, overlay};
+use crate::{ResourceType, SwimlaneMigratorError};
+
+/// Keys which differ between instances, or change without the configuration changing
+const VOLATILE_KEYS: &[&str] = &[
+    "id",
+    "applicationId",
+    "defaultWorkspaceId",
+    "defaultDashboardId",
+    "createdDate",
+    "modifiedDate",
+    "createdByUser",
+    "modifiedByUser",
+    "lastLogin",
+    "lastPasswordChangedDate",
+];
+
+/// Lists whose order isn't meaningful, so are sorted to keep exports stable
+const UNORDERED_KEYS: &[&str] = &["users", "groups", "roles", "workspaces", "applications"];
+
+/// The directory the common tasks are written to, alongside a directory per application
+pub const COMMON_TASKS: &str = "common";
+
+/// The directories of a snapshot, which are replaced each time it's written
+const RESOURCE_DIRS: &[&str] = &["apps", "roles", "groups", "users", "workspaces", "tasks"];
+
+/// The file the pip packages are written to, alongside the resource directories
+const PIP_PACKAGES: &str = "pip";
+
+/// Marks a directory as written by a snapshot, so its resource directories can be replaced
+const SNAPSHOT_MARKER: &str = ".swimlane-snapshot";
+
+/// The extensions of the files which are read, the first being preferred
+const EXTENSIONS: &[&str] = &["yaml", "yml", "json"];
+
+/// The contents of each file of a snapshot, keyed by its path relative to the snapshot's
+/// directory, without its extension
+pub(crate) type SnapshotFiles = BTreeMap<PathBuf, Value>;
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("Swimlane error")]
+    SwimlaneError(#[from] SwimlaneClientError),
+    #[error("Could not read or write the snapshot")]
+    IoError(#[from] std::io::Error),
+    #[error("Could not serialise or deserialise the snapshot as JSON")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Could not serialise or deserialise the snapshot as YAML")]
+    YamlError(#[from] serde_yaml::Error),
+    #[error("The backup '{}' has no manifest", .0.display())]
+    MissingManifest(PathBuf),
+    #[error("'{}' wasn't written by a snapshot, so its resource directories won't be replaced", .0.display())]
+    NotASnapshot(PathBuf),
+    #[error("A task references '{0}', which is the name of more than one application or field")]
+    AmbiguousReference(String),
+    #[error("The redaction policy hashes or pseudonymises fields, so needs a salt")]
+    MissingSalt,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SnapshotFormat {
+    #[default]
+    Yaml,
+    Json,
+}
+
+impl SnapshotFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SnapshotFormat::Yaml => "yaml",
+            SnapshotFormat::Json => "json",
+        }
+    }
+
+    fn serialise(&self, value: &Value) -> Result<String, SnapshotError> {
+        match self {
+            SnapshotFormat::Yaml => Ok(serde_yaml::to_string(value)?),
+            SnapshotFormat::Json => Ok(serde_json::to_string_pretty(value)? + "\n"),
+        }
+    }
+}
+
+/// The configuration of an entire Swimlane instance, with references normalised to names so
+/// snapshots of two instances can be compared
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    pub applications: Vec<Application>,
+    pub roles: Vec<Role>,
+    pub groups: Vec<Group>,
+    pub users: Vec<User>,
+    pub workspaces: Vec<Workspace>,
+    /// Tasks keyed by the name of their application, or `common`
+    pub tasks: BTreeMap<String, Vec<Task>>,
+    pub pip_packages: Vec<PipPackage>,
+}
+
+impl Clone for Snapshot {
+    fn clone(&self) -> Self {
+        Self {
+            applications: self.applications.clone(),
+            roles: self.roles.clone(),
+            groups: self.groups.clone(),
+            users: self.users.clone(),
+            workspaces: clone_workspaces(&self.workspaces),
+            tasks: self.tasks.clone(),
+            pip_packages: self.pip_packages.clone(),
+        }
+    }
+}
+
+/// Keys the permissions by the name of the application, role or group, rather than its id
+fn key_permissions_by_name(permissions: &PermissionMatrix) -> PermissionMatrix {
+    PermissionMatrix {
+        _type: permissions._type.clone(),
+        permissions: permissions
+            .permissions
+            .values()
+            .map(|permission| (permission.name.clone(), permission.clone()))
+            .collect(),
+    }
+}
+
+impl Snapshot {
+    /// Takes a snapshot of the instance
+    pub async fn fetch(swimlane_client: &SwimlaneClient) -> Result<Self, SnapshotError> {
+        let normaliser = SwimlaneResourceNormaliser::new(swimlane_client.clone());
+
+        let applications = normaliser.get_normalised_applications().await?;
+        let app_names: HashMap<String, String> = applications
+            .iter()
+            .map(|app| (app.id.clone(), app.name.clone()))
+            .collect();
+
+        let normalise = |tasks: Vec<Task>| -> Result<Vec<Task>, SnapshotError> {
+            tasks
+                .iter()
+                .map(|task| TaskReferences::for_task(&applications, task).normalise(task))
+                .collect()
+        };
+        let mut tasks = BTreeMap::new();
+        for app in &applications {
+            let app_tasks = swimlane_client.get_tasks_for_application(&app.id).await?;
+            if !app_tasks.is_empty() {
+                tasks.insert(app.name.clone(), normalise(app_tasks)?);
+            }
+        }
+        let common_tasks = swimlane_client.get_common_tasks().await?;
+        if !common_tasks.is_empty() {
+            tasks.insert(COMMON_TASKS.to_string(), normalise(common_tasks)?);
+        }
+
+        let workspaces = swimlane_client
+            .get_workspaces()
+            .await?
+            .into_iter()
+            .map(|mut workspace| {
+                workspace.applications = workspace
+                    .applications
+                    .iter()
+                    .map(|id| app_names.get(id).cloned().unwrap_or(id.clone()))
+                    .collect();
+                workspace
+            })
+            .collect();
+
+        Ok(Self {
+            applications,
+            roles: normaliser.get_normalised_roles().await?,
+            groups: swimlane_client.get_groups().await?,
+            users: swimlane_client.get_users().await?,
+            workspaces,
+            tasks,
+            pip_packages: swimlane_client.get_installed_pip_packages().await?,
+        })
+    }
+
+    /// Returns a copy of the snapshot with the fields in the policy redacted. Tasks and pip
+    /// packages aren't redacted. Hashing or pseudonymising fields without a salt is refused.
+    pub fn redact(&self, policy: &RedactionPolicy) -> Result<Self, SnapshotError> {
+        if policy.is_empty() {
+            return Ok(self.clone());
+        }
+        if policy.needs_salt() && policy.salt.is_empty() {
+            return Err(SnapshotError::MissingSalt);
+        }
+        let mut redactor = Redactor::new(policy);
+        let applications = redactor.redact_fields(ResourceType::Application, &self.applications)?;
+        let roles = redactor.redact_fields(ResourceType::Role, &self.roles)?;
+        let groups = redactor.redact_fields(ResourceType::Group, &self.groups)?;
+        let users = redactor.redact_fields(ResourceType::User, &self.users)?;
+        let workspaces = redactor.redact_fields(ResourceType::Workspace, &self.workspaces)?;
+
+        Ok(Self {
+            applications: redactor.redact_references(applications)?,
+            roles: redactor.redact_references(roles)?,
+            groups: redactor.redact_references(groups)?,
+            users: redactor.redact_references(users)?,
+            workspaces: redactor.redact_references(workspaces)?,
+            tasks: self.tasks.clone(),
+            pip_packages: self.pip_packages.clone(),
+        })
+    }
+
+    /// Returns a copy of the snapshot with each occurrence of the values replaced with its
+    /// placeholder, such as `{{ env.SIEM_HOST }}`
+    pub fn parameterise(&self, values: &TemplateValues) -> Result<Self, TemplateError> {
+        let mut tasks = BTreeMap::new();
+        for (folder, folder_tasks) in &self.tasks {
+            tasks.insert(
+                folder.clone(),
+                values.parameterise_resources(folder_tasks.clone())?,
+            );
+        }
+        Ok(Self {
+            applications: values.parameterise_resources(self.applications.clone())?,
+            roles: values.parameterise_resources(self.roles.clone())?,
+            groups: values.parameterise_resources(self.groups.clone())?,
+            users: values.parameterise_resources(self.users.clone())?,
+            workspaces: values.parameterise_resources(clone_workspaces(&self.workspaces))?,
+            tasks,
+            pip_packages: self.pip_packages.clone(),
+        })
+    }
+
+    /// Writes one file per resource, in the format `{resource type}/{name}.{extension}`. Files of
+    /// resources which no longer exist are removed, so re-writing an unchanged snapshot leaves
+    /// the directory untouched.
+    pub fn write(&self, path: &Path, format: SnapshotFormat) -> Result<(), SnapshotError> {
+        write_files(path, &self.to_files(true)?, format)
+    }
+
+    /// Writes one file per resource, like `Snapshot::write`, but without removing ids, dates or
+    /// any other volatile keys, so the resources are read back exactly as they were fetched
+    pub fn write_raw(&self, path: &Path, format: SnapshotFormat) -> Result<(), SnapshotError> {
+        write_files(path, &self.to_files(false)?, format)
+    }
+
+    /// Writes only the differences between the snapshot and the snapshot in the base directory,
+    /// as an overlay which patches the base into this snapshot. Returns the number of files
+    /// written. See [`crate::overlay`].
+    pub fn write_overlay(
+        &self,
+        path: &Path,
+        base: &Path,
+        format: SnapshotFormat,
+    ) -> Result<usize, SnapshotError> {
+        let overlay = overlay::diff_files(&read_files(base)?, &self.to_files(true)?);
+        write_files(path, &overlay, format)?;
+        Ok(overlay.len())
+    }
+
+    /// Reads a snapshot written by `Snapshot::write`, in either format. As ids aren't written,
+    /// each resource and reference is given its name as its id. The dates and users which
+    /// created or last modified a resource aren't written either, so are left blank.
+    pub fn read(path: &Path) -> Result<Self, SnapshotError> {
+        Self::read_with_overlays(path, &[])
+    }
+
+    /// Reads a snapshot, patched by each of the overlays in turn. See [`crate::overlay`].
+    pub fn read_with_overlays(path: &Path, overlays: &[PathBuf]) -> Result<Self, SnapshotError> {
+        let mut files = read_files(path)?;
+        for overlay_path in overlays {
+            overlay::apply(&mut files, read_files(overlay_path)?);
+        }
+        Self::from_files(files)
+    }
+
+    /// Returns the contents of each file the snapshot is written as. Cleaned files have their
+    /// volatile keys removed, and reference fields and permissions by name.
+    fn to_files(&self, cleaned: bool) -> Result<SnapshotFiles, SnapshotError> {
+        let mut files = SnapshotFiles::new();
+        let mut taken = HashSet::new();
+        // Names which only differ by case or by characters which aren't safe in a file name are
+        // given a numbered suffix, rather than overwriting each other
+        let mut insert = |dir: &Path, name: &str, mut value: Value| {
+            if cleaned {
+                clean(&mut value);
+            }
+            let base = file_name(name);
+            let mut key = dir.join(&base);
+            let mut suffix = 1;
+            while !taken.insert(key.to_string_lossy().to_lowercase()) {
+                suffix += 1;
+                key = dir.join(format!("{} ({})", base, suffix));
+            }
+            files.insert(key, value);
+        };
+
+        for app in &self.applications {
+            let mut app = app.clone();
+            if cleaned {
+                app.permissions = key_permissions_by_name(&app.permissions);
+            }
+            let mut value = serde_json::to_value(&app)?;
+            if cleaned {
+                name_layout_fields(&mut value);
+            }
+            insert(Path::new("apps"), &app.name, value);
+        }
+        for role in &self.roles {
+            let mut role = role.clone();
+            if cleaned {
+                role.permissions = key_permissions_by_name(&role.permissions);
+            }
+            insert(Path::new("roles"), &role.name, serde_json::to_value(&role)?);
+        }
+        for group in &self.groups {
+            insert(
+                Path::new("groups"),
+                &group.name,
+                serde_json::to_value(group)?,
+            );
+        }
+        for user in &self.users {
+            insert(
+                Path::new("users"),
+                &user.user_name,
+                serde_json::to_value(user)?,
+            );
+        }
+        for workspace in &self.workspaces {
+            let value = serde_json::to_value(workspace)?;
+            insert(Path::new("workspaces"), &workspace.name, value);
+        }
+        for (folder, tasks) in &self.tasks {
+            let dir = Path::new("tasks").join(file_name(folder));
+            for task in tasks {
+                insert(&dir, &task.name, serde_json::to_value(task)?);
+            }
+        }
+
+        let mut pip_packages = self.pip_packages.clone();
+        pip_packages.sort_by(|a, b| a.name.cmp(&b.name));
+        insert(
+            Path::new(""),
+            PIP_PACKAGES,
+            serde_json::to_value(pip_packages)?,
+        );
+
+        Ok(files)
+    }
+
+    fn from_files(files: SnapshotFiles) -> Result<Self, SnapshotError> {
+        let mut snapshot = Self::default();
+        for (key, mut value) in files {
+            let components: Vec<String> = key
+                .iter()
+                .map(|component| component.to_string_lossy().to_string())
+                .collect();
+            let components: Vec<&str> = components.iter().map(String::as_str).collect();
+            restore_ids(&mut value);
+            if let Some(dir) = components.first() {
+                restore_required_keys(&mut value, required_volatile_keys(dir));
+            }
+            match components.as_slice() {
+                ["apps", _] => snapshot.applications.push(serde_json::from_value(value)?),
+                ["roles", _] => snapshot.roles.push(serde_json::from_value(value)?),
+                ["groups", _] => snapshot.groups.push(serde_json::from_value(value)?),
+                ["users", _] => snapshot.users.push(serde_json::from_value(value)?),
+                ["workspaces", _] => snapshot.workspaces.push(serde_json::from_value(value)?),
+                ["tasks", folder, _] => {
+                    let mut task: Task = serde_json::from_value(value)?;
+                    task.application_id = (*folder != COMMON_TASKS).then(|| folder.to_string());
+                    snapshot
+                        .tasks
+                        .entry(folder.to_string())
+                        .or_default()
+                        .push(task);
+                }
+                [PIP_PACKAGES] => snapshot.pip_packages = serde_json::from_value(value)?,
+                _ => {}
+            }
+        }
+        Ok(snapshot)
+    }
+}
+
+/// A snapshot stored in a directory
+pub struct SnapshotDirectory {
+    pub path: PathBuf,
+    pub format: SnapshotFormat,
+    /// Applied to the resources each time the directory is written
+    pub redaction: RedactionPolicy,
+    pub(crate) store: MemoryBackend,
+}
+
+impl SnapshotDirectory {
+    /// Reads the snapshot in the directory. Changes are written in the format of the existing
+    /// pip packages file, or YAML if there isn't one.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, SnapshotError> {
+        Self::open_with_overlays(path, &[])
+    }
+
+    /// Reads the snapshot in the directory, patched by each of the overlays in turn. Changes are
+    /// written to the directory, not the overlays.
+    pub fn open_with_overlays(
+        path: impl Into<PathBuf>,
+        overlays: &[PathBuf],
+    ) -> Result<Self, SnapshotError> {
+        let path = path.into();
+        let snapshot = Snapshot::read_with_overlays(&path, overlays)?;
+        let format = match path.join("pip.json").exists() {
+            true => SnapshotFormat::Json,
+            false => SnapshotFormat::Yaml,
+        };
+        Ok(Self::new(path, format, snapshot))
+    }
+
+    pub fn new(path: impl Into<PathBuf>, format: SnapshotFormat, snapshot: Snapshot) -> Self {
+        let path = path.into();
+        let store = MemoryBackend::new(path.display().to_string(), snapshot);
+        Self {
+            path,
+            format,
+            redaction: RedactionPolicy::default(),
+            store,
+        }
+    }
+
+    /// Sets the fields which are redacted when the directory is written
+    pub fn with_redaction(mut self, redaction: RedactionPolicy) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// Returns a copy of the resources in the snapshot
+    pub fn snapshot(&self) -> Snapshot {
+        self.store.snapshot()
+    }
+
+    pub(crate) fn save(&self) -> Result<(), SwimlaneMigratorError> {
+        let snapshot = self.snapshot().redact(&self.redaction)?;
+        Ok(snapshot.write(&self.path, self.format)?)
+    }
+}
+
+/// Reads the contents of each file of the snapshot in the directory
+fn read_files(path: &Path) -> Result<SnapshotFiles, SnapshotError> {
+    let mut files = SnapshotFiles::new();
+    for dir in RESOURCE_DIRS {
+        read_dir_files(path, Path::new(dir), &mut files)?;
+    }
+    let pip_path = EXTENSIONS
+        .iter()
+        .map(|extension| path.join(format!("{}.{}", PIP_PACKAGES, extension)))
+        .find(|file_path| file_path.exists());
+    if let Some(pip_path) = pip_path {
+        files.insert(PathBuf::from(PIP_PACKAGES), read_value(&pip_path)?);
+    }
+    Ok(files)
+}
+
+/// Reads the files in the directory, and its subdirectories, relative to the snapshot's path
+fn read_dir_files(path: &Path, dir: &Path, files: &mut SnapshotFiles) -> Result<(), SnapshotError> {
+    if !path.join(dir).is_dir() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(path.join(dir))? {
+        let entry = entry?;
+        let file_path = entry.path();
+        if entry.file_type()?.is_dir() {
+            read_dir_files(path, &dir.join(entry.file_name()), files)?;
+            continue;
+        }
+        let is_snapshot_file = file_path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| EXTENSIONS.contains(&extension));
+        if let (true, Some(stem)) = (is_snapshot_file, file_path.file_stem()) {
+            files.insert(dir.join(stem), read_value(&file_path)?);
+        }
+    }
+    Ok(())
+}
+
+fn read_value(file_path: &Path) -> Result<Value, SnapshotError> {
+    let contents = std::fs::read_to_string(file_path)?;
+    match file_path.extension().and_then(|e| e.to_str()) {
+        Some("json") => Ok(serde_json::from_str(&contents)?),
+        _ => Ok(serde_yaml::from_str(&contents)?),
+    }
+}
+
+/// Gives each object with a name, but no id, its name as its id. Layouts without a name are
+/// given the name of their field, or otherwise their type.
+fn restore_ids(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            if !map.contains_key("id") {
+                let name = map
+                    .get("name")
+                    .or_else(|| map.get("userName"))
+                    .or_else(|| map.get("fieldId"))
+                    .or_else(|| map.get("layoutType"))
+                    .and_then(Value::as_str)
+                    .map(str::to_string);
+                if let Some(name) = name {
+                    map.insert("id".to_string(), Value::String(name));
+                }
+            }
+            map.values_mut().for_each(restore_ids);
+        }
+        Value::Array(items) => items.iter_mut().for_each(restore_ids),
+        _ => {}
+    }
+}
+
+/// The volatile keys which the resources in the directory can't be read without
+fn required_volatile_keys(dir: &str) -> &'static [&'static str] {
+    match dir {
+        "apps" | "groups" => &[
+            "createdDate",
+            "modifiedDate",
+            "createdByUser",
+            "modifiedByUser",
+        ],
+        "users" => &["createdDate", "modifiedDate", "lastPasswordChangedDate"],
+        "roles" | "workspaces" => &["createdDate", "modifiedDate"],
+        _ => &[],
+    }
+}
+
+/// Gives the resource a blank value for each of the keys it's missing
+fn restore_required_keys(value: &mut Value, keys: &[&str]) {
+    let Value::Object(map) = value else {
+        return;
+    };
+    for key in keys {
+        map.entry(*key)
+            .or_insert_with(|| match key.ends_with("Date") {
+                true => Value::String("1970-01-01T00:00:00Z".to_string()),
+                false => serde_json::json!({ "id": null, "name": null }),
+            });
+    }
+}
+
+/// Writes each file, replacing the resource directories and pip packages file already in the
+/// directory. Resource directories are only replaced if the directory was written by a snapshot
+/// or is a backup, so an unrelated directory isn't deleted.
+fn write_files(
+    path: &Path,
+    files: &SnapshotFiles,
+    format: SnapshotFormat,
+) -> Result<(), SnapshotError> {
+    let has_resource_dirs = RESOURCE_DIRS.iter().any(|dir| path.join(dir).exists());
+    let is_snapshot = path.join(SNAPSHOT_MARKER).exists() || path.join(backup::MANIFEST).exists();
+    if has_resource_dirs && !is_snapshot {
+        return Err(SnapshotError::NotASnapshot(path.to_path_buf()));
+    }
+
+    for dir in RESOURCE_DIRS {
+        if path.join(dir).exists() {
+            std::fs::remove_dir_all(path.join(dir))?;
+        }
+    }
+    for extension in EXTENSIONS {
+        let pip_path = path.join(format!("{}.{}", PIP_PACKAGES, extension));
+        if pip_path.exists() {
+            std::fs::remove_file(pip_path)?;
+        }
+    }
+    std::fs::create_dir_all(path)?;
+    std::fs::write(
+        path.join(SNAPSHOT_MARKER),
+        "Written by swimlane-cli. The resource directories are replaced each time the snapshot is written.\n",
+    )?;
+
+    for (key, value) in files {
+        let mut file_path = path.join(key).into_os_string();
+        file_path.push(format!(".{}", format.extension()));
+        let file_path = PathBuf::from(file_path);
+        if let Some(dir) = file_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(file_path, format.serialise(value)?)?;
+    }
+    Ok(())
+}
+
+/// Converts a resource name into a name which is safe to use as a file name on any platform
+pub fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Replaces the field ids referenced by an application's layout and tracking field with the
+/// fields' names
+fn name_layout_fields(app: &mut Value) {
+    let field_names: HashMap<String, String> = app["fields"]
+        .as_array()
+        .map(|fields| {
+            fields
+                .iter()
+                .filter_map(|field| {
+                    Some((
+                        field["id"].as_str()?.to_string(),
+                        field["name"].as_str()?.to_string(),
+                    ))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    fn replace(value: &mut Value, field_names: &HashMap<String, String>) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    match (key.as_str(), &value) {
+                        ("fieldId", Value::String(id)) => {
+                            if let Some(name) = field_names.get(id) {
+                                *value = Value::String(name.clone());
+                            }
+                        }
+                        _ => replace(value, field_names),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| replace(item, field_names)),
+            _ => {}
+        }
+    }
+
+    replace(&mut app["layout"], &field_names);
+    if let Some(name) = app["trackingFieldId"]
+        .as_str()
+        .and_then(|id| field_names.get(id))
+    {
+        app["trackingFieldId"] = Value::String(name.clone());
+    }
+}
+
+/// Removes the volatile keys and sorts the unordered lists, recursively
+fn clean(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for key in VOLATILE_KEYS {
+                map.remove(*key);
+            }
+            for (key, value) in map.iter_mut() {
+                clean(value);
+                if let (true, Value::Array(items)) = (UNORDERED_KEYS.contains(&key.as_str()), value)
+                {
+                    items.sort_by_key(sort_key);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(clean),
+        _ => {}
+    }
+}
+
+fn sort_key(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        Value::Object(map) => map
+            .get("name")
+            .or_else(|| map.get("userName"))
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_clean_removes_volatile_keys_and_sorts_lists() {
+        let mut value = json!({
+            "id": "aBc123",
+            "name": "Group 1",
+            "modifiedDate": "2023-08-01T00:00:00Z",
+            "users": [
+                {"id": "2", "name": "user2"},
+                {"id": "1", "name": "user1"}
+            ]
+        });
+
+        clean(&mut value);
+
+        assert_eq!(
+            value,
+            json!({
+                "name": "Group 1",
+                "users": [{"name": "user1"}, {"name": "user2"}]
+            })
+        );
+    }
+
+    #[test]
+    fn test_layout_field_ids_are_replaced_with_names() {
+        let mut app = json!({
+            "fields": [{"id": "a1", "name": "Severity"}],
+            "layout": [{"fieldId": "a1", "tabs": [{"fieldId": "a1"}]}]
+        });
+
+        name_layout_fields(&mut app);
+
+        assert_eq!(app["layout"][0]["fieldId"], "Severity");
+        assert_eq!(app["layout"][0]["tabs"][0]["fieldId"], "Severity");
+    }
+
+    #[test]
+    fn test_restore_ids_uses_names() {
+        let mut value = json!({
+            "name": "Group 1",
+            "users": [{"name": "user1"}],
+            "roles": [{"id": "kept", "name": "Role 1"}]
+        });
+
+        restore_ids(&mut value);
+
+        assert_eq!(value["id"], "Group 1");
+        assert_eq!(value["users"][0]["id"], "user1");
+        assert_eq!(value["roles"][0]["id"], "kept");
+    }
+
+    #[test]
+    fn test_restore_ids_names_layouts_by_field_or_type() {
+        let mut value = json!({
+            "layout": [
+                {"layoutType": "field", "fieldId": "Severity"},
+                {"layoutType": "tabs", "tabs": [{"layoutType": "tab", "name": "Details"}]}
+            ]
+        });
+
+        restore_ids(&mut value);
+
+        assert_eq!(value["layout"][0]["id"], "Severity");
+        assert_eq!(value["layout"][1]["id"], "tabs");
+        assert_eq!(value["layout"][1]["tabs"][0]["id"], "Details");
+    }
+
+    #[test]
+    fn test_restore_required_keys_only_fills_missing_keys() {
+        let mut value = json!({"name": "Group 1", "modifiedDate": "2023-08-01T00:00:00Z"});
+
+        restore_required_keys(
+            &mut value,
+            &["createdDate", "modifiedDate", "createdByUser"],
+        );
+
+        assert_eq!(value["createdDate"], "1970-01-01T00:00:00Z");
+        assert_eq!(value["modifiedDate"], "2023-08-01T00:00:00Z");
+        assert_eq!(value["createdByUser"], json!({"id": null, "name": null}));
+    }
+
+    #[test]
+    fn test_snapshot_round_trips_through_a_directory() {
+        let path = std::env::temp_dir().join(format!("swimlane-snapshot-{}", std::process::id()));
+        let snapshot = Snapshot {
+            groups: vec![Group {
+                id: "aBc123".to_string(),
+                name: "Group 1".to_string(),
+                description: Some("Analysts".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        snapshot.write(&path, SnapshotFormat::Yaml).unwrap();
+        let read = Snapshot::read(&path).unwrap();
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert_eq!(read.groups.len(), 1);
+        assert_eq!(read.groups[0].id, "Group 1");
+        assert_eq!(read.groups[0].description, Some("Analysts".to_string()));
+    }
+
+    #[test]
+    fn test_overlay_patches_the_base_snapshot() {
+        let dir = std::env::temp_dir().join(format!("swimlane-overlay-{}", std::process::id()));
+        let group = |name: &str, description: &str| Group {
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            ..Default::default()
+        };
+        let base = Snapshot {
+            groups: vec![group("Analysts", "Triage"), group("Admins", "Admins")],
+            ..Default::default()
+        };
+        let prod = Snapshot {
+            groups: vec![group("Analysts", "Production triage")],
+            ..Default::default()
+        };
+
+        base.write(&dir.join("base"), SnapshotFormat::Yaml).unwrap();
+        let written = prod
+            .write_overlay(&dir.join("prod"), &dir.join("base"), SnapshotFormat::Yaml)
+            .unwrap();
+        let read = Snapshot::read_with_overlays(&dir.join("base"), &[dir.join("prod")]).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(written, 2);
+        assert_eq!(read.groups.len(), 1);
+        assert_eq!(
+            read.groups[0].description,
+            Some("Production triage".to_string())
+        );
+    }
+
+    #[test]
+    fn test_colliding_file_names_are_suffixed() {
+        let group = |name: &str| Group {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let snapshot = Snapshot {
+            groups: vec![group("Tier 1/2"), group("Tier 1:2"), group("tier 1_2")],
+            ..Default::default()
+        };
+
+        let files = snapshot.to_files(true).unwrap();
+
+        assert!(files.contains_key(Path::new("groups/Tier 1_2")));
+        assert!(files.contains_key(Path::new("groups/Tier 1_2 (2)")));
+        assert!(files.contains_key(Path::new("groups/tier 1_2 (3)")));
+    }
+
+    #[test]
+    fn test_directories_not_written_by_a_snapshot_are_kept() {
+        let path =
+            std::env::temp_dir().join(format!("swimlane-not-a-snapshot-{}", std::process::id()));
+        std::fs::create_dir_all(path.join("apps")).unwrap();
+        std::fs::write(path.join("apps/notes.txt"), "keep").unwrap();
+
+        let result = Snapshot::default().write(&path, SnapshotFormat::Yaml);
+        let kept = path.join("apps/notes.txt").exists();
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert!(matches!(result, Err(SnapshotError::NotASnapshot(_))));
+        assert!(kept);
+    }
+
+    #[test]
+    fn test_file_name_replaces_path_separators() {
+        assert_eq!(file_name("Phishing / Triage"), "Phishing _ Triage");
+        assert_eq!(file_name("Alerts: Tier 1?"), "Alerts_ Tier 1_");
+    }
+}
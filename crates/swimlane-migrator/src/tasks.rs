@@ -0,0 +1,1143 @@
+//! Tasks saved as a python script alongside a sidecar of the rest of the task's definition,
+//! `{task}.py` and `{task}.yaml`, so a saved task can be edited locally and recreated. Tasks
+//! without a script, such as plugin actions, are saved as the definition alone.
+//!
+//! The sidecar references the task's application, the fields of its mappings, and the
+//! applications and fields of its triggers by name rather than id. A manifest at the root of the saved tasks records which task each file
+//! belongs to.
+//!
+//! Tasks are migrated between instances in the same normalised form, then resolved to the ids of
+//! the target's applications and fields when they're created or updated.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use swimlane::apps::{Application, LightApplication};
+use swimlane::tasks::Task;
+
+use crate::apps::field_id;
+use crate::equality::{Difference, LooksLike};
+use crate::redact::fnv1a;
+use crate::snapshot::{file_name, SnapshotError, COMMON_TASKS};
+use crate::{
+    check_for_conflicts, MigrationPlan, ResourceType, SwimlaneMigrator, SwimlaneMigratorError,
+};
+
+/// The name of the manifest at the root of the saved tasks
+pub const TASK_MANIFEST: &str = "tasks.lock.json";
+
+/// Keys which differ between instances, or change without the task changing
+const VOLATILE_KEYS: &[&str] = &[
+    "id",
+    "createdDate",
+    "modifiedDate",
+    "createdByUser",
+    "modifiedByUser",
+];
+
+/// The type of an input mapping whose value is the id of an asset
+const ASSET_MAPPING_TYPE: &str = "asset";
+
+/// An asset, such as the credentials of an integration, which tasks reference by id
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Asset {
+    pub id: String,
+    pub name: String,
+}
+
+/// Maps the ids of the applications, and of their fields, to their names
+#[derive(Debug, Clone, Default)]
+pub struct TaskReferences {
+    /// The names of the applications, keyed by id
+    applications: HashMap<String, String>,
+    /// The names of the fields, keyed by the id of their application, then by id
+    fields: HashMap<String, HashMap<String, String>>,
+    /// The id of the task's application, whose fields the task's mappings reference
+    application_id: Option<String>,
+}
+
+/// Whether references are being replaced with names, or names with ids
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Direction {
+    Normalise,
+    Resolve,
+}
+
+impl TaskReferences {
+    /// `application_id` is the id of the task's application, or `None` for a common task
+    pub fn new(applications: &[Application], application_id: Option<&str>) -> Self {
+        let fields = applications
+            .iter()
+            .map(|app| {
+                let fields = app
+                    .fields
+                    .iter()
+                    .map(|field| (field_id(field), field.name()))
+                    .collect();
+                (app.id.clone(), fields)
+            })
+            .collect();
+        Self {
+            applications: applications
+                .iter()
+                .map(|app| (app.id.clone(), app.name.clone()))
+                .collect(),
+            fields,
+            application_id: application_id.map(str::to_string),
+        }
+    }
+
+    /// The references of the task, whose application is referenced by either its id or its name
+    pub fn for_task(applications: &[Application], task: &Task) -> Self {
+        let application_id = task.application_id.as_deref().and_then(|reference| {
+            applications
+                .iter()
+                .find(|app| app.id == reference || app.name == reference)
+                .map(|app| app.id.as_str())
+        });
+        Self::new(applications, application_id)
+    }
+
+    /// Replaces the ids of applications and fields the task references with their names
+    pub fn normalise(&self, task: &Task) -> Result<Task, SnapshotError> {
+        let mut value = serde_json::to_value(task)?;
+        self.replace_references(&mut value, Direction::Normalise)?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Replaces the names of applications and fields the task references with their ids
+    pub fn resolve(&self, task: &Task) -> Result<Task, SnapshotError> {
+        let mut value = serde_json::to_value(task)?;
+        self.replace_references(&mut value, Direction::Resolve)?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Replaces the references in the places tasks keep them: the task's application, the
+    /// fields of its input and output mappings, and the application and field of its triggers.
+    /// Nothing else is replaced, even if it matches a name.
+    fn replace_references(
+        &self,
+        task: &mut Value,
+        direction: Direction,
+    ) -> Result<(), SnapshotError> {
+        let application_id = self.application_id.as_deref();
+        if let Some(application) = task.get_mut("applicationId") {
+            self.replace_application(application, direction)?;
+        }
+        for mapping in items(task, "inputMapping") {
+            if let Some(field) = mapping.get_mut("value") {
+                self.replace_field(field, application_id, direction)?;
+            }
+        }
+        for output in items(task, "outputs") {
+            for mapping in items(output, "mappings") {
+                if let Some(field) = mapping.get_mut("value") {
+                    self.replace_field(field, application_id, direction)?;
+                }
+            }
+        }
+        for trigger in items(task, "triggers") {
+            let trigger_application_id = match trigger.get("applicationId").and_then(Value::as_str)
+            {
+                Some(reference) => self.find_application(reference)?,
+                None => application_id.map(str::to_string),
+            };
+            if let Some(application) = trigger.get_mut("applicationId") {
+                self.replace_application(application, direction)?;
+            }
+            if let Some(field) = trigger.get_mut("fieldId") {
+                self.replace_field(field, trigger_application_id.as_deref(), direction)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The id of the application referenced by either its id or its name
+    fn find_application(&self, reference: &str) -> Result<Option<String>, SnapshotError> {
+        if self.applications.contains_key(reference) {
+            return Ok(Some(reference.to_string()));
+        }
+        let mut ids = self
+            .applications
+            .iter()
+            .filter(|(_, name)| name.as_str() == reference)
+            .map(|(id, _)| id.clone());
+        match (ids.next(), ids.next()) {
+            (Some(_), Some(_)) => Err(SnapshotError::AmbiguousReference(reference.to_string())),
+            (id, _) => Ok(id),
+        }
+    }
+
+    fn replace_application(
+        &self,
+        application: &mut Value,
+        direction: Direction,
+    ) -> Result<(), SnapshotError> {
+        let Value::String(reference) = application else {
+            return Ok(());
+        };
+        let replacement = match direction {
+            Direction::Normalise => match self.applications.get(reference.as_str()) {
+                Some(name) => {
+                    // Fails if another application has the same name
+                    self.find_application(name)?;
+                    Some(name.clone())
+                }
+                None => None,
+            },
+            Direction::Resolve => self.find_application(reference)?,
+        };
+        if let Some(replacement) = replacement {
+            *reference = replacement;
+        }
+        Ok(())
+    }
+
+    fn replace_field(
+        &self,
+        field: &mut Value,
+        application_id: Option<&str>,
+        direction: Direction,
+    ) -> Result<(), SnapshotError> {
+        let (Value::String(reference), Some(fields)) =
+            (field, application_id.and_then(|id| self.fields.get(id)))
+        else {
+            return Ok(());
+        };
+        let name = match direction {
+            Direction::Normalise => fields.get(reference.as_str()),
+            Direction::Resolve => Some(&*reference),
+        };
+        let Some(name) = name.cloned() else {
+            return Ok(());
+        };
+        let mut ids = fields
+            .iter()
+            .filter(|(_, field_name)| **field_name == name)
+            .map(|(id, _)| id.clone());
+        let id = match (ids.next(), ids.next()) {
+            (Some(_), Some(_)) => return Err(SnapshotError::AmbiguousReference(name)),
+            (id, _) => id,
+        };
+        // A field named like an application can't be told apart from the application
+        if id.is_some() && self.applications.values().any(|app| *app == name) {
+            return Err(SnapshotError::AmbiguousReference(name));
+        }
+        match direction {
+            Direction::Normalise => *reference = name,
+            Direction::Resolve => {
+                if let Some(id) = id {
+                    *reference = id;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The items of the list under the key, if there is one
+fn items<'a>(value: &'a mut Value, key: &str) -> impl Iterator<Item = &'a mut Value> {
+    value
+        .get_mut(key)
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+}
+
+/// Tasks are the same resource if they have the same name and belong to the same application.
+/// The script is compared separately to the rest of the task, so it can be shown as a diff.
+impl LooksLike for Task {
+    fn differences(&self, other: &Self) -> Vec<Difference> {
+        let mut differences = vec![];
+        if self.description != other.description {
+            differences.push(Difference::UpdatingField {
+                field: "description".to_string(),
+                current_value: self.description.clone().unwrap_or_default(),
+                new_value: other.description.clone().unwrap_or_default(),
+            });
+        }
+        if self.action.script != other.action.script {
+            differences.push(Difference::UpdatingScript {
+                field: "action.script".to_string(),
+                current_script: self.action.script.clone().unwrap_or_default(),
+                new_script: other.action.script.clone().unwrap_or_default(),
+            });
+        }
+
+        let (this, other) = (comparable_keys(self), comparable_keys(other));
+        let keys: BTreeSet<&String> = this.keys().chain(other.keys()).collect();
+        for key in keys {
+            if this.get(key) != other.get(key) {
+                differences.push(Difference::UpdatingComplexField { field: key.clone() });
+            }
+        }
+        differences
+    }
+
+    fn is_same_resource(&self, other: &Self) -> bool {
+        self.name == other.name && self.application_id == other.application_id
+    }
+}
+
+/// The keys of the task other than those compared on their own, and those which differ between
+/// instances, including those of the objects within the task's action
+fn comparable_keys(task: &Task) -> Map<String, Value> {
+    let mut map = match serde_json::to_value(task) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    };
+    for key in VOLATILE_KEYS
+        .iter()
+        .chain(&["name", "applicationId", "description"])
+    {
+        map.remove(*key);
+    }
+    if let Some(action) = map.get_mut("action") {
+        remove_volatile_keys(action);
+        if let Value::Object(action) = action {
+            action.remove("script");
+        }
+    }
+    map
+}
+
+/// Removes the keys which differ between instances from every object within the value
+fn remove_volatile_keys(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for key in VOLATILE_KEYS {
+                object.remove(*key);
+            }
+            object.values_mut().for_each(remove_volatile_keys);
+        }
+        Value::Array(items) => items.iter_mut().for_each(remove_volatile_keys),
+        _ => {}
+    }
+}
+
+impl SwimlaneMigrator {
+    /// Plans the migration of the tasks. The source's references to other tasks and to assets
+    /// are rewritten to the ids of the target's equivalents first, so they compare like for like.
+    pub async fn get_tasks_to_migrate(
+        &self,
+    ) -> Result<Vec<MigrationPlan<Task>>, SwimlaneMigratorError> {
+        let source_tasks = self.from.get_tasks();
+        let target_tasks = self.to.get_tasks();
+        let source_assets = self.from.get_assets();
+        let target_assets = self.to.get_assets();
+
+        let source_tasks = source_tasks.await?;
+        let target_tasks = target_tasks.await?;
+        let asset_hashmap = asset_id_hashmap(&source_assets.await?, &target_assets.await?);
+        let task_hashmap = task_id_hashmap(&source_tasks, &target_tasks);
+
+        let source_tasks = source_tasks
+            .iter()
+            .map(|task| adapt_task(task, &task_hashmap, &asset_hashmap))
+            .collect::<Result<Vec<_>, _>>()?;
+        self._get_resources_to_migrate(ResourceType::Task, source_tasks, target_tasks)
+    }
+
+    pub async fn migrate_tasks(&self) -> Result<(), SwimlaneMigratorError> {
+        let tasks_to_migrate = self.get_tasks_to_migrate().await?;
+        self.apply_tasks(tasks_to_migrate).await
+    }
+
+    /// Creates, updates and deletes the tasks from `get_tasks_to_migrate` on the target. The target
+    /// resolves the application and fields of the tasks it creates or updates all at once.
+    pub async fn apply_tasks(
+        &self,
+        tasks_to_migrate: Vec<MigrationPlan<Task>>,
+    ) -> Result<(), SwimlaneMigratorError> {
+        check_for_conflicts(&tasks_to_migrate)?;
+        self.check_for_destructive(ResourceType::Task, &tasks_to_migrate)?;
+
+        let mut created = vec![];
+        let mut updated = vec![];
+        for plan in &tasks_to_migrate {
+            match plan {
+                MigrationPlan::Create { source_resource } => created.push(source_resource.clone()),
+                MigrationPlan::Update {
+                    source_resource,
+                    target_resource,
+                    ..
+                } => {
+                    let mut task = source_resource.clone();
+                    task.id = target_resource.id.clone();
+                    updated.push(task);
+                }
+                _ => {}
+            }
+        }
+
+        let created_count = created.len();
+        let mut created = self.to.resolve_tasks([created, updated].concat()).await?;
+        let updated = created.split_off(created_count);
+        for task in &created {
+            self.to.create_task(task).await?;
+        }
+        for task in &updated {
+            self.to.update_task(task).await?;
+        }
+        for plan in &tasks_to_migrate {
+            if let MigrationPlan::Delete { target_resource } = plan {
+                self.to.delete_task(target_resource).await?;
+            }
+        }
+
+        let applied: Vec<&Task> = tasks_to_migrate
+            .iter()
+            .map(MigrationPlan::resource)
+            .collect();
+        self.save_baseline(ResourceType::Task, &applied, self.to.get_tasks())
+            .await
+    }
+}
+
+/// Maps the ids of the source's tasks to those of the target's tasks with the same name,
+/// ignoring case, which belong to the same application
+pub(crate) fn task_id_hashmap(source: &[Task], target: &[Task]) -> HashMap<String, String> {
+    source
+        .iter()
+        .filter_map(|source_task| {
+            target
+                .iter()
+                .find(|target_task| {
+                    target_task.application_id == source_task.application_id
+                        && target_task.name.to_lowercase() == source_task.name.to_lowercase()
+                })
+                .map(|target_task| (source_task.id.clone(), target_task.id.clone()))
+        })
+        .collect()
+}
+
+/// Maps the ids of the source's assets to the ids of the target's assets with the same name,
+/// ignoring case, or to the name of the asset if the target doesn't have it
+fn asset_id_hashmap(source: &[Asset], target: &[Asset]) -> HashMap<String, Result<String, String>> {
+    source
+        .iter()
+        .map(|source_asset| {
+            let target_id = target
+                .iter()
+                .find(|target_asset| {
+                    target_asset.name.to_lowercase() == source_asset.name.to_lowercase()
+                })
+                .map(|target_asset| target_asset.id.clone())
+                .ok_or_else(|| source_asset.name.clone());
+            (source_asset.id.clone(), target_id)
+        })
+        .collect()
+}
+
+/// Rewrites the ids of the source's tasks and assets which the task references to those of the
+/// target. Fails if the task references an asset the target doesn't have.
+fn adapt_task(
+    task: &Task,
+    task_hashmap: &HashMap<String, String>,
+    asset_hashmap: &HashMap<String, Result<String, String>>,
+) -> Result<Task, SwimlaneMigratorError> {
+    let mut value = serde_json::to_value(task)?;
+    adapt_task_payload(&mut value, task_hashmap, asset_hashmap)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Rewrites the references of a task's JSON payload, as for `adapt_task`. The `swimlane` crate's
+/// `Task` doesn't keep the input mappings or the next task of the descriptor, so only payloads
+/// which include them have them rewritten.
+fn adapt_task_payload(
+    value: &mut Value,
+    task_hashmap: &HashMap<String, String>,
+    asset_hashmap: &HashMap<String, Result<String, String>>,
+) -> Result<(), SwimlaneMigratorError> {
+    for mapping in items(value, "inputMapping") {
+        if mapping.get("type").and_then(Value::as_str) != Some(ASSET_MAPPING_TYPE) {
+            continue;
+        }
+        if let Some(Value::String(asset_id)) = mapping.get_mut("value") {
+            match asset_hashmap.get(asset_id.as_str()) {
+                Some(Ok(target_id)) => *asset_id = target_id.clone(),
+                Some(Err(asset_name)) => {
+                    return Err(SwimlaneMigratorError::MissingAsset {
+                        asset_name: asset_name.clone(),
+                    })
+                }
+                None => {}
+            }
+        }
+    }
+    replace_task_ids(value, task_hashmap);
+    Ok(())
+}
+
+/// Identifies the task each saved file belongs to, so files can be matched to their tasks even
+/// when the task's name isn't a valid file name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TaskManifest {
+    /// Keyed by the path of the task's script, or its definition if it has no script, relative
+    /// to the manifest, such as `Phishing/Enrich URL.py`
+    pub tasks: BTreeMap<String, TaskManifestEntry>,
+    /// The name of the application whose tasks are saved to each folder, keyed by the folder
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub folders: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskManifestEntry {
+    pub task_id: String,
+    /// `None` for a common task
+    pub application_id: Option<String>,
+    pub name: String,
+    /// The hash of the script when it was last saved or pushed, see `script_hash`. `None` for a
+    /// task without a script.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script_hash: Option<String>,
+    /// The plugin action the task runs, for a task without a script of its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<PluginAction>,
+    /// The plugin action the task was forked from, for a task with a script of its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forked_from: Option<PluginAction>,
+}
+
+impl TaskManifestEntry {
+    pub fn new(task: &Task) -> Self {
+        let plugin = serde_json::to_value(task)
+            .ok()
+            .and_then(|task| PluginAction::from_task(&task));
+        Self::with_plugin(task, plugin)
+    }
+
+    /// The entry for a task whose plugin action was read from its payload, as the `swimlane`
+    /// crate's `Task` doesn't keep the package descriptor
+    fn with_plugin(task: &Task, plugin: Option<PluginAction>) -> Self {
+        let (plugin, forked_from) = match task.action.script {
+            Some(_) => (None, plugin),
+            None => (plugin, None),
+        };
+        Self {
+            task_id: task.id.clone(),
+            application_id: task.application_id.clone(),
+            name: task.name.clone(),
+            script_hash: task.action.script.as_deref().map(script_hash),
+            plugin,
+            forked_from,
+        }
+    }
+}
+
+/// An action of a plugin, read from the descriptor of a task's action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginAction {
+    pub plugin: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+}
+
+impl PluginAction {
+    fn from_task(task: &Value) -> Option<Self> {
+        let descriptor = &task["action"]["descriptor"];
+        let package = &descriptor["packageDescriptor"];
+        let text = |value: &Value| value.as_str().map(str::to_string);
+        Some(Self {
+            plugin: text(&package["name"])?,
+            version: text(&package["version"]),
+            action: text(&descriptor["name"]),
+        })
+    }
+}
+
+impl std::fmt::Display for PluginAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.plugin)?;
+        if let Some(version) = &self.version {
+            write!(f, " {}", version)?;
+        }
+        if let Some(action) = &self.action {
+            write!(f, ": {}", action)?;
+        }
+        Ok(())
+    }
+}
+
+impl TaskManifest {
+    /// Reads the manifest in the directory, or returns an empty manifest if there isn't one
+    pub fn read(dir: &Path) -> Result<Self, SnapshotError> {
+        let path = dir.join(TASK_MANIFEST);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn write(&self, dir: &Path) -> Result<(), SnapshotError> {
+        let contents = serde_json::to_string_pretty(self)? + "\n";
+        std::fs::write(dir.join(TASK_MANIFEST), contents)?;
+        Ok(())
+    }
+
+    /// Returns the key of the task's file in the folder
+    pub fn key(folder: &str, file_name: &str) -> String {
+        format!("{}/{}", folder, file_name)
+    }
+
+    /// Returns the folder of the key
+    pub fn folder(key: &str) -> &str {
+        key.split('/').next().unwrap_or_default()
+    }
+
+    /// Whether the folder holds the tasks of the application, or the common tasks
+    pub fn is_folder_of(&self, folder: &str, application: &str) -> bool {
+        match self.folders.get(folder) {
+            Some(name) => name == application,
+            None => file_name(application) == folder,
+        }
+    }
+}
+
+/// A stable hash of the script, used to detect whether it changed since it was saved
+pub fn script_hash(script: &str) -> String {
+    format!("{:016x}", fnv1a(script))
+}
+
+/// Gives each task a file name, keyed by the task's id, which is safe to use on any platform and
+/// unique within the folder, ignoring case. Where names collide, tasks are suffixed ` (2)`, ` (3)`
+/// and so on, in order of their id.
+pub fn task_file_names(tasks: &[Task]) -> HashMap<String, String> {
+    unique_file_names(
+        tasks
+            .iter()
+            .map(|task| (task.id.as_str(), task.name.as_str())),
+        "task",
+        &[],
+    )
+}
+
+/// Gives each application the name of the folder its tasks are saved to, keyed by the
+/// application's id, in the same way as `task_file_names`. No application is given the folder
+/// of the common tasks.
+pub fn application_folder_names(applications: &[LightApplication]) -> HashMap<String, String> {
+    unique_file_names(
+        applications
+            .iter()
+            .map(|app| (app.id.as_str(), app.name.as_str())),
+        "application",
+        &[COMMON_TASKS],
+    )
+}
+
+fn unique_file_names<'a>(
+    named: impl Iterator<Item = (&'a str, &'a str)>,
+    default_name: &str,
+    reserved: &[&str],
+) -> HashMap<String, String> {
+    let mut named: Vec<(&str, &str)> = named.collect();
+    named.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut taken: HashSet<String> = reserved.iter().map(|name| name.to_lowercase()).collect();
+    let mut names = HashMap::new();
+    for (id, name) in named {
+        let base = match file_name(name) {
+            name if name.is_empty() => default_name.to_string(),
+            name => name,
+        };
+        let mut name = base.clone();
+        let mut suffix = 1;
+        while !taken.insert(name.to_lowercase()) {
+            suffix += 1;
+            name = format!("{} ({})", base, suffix);
+        }
+        names.insert(id.to_string(), name);
+    }
+    names
+}
+
+/// Returns the path of the sidecar of the task script
+pub fn sidecar_path(script_path: &Path) -> PathBuf {
+    script_path.with_extension("yaml")
+}
+
+/// Writes the task's script to the path, and the rest of its definition to the sidecar
+pub fn write_task(
+    script_path: &Path,
+    task: &Task,
+    references: &TaskReferences,
+) -> Result<(), SnapshotError> {
+    std::fs::write(script_path, task.action.script.clone().unwrap_or_default())?;
+    write_task_definition(&sidecar_path(script_path), task, references)
+}
+
+/// Writes the task's definition, without its script, as YAML
+pub fn write_task_definition(
+    path: &Path,
+    task: &Task,
+    references: &TaskReferences,
+) -> Result<(), SnapshotError> {
+    let definition = to_sidecar(task, references)?;
+    std::fs::write(path, serde_yaml::to_string(&definition)?)?;
+    Ok(())
+}
+
+/// Reconstructs a task without a script from its definition
+pub fn read_task_definition(
+    path: &Path,
+    references: &TaskReferences,
+) -> Result<Task, SnapshotError> {
+    let definition = serde_yaml::from_str(&std::fs::read_to_string(path)?)?;
+    from_sidecar(definition, None, references)
+}
+
+/// Reconstructs a task from its script and sidecar. Without a sidecar, the task is named after
+/// the script. As ids aren't saved, the task is given its name as its id.
+pub fn read_task(script_path: &Path, references: &TaskReferences) -> Result<Task, SnapshotError> {
+    let script = std::fs::read_to_string(script_path)?;
+    let sidecar = match sidecar_path(script_path).exists() {
+        true => serde_yaml::from_str(&std::fs::read_to_string(sidecar_path(script_path))?)?,
+        false => {
+            let name = script_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            Value::Object(Map::from_iter([("name".to_string(), Value::String(name))]))
+        }
+    };
+    from_sidecar(sidecar, Some(script), references)
+}
+
+fn to_sidecar(task: &Task, references: &TaskReferences) -> Result<Value, SnapshotError> {
+    let mut value = serde_json::to_value(task)?;
+    if let Value::Object(map) = &mut value {
+        for key in VOLATILE_KEYS {
+            map.remove(*key);
+        }
+        if let Some(Value::Object(action)) = map.get_mut("action") {
+            action.remove("script");
+        }
+    }
+    references.replace_references(&mut value, Direction::Normalise)?;
+    Ok(value)
+}
+
+fn from_sidecar(
+    mut value: Value,
+    script: Option<String>,
+    references: &TaskReferences,
+) -> Result<Task, SnapshotError> {
+    references.replace_references(&mut value, Direction::Resolve)?;
+    if let Some(script) = script {
+        value["action"]["script"] = Value::String(script);
+    }
+    if value.get("id").is_none() {
+        value["id"] = value["name"].clone();
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Replaces every string which is the id of one of the source's tasks, other than the task's own
+/// id, name and description
+fn replace_task_ids(task: &mut Value, task_ids: &HashMap<String, String>) {
+    fn replace(value: &mut Value, task_ids: &HashMap<String, String>) {
+        match value {
+            Value::String(s) => {
+                if let Some(replacement) = task_ids.get(s.as_str()) {
+                    *s = replacement.clone();
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| replace(item, task_ids)),
+            Value::Object(object) => object
+                .values_mut()
+                .for_each(|value| replace(value, task_ids)),
+            _ => {}
+        }
+    }
+
+    if let Value::Object(object) = task {
+        for (key, value) in object.iter_mut() {
+            if !["id", "name", "description"].contains(&key.as_str()) {
+                replace(value, task_ids);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use swimlane::apps::fields::Field;
+
+    use crate::fixtures;
+
+    use super::*;
+
+    fn phishing(fields: Vec<Field>) -> Application {
+        let mut app = fixtures::application("a1", "Phishing");
+        app.fields = fields;
+        app
+    }
+
+    fn references() -> TaskReferences {
+        TaskReferences::new(
+            &[phishing(vec![fixtures::text_field("f1", "URL")])],
+            Some("a1"),
+        )
+    }
+
+    #[test]
+    fn test_task_file_names_are_unique_ignoring_case() {
+        let task = |id: &str, name: &str| fixtures::task(id, name, None);
+        let names = task_file_names(&[
+            task("3", "Enrich URL"),
+            task("1", "Enrich/URL"),
+            task("2", "enrich_url"),
+            task("4", "Lookup: IP?"),
+        ]);
+
+        assert_eq!(names["1"], "Enrich_URL");
+        assert_eq!(names["2"], "enrich_url (2)");
+        assert_eq!(names["3"], "Enrich URL");
+        assert_eq!(names["4"], "Lookup_ IP_");
+    }
+
+    #[test]
+    fn test_application_folder_names_are_unique_and_not_common() {
+        let app = fixtures::light_application;
+        let names = application_folder_names(&[
+            app("a2", "Phishing"),
+            app("a1", "phishing"),
+            app("a3", "Common"),
+        ]);
+
+        assert_eq!(names["a1"], "phishing");
+        assert_eq!(names["a2"], "Phishing (2)");
+        assert_eq!(names["a3"], "Common (2)");
+    }
+
+    #[test]
+    fn test_plugin_action_is_read_from_the_descriptor() {
+        let task = json!({
+            "name": "Lookup URL",
+            "action": {
+                "descriptor": {
+                    "name": "Lookup URL",
+                    "packageDescriptor": {"name": "sw_virustotal", "version": "2.1.0"}
+                }
+            }
+        });
+
+        let plugin = PluginAction::from_task(&task).unwrap();
+
+        assert_eq!(plugin.to_string(), "sw_virustotal 2.1.0: Lookup URL");
+        assert_eq!(PluginAction::from_task(&json!({"action": {}})), None);
+    }
+
+    fn plugin_task(script: Option<&str>) -> Value {
+        json!({
+            "$type": "Core.Models.Integrations.Task, Core",
+            "id": "aW9V5hUQeCL4oA3xN",
+            "name": "VirusTotal - Lookup URL",
+            "applicationId": "a1",
+            "description": null,
+            "valid": true,
+            "disabled": false,
+            "action": {
+                "$type": "Core.Models.Integrations.PluginTaskAction, Core",
+                "readonly": false,
+                "type": "plugin",
+                "descriptor": {
+                    "$type": "Core.Models.Integrations.PluginActionDescriptor, Core",
+                    "id": "aBn2tY8wQ6fLkV3mR",
+                    "name": "Lookup URL",
+                    "disabled": false,
+                    "actionType": "plugin",
+                    "base64Image": "",
+                    "packageDescriptor": {
+                        "$type": "Core.Models.Integrations.PackageDescriptor, Core",
+                        "name": "sw_virustotal",
+                        "version": "2.1.0"
+                    }
+                },
+                "script": script
+            },
+            "inputMapping": [{"key": "url", "type": "record", "value": "f1"}],
+            "createdDate": "2024-01-01T00:00:00Z"
+        })
+    }
+
+    #[test]
+    fn test_manifest_records_the_plugin_action_of_a_task_payload() {
+        let payload = plugin_task(None);
+        let task: Task = serde_json::from_value(payload.clone()).unwrap();
+        let plugin = PluginAction::from_task(&payload).unwrap();
+        assert_eq!(plugin.to_string(), "sw_virustotal 2.1.0: Lookup URL");
+
+        let entry = TaskManifestEntry::with_plugin(&task, Some(plugin.clone()));
+        assert_eq!(entry.plugin, Some(plugin.clone()));
+        assert_eq!(entry.forked_from, None);
+
+        let fork: Task = serde_json::from_value(plugin_task(Some("print('fork')"))).unwrap();
+        let entry = TaskManifestEntry::with_plugin(&fork, Some(plugin.clone()));
+        assert_eq!(entry.plugin, None);
+        assert_eq!(entry.forked_from, Some(plugin));
+    }
+
+    #[test]
+    fn test_sidecar_references_fields_and_applications_by_name() {
+        let mut task = json!({
+            "name": "URL",
+            "applicationId": "a1",
+            "inputMapping": [{"key": "url", "value": "f1"}],
+            "outputs": [{"mappings": [{"key": "verdict", "value": "f1"}]}],
+            "triggers": [{"applicationId": "a1", "fieldId": "f1"}],
+            "parameters": {"static": "f1", "application": "a1"}
+        });
+        let references = references();
+        references
+            .replace_references(&mut task, Direction::Normalise)
+            .unwrap();
+
+        assert_eq!(
+            task,
+            json!({
+                "name": "URL",
+                "applicationId": "Phishing",
+                "inputMapping": [{"key": "url", "value": "URL"}],
+                "outputs": [{"mappings": [{"key": "verdict", "value": "URL"}]}],
+                "triggers": [{"applicationId": "Phishing", "fieldId": "URL"}],
+                "parameters": {"static": "f1", "application": "a1"}
+            })
+        );
+
+        references
+            .replace_references(&mut task, Direction::Resolve)
+            .unwrap();
+        assert_eq!(task["applicationId"], "a1");
+        assert_eq!(task["inputMapping"][0]["value"], "f1");
+        assert_eq!(task["outputs"][0]["mappings"][0]["value"], "f1");
+        assert_eq!(
+            task["triggers"][0],
+            json!({"applicationId": "a1", "fieldId": "f1"})
+        );
+    }
+
+    #[test]
+    fn test_ambiguous_references_are_rejected() {
+        let mut task = json!({"applicationId": "a1", "inputMapping": [{"value": "f1"}]});
+        let duplicate_fields = TaskReferences::new(
+            &[phishing(vec![
+                fixtures::text_field("f1", "URL"),
+                fixtures::text_field("f2", "URL"),
+            ])],
+            Some("a1"),
+        );
+        assert!(matches!(
+            duplicate_fields.replace_references(&mut task.clone(), Direction::Normalise),
+            Err(SnapshotError::AmbiguousReference(name)) if name == "URL"
+        ));
+
+        let field_named_like_an_app = TaskReferences::new(
+            &[phishing(vec![fixtures::text_field("f1", "Phishing")])],
+            Some("a1"),
+        );
+        assert!(matches!(
+            field_named_like_an_app.replace_references(&mut task, Direction::Normalise),
+            Err(SnapshotError::AmbiguousReference(name)) if name == "Phishing"
+        ));
+
+        let mut duplicate_app = phishing(vec![]);
+        duplicate_app.id = "a2".to_string();
+        let duplicate_apps = TaskReferences::new(&[phishing(vec![]), duplicate_app], Some("a1"));
+        let mut task = json!({"applicationId": "Phishing"});
+        assert!(matches!(
+            duplicate_apps.replace_references(&mut task, Direction::Resolve),
+            Err(SnapshotError::AmbiguousReference(name)) if name == "Phishing"
+        ));
+    }
+
+    #[test]
+    fn test_task_round_trips_through_its_script_and_sidecar() {
+        let dir = std::env::temp_dir().join(format!("swimlane-task-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script_path = dir.join("Enrich URL.py");
+        let mut task = fixtures::task("t1", "Enrich URL", Some("a1"));
+        task.description = Some("Looks up the URL".to_string());
+        task.action.script = Some("print('hello')\n".to_string());
+
+        write_task(&script_path, &task, &references()).unwrap();
+        let sidecar = std::fs::read_to_string(sidecar_path(&script_path)).unwrap();
+        let read = read_task(&script_path, &references()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(sidecar.contains("applicationId: Phishing"));
+        assert!(!sidecar.contains("print"));
+        assert_eq!(read.name, task.name);
+        assert_eq!(read.application_id, task.application_id);
+        assert_eq!(read.description, task.description);
+        assert_eq!(read.action.script, task.action.script);
+    }
+
+    #[tokio::test]
+    async fn test_migrates_tasks_between_memory_backends() {
+        use crate::backend::MemoryBackend;
+        use crate::snapshot::{Snapshot, COMMON_TASKS};
+
+        let task = |name: &str, application: Option<&str>, script: &str| {
+            let mut task = fixtures::task(name, name, application);
+            task.action.script = Some(script.to_string());
+            task
+        };
+        let backend = |name: &str, tasks: Vec<(&str, Vec<Task>)>| {
+            let snapshot = Snapshot {
+                tasks: tasks
+                    .into_iter()
+                    .map(|(folder, tasks)| (folder.to_string(), tasks))
+                    .collect(),
+                ..Default::default()
+            };
+            MemoryBackend::new(name, snapshot)
+        };
+        let source = backend(
+            "source",
+            vec![
+                (
+                    "Phishing",
+                    vec![task("Enrich URL", Some("Phishing"), "new()")],
+                ),
+                (COMMON_TASKS, vec![task("Notify", None, "notify()")]),
+            ],
+        );
+        let target = backend(
+            "target",
+            vec![(
+                "Phishing",
+                vec![
+                    task("Enrich URL", Some("Phishing"), "old()"),
+                    task("Unused", Some("Phishing"), ""),
+                ],
+            )],
+        );
+        let migrator = SwimlaneMigrator::new(source, target, false)
+            .unwrap()
+            .with_allow_destructive(true);
+
+        let plans = migrator.get_tasks_to_migrate().await.unwrap();
+        assert_eq!(plans.len(), 3);
+        assert!(matches!(
+            &plans[0],
+            MigrationPlan::Update { differences, .. } if differences == &vec![Difference::UpdatingScript {
+                field: "action.script".to_string(),
+                current_script: "new()".to_string(),
+                new_script: "old()".to_string(),
+            }]
+        ));
+        assert!(
+            matches!(&plans[1], MigrationPlan::Create { source_resource } if source_resource.name == "Notify")
+        );
+        assert!(
+            matches!(&plans[2], MigrationPlan::Delete { target_resource } if target_resource.name == "Unused")
+        );
+
+        migrator.apply_tasks(plans).await.unwrap();
+
+        let tasks = migrator.to.get_tasks().await.unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert!(migrator.get_tasks_to_migrate().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_task_and_asset_references_are_rewritten_to_the_targets_ids() {
+        let asset = |id: &str, name: &str| Asset {
+            id: id.to_string(),
+            name: name.to_string(),
+        };
+        let source = [
+            fixtures::task("s1", "Enrich", Some("Phishing")),
+            fixtures::task("s2", "Enrich", Some("Malware")),
+        ];
+        let target = [
+            fixtures::task("t1", "Enrich", Some("Phishing")),
+            fixtures::task("t2", "Enrich", Some("Malware")),
+        ];
+        let task_hashmap = task_id_hashmap(&source, &target);
+        let notify = json!({
+            "id": "s3",
+            "name": "Notify",
+            "applicationId": "Malware",
+            "action": {"script": "notify()", "descriptor": {"nextTaskId": "s2"}},
+            "inputMapping": [{"key": "api_key", "type": "asset", "value": "as1"}],
+        });
+        let source_assets = [asset("as1", "VirusTotal")];
+
+        let missing = asset_id_hashmap(&source_assets, &[]);
+        assert!(matches!(
+            adapt_task_payload(&mut notify.clone(), &task_hashmap, &missing),
+            Err(SwimlaneMigratorError::MissingAsset { asset_name }) if asset_name == "VirusTotal"
+        ));
+
+        let asset_hashmap = asset_id_hashmap(&source_assets, &[asset("at1", "virustotal")]);
+        let mut adapted = notify;
+        adapt_task_payload(&mut adapted, &task_hashmap, &asset_hashmap).unwrap();
+        assert_eq!(adapted["id"], "s3");
+        assert_eq!(adapted["action"]["descriptor"]["nextTaskId"], "t2");
+        assert_eq!(adapted["inputMapping"][0]["value"], "at1");
+    }
+
+    #[tokio::test]
+    async fn test_tasks_are_migrated_between_backends_with_assets() {
+        use crate::backend::MemoryBackend;
+        use crate::snapshot::Snapshot;
+
+        let asset = |id: &str, name: &str| Asset {
+            id: id.to_string(),
+            name: name.to_string(),
+        };
+        let backend = |name: &str, tasks: Vec<Task>, assets: Vec<Asset>| {
+            let snapshot = Snapshot {
+                tasks: [("Malware".to_string(), tasks)].into(),
+                ..Default::default()
+            };
+            MemoryBackend::new(name, snapshot).with_assets(assets)
+        };
+        let source = backend(
+            "source",
+            vec![
+                fixtures::task("s2", "Enrich", Some("Malware")),
+                fixtures::task("s3", "Notify", Some("Malware")),
+            ],
+            vec![asset("as1", "VirusTotal")],
+        );
+        let target = backend(
+            "target",
+            vec![fixtures::task("t2", "Enrich", Some("Malware"))],
+            vec![asset("at1", "virustotal")],
+        );
+        let migrator = SwimlaneMigrator::new(source, target, false).unwrap();
+
+        let plans = migrator.get_tasks_to_migrate().await.unwrap();
+        assert_eq!(plans.len(), 1);
+        migrator.apply_tasks(plans).await.unwrap();
+
+        let tasks = migrator.to.get_tasks().await.unwrap();
+        assert!(tasks.iter().any(|task| task.name == "Notify"));
+        assert!(migrator.get_tasks_to_migrate().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_task_references_are_normalised_and_resolved() {
+        let app = phishing(vec![fixtures::text_field("f1", "URL")]);
+        let task = fixtures::task("t1", "Phishing", Some("a1"));
+        let references = TaskReferences::for_task(&[app], &task);
+
+        let normalised = references.normalise(&task).unwrap();
+        assert_eq!(normalised.application_id.as_deref(), Some("Phishing"));
+        assert_eq!(normalised.name, "Phishing");
+
+        let resolved = references.resolve(&normalised).unwrap();
+        assert_eq!(
+            serde_json::to_value(resolved).unwrap(),
+            serde_json::to_value(task).unwrap()
+        );
+    }
+}
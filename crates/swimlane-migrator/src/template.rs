@@ -0,0 +1,400 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use swimlane::apps::Application;
+use swimlane::groups::Group;
+use swimlane::roles::Role;
+use swimlane::tasks::Task;
+use swimlane::users::User;
+use swimlane::workspaces::Workspace;
+use thiserror::Error;
+
+use crate::backend::Backend;
+use crate::tasks::Asset;
+use crate::SwimlaneMigratorError;
+
+const PLACEHOLDER_START: &str = "{{";
+const PLACEHOLDER_END: &str = "}}";
+const PLACEHOLDER_PREFIX: &str = "env.";
+
+/// The keys of the strings which values are replaced in by default
+pub const DEFAULT_KEYS: &[&str] = &["script", "description", "url"];
+
+/// The keys of the strings which values are never replaced in, as resources are matched by them
+const EXCLUDED_KEYS: &[&str] = &["id", "name"];
+
+#[derive(Error, Debug)]
+pub enum TemplateError {
+    #[error("No value was provided for the placeholder '{{{{ env.{0} }}}}'")]
+    UndefinedValue(String),
+    #[error("Could not read the values file")]
+    IoError(#[from] std::io::Error),
+    #[error("Could not parse the values file")]
+    YamlError(#[from] serde_yaml::Error),
+    #[error("Could not substitute the values")]
+    JsonError(#[from] serde_json::Error),
+}
+
+/// Environment-specific values, which are substituted for placeholders such as
+/// `{{ env.SIEM_HOST }}` in the strings of resources
+///
+/// ```yaml
+/// SIEM_HOST: siem.prod.example.com
+/// EMAIL_DOMAIN: example.com
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct TemplateValues {
+    values: BTreeMap<String, String>,
+    /// The keys of the strings which values are replaced in, in addition to `DEFAULT_KEYS`
+    #[serde(skip)]
+    keys: Vec<String>,
+}
+
+impl TemplateValues {
+    pub fn new(values: BTreeMap<String, String>) -> Self {
+        Self {
+            values,
+            keys: vec![],
+        }
+    }
+
+    /// Also replaces values in the strings with these keys. `id` and `name` are always skipped.
+    pub fn with_keys(mut self, keys: Vec<String>) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    fn is_parameterised_key(&self, key: &str) -> bool {
+        !EXCLUDED_KEYS.contains(&key)
+            && (DEFAULT_KEYS.contains(&key) || self.keys.iter().any(|k| k == key))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, TemplateError> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_yaml::from_str(&contents)?)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Replaces each placeholder with its value. Text between braces which doesn't start with
+    /// `env.`, such as a Jinja expression in a task script, is left alone.
+    pub fn render(&self, text: &str) -> Result<String, TemplateError> {
+        let mut rendered = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find(PLACEHOLDER_START) {
+            let after_start = &rest[start + PLACEHOLDER_START.len()..];
+            let Some(end) = after_start.find(PLACEHOLDER_END) else {
+                break;
+            };
+            rendered.push_str(&rest[..start]);
+            match after_start[..end].trim().strip_prefix(PLACEHOLDER_PREFIX) {
+                Some(name) => match self.values.get(name) {
+                    Some(value) => rendered.push_str(value),
+                    None => return Err(TemplateError::UndefinedValue(name.to_string())),
+                },
+                None => {
+                    let placeholder_len = PLACEHOLDER_START.len() + end + PLACEHOLDER_END.len();
+                    rendered.push_str(&rest[start..start + placeholder_len])
+                }
+            }
+            rest = &after_start[end + PLACEHOLDER_END.len()..];
+        }
+        rendered.push_str(rest);
+        Ok(rendered)
+    }
+
+    /// Replaces each occurrence of a value with its placeholder. A value is only replaced where it
+    /// isn't part of a longer word, and where values overlap, the longest is replaced.
+    pub fn parameterise(&self, text: &str) -> String {
+        let mut values: Vec<_> = self
+            .values
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .collect();
+        values.sort_by_key(|(_, value)| std::cmp::Reverse(value.len()));
+
+        let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        let mut parameterised = String::new();
+        let mut previous = None;
+        let mut rest = text;
+        'outer: while let Some(c) = rest.chars().next() {
+            if !is_word(previous) {
+                for (name, value) in &values {
+                    let Some(after) = rest.strip_prefix(value.as_str()) else {
+                        continue;
+                    };
+                    if is_word(after.chars().next()) {
+                        continue;
+                    }
+                    parameterised.push_str(&format!("{{{{ env.{} }}}}", name));
+                    previous = value.chars().last();
+                    rest = after;
+                    continue 'outer;
+                }
+            }
+            parameterised.push(c);
+            previous = Some(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        parameterised
+    }
+
+    /// Renders the placeholders in every string of the resources
+    pub fn render_resources<T: Serialize + DeserializeOwned>(
+        &self,
+        resources: Vec<T>,
+    ) -> Result<Vec<T>, TemplateError> {
+        map_strings(resources, |_| true, |s| self.render(s))
+    }
+
+    /// Parameterises the strings of the resources with one of the keys, such as a task's script.
+    /// Other strings, such as ids, names and email addresses, are left alone.
+    pub fn parameterise_resources<T: Serialize + DeserializeOwned>(
+        &self,
+        resources: Vec<T>,
+    ) -> Result<Vec<T>, TemplateError> {
+        map_strings(
+            resources,
+            |key| self.is_parameterised_key(key),
+            |s| Ok(self.parameterise(s)),
+        )
+    }
+}
+
+/// Maps the strings of the resources whose key is included. Strings in a list have the key of
+/// the list.
+fn map_strings<T: Serialize + DeserializeOwned>(
+    resources: Vec<T>,
+    include: impl Fn(&str) -> bool,
+    f: impl Fn(&str) -> Result<String, TemplateError>,
+) -> Result<Vec<T>, TemplateError> {
+    fn map_value(
+        value: &mut Value,
+        key: &str,
+        include: &impl Fn(&str) -> bool,
+        f: &impl Fn(&str) -> Result<String, TemplateError>,
+    ) -> Result<(), TemplateError> {
+        match value {
+            Value::String(s) if include(key) => *s = f(s)?,
+            Value::Array(items) => {
+                for item in items {
+                    map_value(item, key, include, f)?;
+                }
+            }
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    map_value(value, key, include, f)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    resources
+        .into_iter()
+        .map(|resource| {
+            let mut value = serde_json::to_value(resource)?;
+            map_value(&mut value, "", &include, &f)?;
+            Ok(serde_json::from_value(value)?)
+        })
+        .collect()
+}
+
+/// Substitutes the values of another environment when resources are read from the backend
+///
+/// The values of the backend's environment are first replaced with their placeholders, then
+/// every placeholder is rendered with the values of the target environment. Writes are passed
+/// through unchanged.
+pub struct TemplatedBackend<B: Backend> {
+    inner: B,
+    source_values: TemplateValues,
+    values: TemplateValues,
+}
+
+impl<B: Backend> TemplatedBackend<B> {
+    pub fn new(inner: B, source_values: TemplateValues, values: TemplateValues) -> Self {
+        Self {
+            inner,
+            source_values,
+            values,
+        }
+    }
+
+    fn substitute<T: Serialize + DeserializeOwned>(
+        &self,
+        resources: Vec<T>,
+    ) -> Result<Vec<T>, SwimlaneMigratorError> {
+        let resources = self.source_values.parameterise_resources(resources)?;
+        Ok(self.values.render_resources(resources)?)
+    }
+}
+
+#[async_trait]
+impl<B: Backend> Backend for TemplatedBackend<B> {
+    fn location(&self) -> String {
+        self.inner.location()
+    }
+
+    async fn get_users(&self) -> Result<Vec<User>, SwimlaneMigratorError> {
+        self.substitute(self.inner.get_users().await?)
+    }
+
+    async fn get_groups(&self) -> Result<Vec<Group>, SwimlaneMigratorError> {
+        self.substitute(self.inner.get_groups().await?)
+    }
+
+    async fn get_roles(&self) -> Result<Vec<Role>, SwimlaneMigratorError> {
+        self.substitute(self.inner.get_roles().await?)
+    }
+
+    async fn get_applications(&self) -> Result<Vec<Application>, SwimlaneMigratorError> {
+        self.substitute(self.inner.get_applications().await?)
+    }
+
+    async fn get_raw_applications(&self) -> Result<Vec<Application>, SwimlaneMigratorError> {
+        self.substitute(self.inner.get_raw_applications().await?)
+    }
+
+    async fn get_workspaces(&self) -> Result<Vec<Workspace>, SwimlaneMigratorError> {
+        self.substitute(self.inner.get_workspaces().await?)
+    }
+
+    async fn get_tasks(&self) -> Result<Vec<Task>, SwimlaneMigratorError> {
+        self.substitute(self.inner.get_tasks().await?)
+    }
+
+    async fn get_assets(&self) -> Result<Vec<Asset>, SwimlaneMigratorError> {
+        self.inner.get_assets().await
+    }
+
+    async fn create_user(&self, user: &User) -> Result<(), SwimlaneMigratorError> {
+        self.inner.create_user(user).await
+    }
+
+    async fn update_user(&self, user: &User) -> Result<(), SwimlaneMigratorError> {
+        self.inner.update_user(user).await
+    }
+
+    async fn delete_user(&self, user: &User) -> Result<(), SwimlaneMigratorError> {
+        self.inner.delete_user(user).await
+    }
+
+    async fn create_group(&self, group: &Group) -> Result<(), SwimlaneMigratorError> {
+        self.inner.create_group(group).await
+    }
+
+    async fn create_application(&self, app: &Application) -> Result<(), SwimlaneMigratorError> {
+        self.inner.create_application(app).await
+    }
+
+    async fn update_application(&self, app: &Application) -> Result<(), SwimlaneMigratorError> {
+        self.inner.update_application(app).await
+    }
+
+    async fn resolve_tasks(&self, tasks: Vec<Task>) -> Result<Vec<Task>, SwimlaneMigratorError> {
+        self.inner.resolve_tasks(tasks).await
+    }
+
+    async fn create_task(&self, task: &Task) -> Result<(), SwimlaneMigratorError> {
+        self.inner.create_task(task).await
+    }
+
+    async fn update_task(&self, task: &Task) -> Result<(), SwimlaneMigratorError> {
+        self.inner.update_task(task).await
+    }
+
+    async fn delete_task(&self, task: &Task) -> Result<(), SwimlaneMigratorError> {
+        self.inner.delete_task(task).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(values: &[(&str, &str)]) -> TemplateValues {
+        TemplateValues::new(
+            values
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_render_replaces_placeholders() {
+        let values = values(&[("SIEM_HOST", "siem.prod.example.com")]);
+        assert_eq!(
+            values
+                .render("https://{{ env.SIEM_HOST }}/api {{env.SIEM_HOST}}")
+                .unwrap(),
+            "https://siem.prod.example.com/api siem.prod.example.com"
+        );
+        assert_eq!(
+            values.render("{{ record.name }}").unwrap(),
+            "{{ record.name }}"
+        );
+        assert!(matches!(
+            values.render("{{ env.MISSING }}"),
+            Err(TemplateError::UndefinedValue(name)) if name == "MISSING"
+        ));
+    }
+
+    #[test]
+    fn test_parameterise_prefers_the_longest_value() {
+        let values = values(&[("DOMAIN", "example.com"), ("SIEM_HOST", "siem.example.com")]);
+        let parameterised = values.parameterise("https://siem.example.com, soc@example.com");
+        assert_eq!(
+            parameterised,
+            "https://{{ env.SIEM_HOST }}, soc@{{ env.DOMAIN }}"
+        );
+        assert_eq!(
+            values.render(&parameterised).unwrap(),
+            "https://siem.example.com, soc@example.com"
+        );
+    }
+
+    #[test]
+    fn test_parameterise_only_replaces_whole_words() {
+        let values = values(&[("ENV", "dev")]);
+        assert_eq!(
+            values.parameterise("https://dev.example.com/devices?env=dev"),
+            "https://{{ env.ENV }}.example.com/devices?env={{ env.ENV }}"
+        );
+    }
+
+    #[test]
+    fn test_parameterise_resources_only_replaces_listed_keys() {
+        let values = values(&[("SIEM_HOST", "siem.example.com")])
+            .with_keys(vec!["host".to_string(), "name".to_string()]);
+        let resource = serde_json::json!({
+            "id": "siem.example.com",
+            "name": "siem.example.com",
+            "email": "soc@siem.example.com",
+            "host": "siem.example.com",
+            "action": {"script": "connect('siem.example.com')"},
+            "urls": ["https://siem.example.com"],
+        });
+
+        let parameterised = values.parameterise_resources(vec![resource]).unwrap();
+        assert_eq!(
+            parameterised[0],
+            serde_json::json!({
+                "id": "siem.example.com",
+                "name": "siem.example.com",
+                "email": "soc@siem.example.com",
+                "host": "{{ env.SIEM_HOST }}",
+                "action": {"script": "connect('{{ env.SIEM_HOST }}')"},
+                "urls": ["https://siem.example.com"],
+            })
+        );
+    }
+}
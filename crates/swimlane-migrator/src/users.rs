@@ -1,42 +1,41 @@
 use std::collections::HashMap;
 
-use crate::equality::{Difference, LooksLike};
-use crate::{MigrationPlan, SwimlaneMigrator, SwimlaneMigratorError};
-
-use swimlane::users::{User, UserCreationRequest};
-
-impl LooksLike for User {
-    fn differences(&self, other: &Self) -> Vec<Difference> {
-        let mut diffs = vec![];
-
-        push_difference!(diffs, "display_name", &self.display_name, &other.display_name, optional: true);
-        push_difference!(diffs, "disabled", &self.disabled, &other.disabled);
-        // todo: Convert from ID to name somehow
-        // push_difference!(diffs, "default_workspace_id", &self.default_workspace_id, &other.default_workspace_id, optional: true);
-        // todo: Convert from ID to name somehow
-        // push_difference!(diffs, "default_dashboard_id", &self.default_dashboard_id, &other.default_dashboard_id, optional: true);
-        // todo: Reduce the migrationplan output for avatar as it's a base64encoded string.
-        if self.avatar != other.avatar {
-            diffs.push(Difference::UpdatingComplexField {
-                field: "avatar".to_string(),
-            });
-        }
-        push_difference!(diffs, "timezone_id", &self.timezone_id, &other.timezone_id);
-        push_difference!(diffs, "email", &self.email, &other.email);
-        push_difference!(diffs, "phone_number", &self.phone_number, &other.phone_number, optional: true);
-        push_difference!(diffs, "first_name", &self.first_name, &other.first_name, optional: true);
-        push_difference!(diffs, "middle_initial", &self.middle_initial, &other.middle_initial, optional: true);
-        push_difference!(diffs, "last_name", &self.last_name, &other.last_name, optional: true);
-        push_difference!(diffs, "roles", &self.roles, &other.roles, vec: true);
-        push_difference!(diffs, "groups", &self.groups, &other.groups, vec: true);
-        push_difference!(diffs, "primary_group", &self.primary_group, &other.primary_group, optional: true);
-
-        diffs
-    }
-
-    fn is_same_resource(&self, other: &Self) -> bool {
-        self.user_name == other.user_name
-    }
+use crate::equality::LooksLike;
+use crate::{
+    check_for_conflicts, MigrationPlan, ResourceType, SwimlaneMigrator, SwimlaneMigratorError,
+};
+
+use swimlane::users::User;
+use swimlane::BaseEntity;
+
+#[allow(dead_code)]
+#[derive(LooksLike)]
+#[looks_like(remote = "User")]
+struct UserDef {
+    #[looks_like(identity, skip)]
+    user_name: String,
+    display_name: Option<String>,
+    disabled: bool,
+    // todo: Convert from ID to name somehow
+    #[looks_like(skip)]
+    default_workspace_id: Option<String>,
+    // todo: Convert from ID to name somehow
+    #[looks_like(skip)]
+    default_dashboard_id: Option<String>,
+    // todo: Reduce the migrationplan output for avatar as it's a base64encoded string.
+    #[looks_like(complex)]
+    avatar: Option<String>,
+    timezone_id: String,
+    email: String,
+    phone_number: Option<String>,
+    first_name: Option<String>,
+    middle_initial: Option<String>,
+    last_name: Option<String>,
+    #[looks_like(by_name)]
+    roles: Vec<BaseEntity>,
+    #[looks_like(by_name)]
+    groups: Vec<BaseEntity>,
+    primary_group: Option<BaseEntity>,
 }
 
 impl SwimlaneMigrator {
@@ -46,18 +45,29 @@ impl SwimlaneMigrator {
         let source_users_future = self.from.get_users();
         let target_users_future = self.to.get_users();
 
-        self.get_resources_to_migrate(source_users_future, target_users_future)
+        self.get_resources_to_migrate(ResourceType::User, source_users_future, target_users_future)
             .await
     }
 
     // todo: add argument to auto-create missing groups, roles, apps, dashboards
     // todo: Take an argument of Vec<MigrationPlan> and only migrate those resources?
     pub async fn migrate_users(&self) -> Result<(), SwimlaneMigratorError> {
-        let users_to_migrate = self.get_users_to_migrate();
+        let users_to_migrate = self.get_users_to_migrate().await?;
+        self.apply_users(users_to_migrate).await
+    }
+
+    /// Deletes the users planned for deletion first, to free their licenses, then creates and
+    /// updates the rest with their groups and roles mapped to the target's ids.
+    pub async fn apply_users(
+        &self,
+        users_to_migrate: Vec<MigrationPlan<User>>,
+    ) -> Result<(), SwimlaneMigratorError> {
+        check_for_conflicts(&users_to_migrate)?;
+        self.check_for_destructive(ResourceType::User, &users_to_migrate)?;
+
         let group_id_hashmap = self.get_group_id_hashmap();
         let role_id_hashmap = self.get_role_id_hashmap();
 
-        let users_to_migrate = users_to_migrate.await?;
         let group_id_hashmap = group_id_hashmap.await?;
         let role_id_hashmap = role_id_hashmap.await?;
 
@@ -66,7 +76,7 @@ impl SwimlaneMigrator {
         // Delete users first, in order to ensure enough free licenses.
         for user in &users_to_migrate {
             if let MigrationPlan::Delete { target_resource } = user {
-                self.to.delete_user(&target_resource.id).await?;
+                self.to.delete_user(target_resource).await?;
             }
         }
 
@@ -76,12 +86,12 @@ impl SwimlaneMigrator {
                 MigrationPlan::Create { source_resource } => {
                     let mut adapted_user = source_resource.clone();
                     self.adapt_user(&mut adapted_user, &group_id_hashmap, &role_id_hashmap);
-                    let user_creation_request = UserCreationRequest::from(adapted_user);
-                    self.to.create_user(&user_creation_request).await?;
+                    self.to.create_user(&adapted_user).await?;
                 }
                 MigrationPlan::Update {
                     source_resource,
                     target_resource,
+                    ..
                 } => {
                     let mut adapted_user = source_resource.clone();
                     self.adapt_user(&mut adapted_user, &group_id_hashmap, &role_id_hashmap);
@@ -92,7 +102,12 @@ impl SwimlaneMigrator {
             }
         }
 
-        Ok(())
+        let applied: Vec<&User> = users_to_migrate
+            .iter()
+            .map(MigrationPlan::resource)
+            .collect();
+        self.save_baseline(ResourceType::User, &applied, self.to.get_users())
+            .await
     }
 
     fn adapt_user(
@@ -1,6 +1,9 @@
-use swimlane::error::SwimlaneClientError;
+use serde::de::DeserializeOwned;
 
-use crate::{equality::LooksLike, MigrationPlan, SwimlaneMigrator, SwimlaneMigratorError};
+use crate::{
+    equality::LooksLike, tasks::task_id_hashmap, MigrationPlan, ResourceType, SwimlaneMigrator,
+    SwimlaneMigratorError,
+};
 
 use std::{collections::HashMap, future::Future};
 
@@ -74,31 +77,23 @@ impl SwimlaneMigrator {
         Ok(hashmap)
     }
 
+    /// Returns a hashmap of id to id for all tasks present in both the source and target systems,
+    /// matched by name within the same application
     pub async fn get_task_hashmap(&self) -> Result<HashMap<String, String>, SwimlaneMigratorError> {
-        let source_tasks = self.from.get_tasks_light();
-        let target_tasks = self.to.get_tasks_light();
+        let source_tasks = self.from.get_tasks();
+        let target_tasks = self.to.get_tasks();
 
         let source_tasks = source_tasks.await?;
         let target_tasks = target_tasks.await?;
 
-        let mut hashmap = HashMap::new();
-
-        for source_task in source_tasks {
-            if let Some(target_task) = target_tasks.iter().find(|target_task| {
-                target_task.name.to_lowercase() == source_task.name.to_lowercase()
-            }) {
-                hashmap.insert(source_task.id.clone(), target_task.id.clone());
-            }
-        }
-
-        Ok(hashmap)
+        Ok(task_id_hashmap(&source_tasks, &target_tasks))
     }
 
     pub async fn get_application_hashmap(
         &self,
     ) -> Result<HashMap<String, String>, SwimlaneMigratorError> {
-        let source_applications = self.from.get_applications_light();
-        let target_applications = self.to.get_applications_light();
+        let source_applications = self.from.get_applications();
+        let target_applications = self.to.get_applications();
 
         let source_applications = source_applications.await?;
         let target_applications = target_applications.await?;
@@ -118,26 +113,36 @@ impl SwimlaneMigrator {
         Ok(hashmap)
     }
 
-    pub async fn get_resources_to_migrate<T: LooksLike + Clone, FutSrc, FutDest>(
+    pub async fn get_resources_to_migrate<
+        T: LooksLike + Clone + DeserializeOwned,
+        FutSrc,
+        FutDest,
+        ESrc,
+        EDest,
+    >(
         &self,
+        resource_type: ResourceType,
         source_resource_getter: FutSrc,
         target_resource_getter: FutDest,
     ) -> Result<Vec<MigrationPlan<T>>, SwimlaneMigratorError>
     where
-        FutSrc: Future<Output = Result<Vec<T>, SwimlaneClientError>>,
-        FutDest: Future<Output = Result<Vec<T>, SwimlaneClientError>>,
+        FutSrc: Future<Output = Result<Vec<T>, ESrc>>,
+        FutDest: Future<Output = Result<Vec<T>, EDest>>,
+        SwimlaneMigratorError: From<ESrc> + From<EDest>,
     {
         let source_resources = source_resource_getter.await?;
         let target_resources = target_resource_getter.await?;
 
-        self._get_resources_to_migrate(source_resources, target_resources)
+        self._get_resources_to_migrate(resource_type, source_resources, target_resources)
     }
 
-    pub fn _get_resources_to_migrate<T: LooksLike + Clone>(
+    pub fn _get_resources_to_migrate<T: LooksLike + Clone + DeserializeOwned>(
         &self,
+        resource_type: ResourceType,
         source_resources: Vec<T>,
         target_resources: Vec<T>,
     ) -> Result<Vec<MigrationPlan<T>>, SwimlaneMigratorError> {
+        let rules = self.diff_rules.rules_for(resource_type);
         let mut resources_to_migrate = vec![];
 
         for source_resource in source_resources.clone() {
@@ -145,12 +150,14 @@ impl SwimlaneMigrator {
                 .iter()
                 .find(|target_resource| target_resource.is_same_resource(&source_resource))
             {
-                if source_resource.looks_like(target_resource) {
+                let differences = source_resource.differences_with_rules(target_resource, &rules);
+                if differences.is_empty() {
                     continue;
                 }
                 resources_to_migrate.push(MigrationPlan::Update {
                     source_resource,
                     target_resource: target_resource.clone(),
+                    differences,
                 });
             } else {
                 resources_to_migrate.push(MigrationPlan::Create { source_resource });
@@ -166,6 +173,6 @@ impl SwimlaneMigrator {
             }
         }
 
-        Ok(resources_to_migrate)
+        self.apply_baseline(resource_type, resources_to_migrate, &rules)
     }
 }
@@ -1,25 +1,27 @@
-use std::collections::hash_map;
-
-use crate::equality::{Difference, LooksLike};
+use crate::equality::LooksLike;
 use crate::{MigrationPlan, SwimlaneMigrator, SwimlaneMigratorError};
 use swimlane::workspaces::Workspace;
 
-impl LooksLike for Workspace {
-    fn differences(&self, other: &Self) -> Vec<Difference> {
-        let mut diffs = vec![];
-
-        push_difference!(diffs, "name", &self.name, &other.name);
-        push_difference!(diffs, "description", &self.description, &other.description, optional: true);
-        push_difference!(diffs, "disabled", &self.disabled, &other.disabled);
-        /// TODO : We need to ensure that we conver the list of ids to list of names before performing the comparison.
-        // push_difference!(diffs, "dashboards", &self.dashboards, &other.dashboards);
-        // push_difference!(diffs, "applications", &self.applications, &other.applications);
-        diffs
-    }
+#[allow(dead_code)]
+#[derive(LooksLike)]
+#[looks_like(remote = "Workspace")]
+struct WorkspaceDef {
+    #[looks_like(identity)]
+    name: String,
+    description: Option<String>,
+    disabled: bool,
+    // TODO : We need to ensure that we conver the list of ids to list of names before performing the comparison.
+    #[looks_like(skip)]
+    dashboards: Vec<String>,
+    #[looks_like(skip)]
+    applications: Vec<String>,
+}
 
-    fn is_same_resource(&self, other: &Self) -> bool {
-        self.name == other.name
-    }
+/// Copies the workspaces through their JSON form, as `Workspace` isn't `Clone`
+pub(crate) fn clone_workspaces(workspaces: &[Workspace]) -> Vec<Workspace> {
+    serde_json::to_value(workspaces)
+        .and_then(serde_json::from_value)
+        .expect("workspaces deserialise from their own serialisation")
 }
 
 impl SwimlaneMigrator {
@@ -15,6 +15,10 @@ export default defineConfig({
 				github: 'https://github.com/alex-way/swimlane-cli',
 			},
 			sidebar: [
+				{
+					label: "Configuration",
+					link: "/configuration/"
+				},
 				{
 					label: "CLI Commands",
 					autogenerate: { directory: 'commands' },
@@ -25,6 +29,30 @@ export default defineConfig({
 								{
 									label: "task save",
 									link: "/commands/task/save/"
+								},
+								{
+									label: "task diff",
+									link: "/commands/task/diff/"
+								},
+								{
+									label: "task push",
+									link: "/commands/task/push/"
+								},
+								{
+									label: "task lint",
+									link: "/commands/task/lint/"
+								},
+								{
+									label: "task deps",
+									link: "/commands/task/deps/"
+								},
+								{
+									label: "task run",
+									link: "/commands/task/run/"
+								},
+								{
+									label: "task watch",
+									link: "/commands/task/watch/"
 								}
 							]
 						},
@@ -67,6 +95,56 @@ export default defineConfig({
 								{
 									label: "migrate apps",
 									link: "/commands/migrate/apps/"
+								},
+								{
+									label: "migrate tasks",
+									link: "/commands/migrate/tasks/"
+								}
+							]
+						},
+						{
+							label: "export",
+							link: "/commands/export/"
+						},
+						{
+							label: "apply",
+							link: "/commands/apply/"
+						},
+						{
+							label: "diff",
+							link: "/commands/diff/"
+						},
+						{
+							label: "app",
+							items: [
+								{
+									label: "app export",
+									link: "/commands/app/export/"
+								},
+								{
+									label: "app import",
+									link: "/commands/app/import/"
+								}
+							]
+						},
+						{
+							label: "backup",
+							items: [
+								{
+									label: "backup create",
+									link: "/commands/backup/create/"
+								},
+								{
+									label: "backup list",
+									link: "/commands/backup/list/"
+								},
+								{
+									label: "backup prune",
+									link: "/commands/backup/prune/"
+								},
+								{
+									label: "backup restore",
+									link: "/commands/backup/restore/"
 								}
 							]
 						},